@@ -32,5 +32,8 @@ fn main() {
     println!("cargo:rerun-if-changed=src/key_api.rs");
     println!("cargo:rerun-if-changed=src/record_api.rs");
     println!("cargo:rerun-if-changed=src/tombstone_api.rs");
+    println!("cargo:rerun-if-changed=src/export_api.rs");
+    println!("cargo:rerun-if-changed=src/maintenance_api.rs");
+    println!("cargo:rerun-if-changed=src/key_rotation_api.rs");
     println!("cargo:rerun-if-changed=src/main.rs");
 }
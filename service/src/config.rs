@@ -16,13 +16,122 @@
 
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::str::FromStr;
 
-use crate::defaults::{CONFIG_FILE, DEFAULT_EXPIRED_AGE, DEFAULT_STALE_AGE};
+use crate::defaults::{
+    CONFIG_FILE, DEFAULT_EXPIRED_AGE, DEFAULT_STALE_AGE, MAX_AUTHORS_PER_KEY, MAX_CLOCK_SKEW_SEC,
+    MIN_WEIGHT_FOR_PUT,
+};
+use crate::error::ServiceError;
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ValueEncoding {
+    /// any value is accepted
+    #[default]
+    None,
+    /// printable text, control characters are rejected
+    Utf8,
+    Json,
+    Base64,
+}
+
+impl FromStr for ValueEncoding {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "None" => Ok(ValueEncoding::None),
+            "Utf8" => Ok(ValueEncoding::Utf8),
+            "Json" => Ok(ValueEncoding::Json),
+            "Base64" => Ok(ValueEncoding::Base64),
+            _ => Err(ServiceError::UnknownValueEncoding(s.to_string())),
+        }
+    }
+}
+
+/// Which non-host record is evicted to make room in a full key
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// the lightest record, if the new one is heavier or as heavy and newer
+    #[default]
+    LowestWeight,
+    /// the record created first, if the new one is newer
+    Oldest,
+    /// the record least recently put or read by get_records
+    LeastRecentlyAccessed,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = ServiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "LowestWeight" => Ok(EvictionPolicy::LowestWeight),
+            "Oldest" => Ok(EvictionPolicy::Oldest),
+            "LeastRecentlyAccessed" => Ok(EvictionPolicy::LeastRecentlyAccessed),
+            _ => Err(ServiceError::UnknownEvictionPolicy(s.to_string())),
+        }
+    }
+}
 
 #[derive(Deserialize, Serialize)]
 pub struct Config {
     pub expired_timeout: u64,
     pub stale_timeout: u64,
+    /// encoding record values are required to conform in put_record
+    #[serde(default)]
+    pub value_encoding: ValueEncoding,
+    /// log identities of overwritten records
+    #[serde(default)]
+    pub overwrite_log: bool,
+    /// tolerance for records created in the future in republish_records
+    #[serde(default = "default_max_clock_skew_sec")]
+    pub max_clock_skew_sec: u64,
+    /// records created earlier than this many seconds ago are rejected, 0 means no limit
+    #[serde(default)]
+    pub max_record_age_sec: u64,
+    /// reject signatures not matching the signer key format before verification
+    #[serde(default)]
+    pub strict_signatures: bool,
+    /// use `default_weight` instead of the "no weight" sentinel, such weights aren't verified by trust-graph
+    #[serde(default)]
+    pub default_weight_enabled: bool,
+    #[serde(default)]
+    pub default_weight: u32,
+    /// how update_record makes room in a full key
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicy,
+    /// let get_records delete records of keys that no longer exist
+    #[serde(default)]
+    pub self_heal: bool,
+    /// also accept keys and record metadata signed over the layout without domain tags
+    #[serde(default = "default_legacy_signatures")]
+    pub legacy_signatures: bool,
+    /// records with lighter weights are rejected by puts and skipped by republish
+    #[serde(default = "default_min_weight_for_put")]
+    pub min_weight_for_put: u32,
+    /// reject puts of records without relay_id
+    #[serde(default)]
+    pub require_relay_id: bool,
+    /// puts of new issuers to a key with that many issuers are rejected
+    #[serde(default = "default_max_authors_per_key")]
+    pub max_authors_per_key: u64,
+}
+
+fn default_max_clock_skew_sec() -> u64 {
+    MAX_CLOCK_SKEW_SEC
+}
+
+fn default_legacy_signatures() -> bool {
+    false
+}
+
+fn default_min_weight_for_put() -> u32 {
+    MIN_WEIGHT_FOR_PUT
+}
+
+fn default_max_authors_per_key() -> u64 {
+    MAX_AUTHORS_PER_KEY
 }
 
 pub fn write_config(config: Config) {
@@ -40,6 +149,19 @@ pub fn create_config() {
         write_config(Config {
             expired_timeout: DEFAULT_EXPIRED_AGE,
             stale_timeout: DEFAULT_STALE_AGE,
+            value_encoding: ValueEncoding::default(),
+            overwrite_log: false,
+            max_clock_skew_sec: MAX_CLOCK_SKEW_SEC,
+            max_record_age_sec: 0,
+            strict_signatures: false,
+            default_weight_enabled: false,
+            default_weight: 0,
+            eviction_policy: EvictionPolicy::default(),
+            self_heal: false,
+            legacy_signatures: false,
+            min_weight_for_put: MIN_WEIGHT_FOR_PUT,
+            require_relay_id: false,
+            max_authors_per_key: MAX_AUTHORS_PER_KEY,
         });
     }
 }
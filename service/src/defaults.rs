@@ -16,11 +16,52 @@
 
 pub static KEYS_TABLE_NAME: &str = "keys_table";
 pub static RECORDS_TABLE_NAME: &str = "records_table";
+pub static RECORDS_SEQ_TABLE_NAME: &str = "records_seq_table";
+pub static OVERWRITE_LOG_TABLE_NAME: &str = "overwrite_log_table";
+pub static KEY_ROTATIONS_TABLE_NAME: &str = "key_rotations_table";
+pub static KEY_ACCESS_TABLE_NAME: &str = "key_access_table";
+pub static RECORD_ACCESS_TABLE_NAME: &str = "record_access_table";
+pub static PINNED_KEYS_TABLE_NAME: &str = "pinned_keys_table";
+pub static IMMUTABLE_KEYS_TABLE_NAME: &str = "immutable_keys_table";
+pub static KEY_LABELS_TABLE_NAME: &str = "key_labels_table";
+pub static KEY_ID_COLLISIONS_TABLE_NAME: &str = "key_id_collisions_table";
+pub static SCHEMA_VERSION_TABLE_NAME: &str = "schema_version_table";
 pub static CONFIG_FILE: &str = "/tmp/Config.toml";
 pub static DB_PATH: &str = "/tmp/registry.db";
 pub static DEFAULT_STALE_AGE: u64 = 60 * 60;
 pub static DEFAULT_EXPIRED_AGE: u64 = 24 * 60 * 60;
 pub static RECORDS_LIMIT: usize = 32;
+pub static PUT_RATE_LIMIT: u32 = 100;
+pub static RATE_LIMIT_WINDOW_SEC: u64 = 60;
+pub static OVERWRITE_LOG_LIMIT: u64 = 1024;
+/// version of the storage schema, bumped with every migration in `Storage::migrate`
+pub static SCHEMA_VERSION: u64 = 2;
+/// version of the `export_all` blob format
+pub static EXPORT_SCHEMA_VERSION: u32 = 1;
+pub static MAX_METADATA_BYTES: usize = 1024;
+pub static MAX_VALUE_SIZE_BYTES: usize = 1024;
+/// relay_id and service_id are optional single values, storage keeps only the first id
+pub static MAX_RELAY_IDS: usize = 1;
+pub static MAX_SERVICE_IDS: usize = 1;
+pub static MAX_DISPLAY_LABEL_LEN: usize = 64;
+/// larger `get_global_feed` pages are cut to this size
+pub static MAX_FEED_PAGE_LIMIT: u32 = 100;
+/// ceiling for explicitly set key weights
+pub static MAX_WEIGHT: u32 = 1_000_000;
+/// records of lighter issuers are rejected, 0 accepts any weight
+pub static MIN_WEIGHT_FOR_PUT: u32 = 0;
+/// distinct issuers of records per key, above `RECORDS_LIMIT` so eviction still makes room for new authors
+pub static MAX_AUTHORS_PER_KEY: u64 = 64;
+/// records created this much ahead of local time are still accepted on republish
+pub static MAX_CLOCK_SKEW_SEC: u64 = 60;
+/// tombstones should outlive the records they shadow on every replica
+pub static TOMBSTONE_GRACE_SEC: u64 = 2 * 24 * 60 * 60;
+/// domain tags prefixing signed bytes, so a signature of one type can't be replayed as another
+pub static KEY_SIGNATURE_DOMAIN: &str = "aqua-dht-key-v1";
+pub static RECORD_METADATA_SIGNATURE_DOMAIN: &str = "aqua-dht-record-metadata-v1";
+pub static RECORD_SIGNATURE_DOMAIN: &str = "aqua-dht-record-v1";
+/// module env variable with the bs58 encoded ed25519 secret key `export_key_signed` attests exports with
+pub static HOST_SIGNING_KEY_ENV: &str = "HOST_SIGNING_KEY";
 
 pub static TRUSTED_TIMESTAMP_SERVICE_ID: &str = "peer";
 pub static TRUSTED_TIMESTAMP_FUNCTION_NAME: &str = "timestamp_sec";
@@ -13,6 +13,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::config::ValueEncoding;
 use fluence_keypair::error::DecodingError;
 use marine_sqlite_connector::Error as SqliteError;
 use thiserror::Error as ThisError;
@@ -20,11 +21,7 @@ use thiserror::Error as ThisError;
 #[derive(ThisError, Debug)]
 pub enum ServiceError {
     #[error("Internal Sqlite error: {0}")]
-    SqliteError(
-        #[from]
-        #[source]
-        SqliteError,
-    ),
+    SqliteError(#[source] SqliteError),
     #[error("Requested key {0} does not exist")]
     KeyNotExists(String),
     #[error("Key {0} for {1} peer_id already exists with newer timestamp")]
@@ -104,8 +101,210 @@ pub enum ServiceError {
     MissingRecordWeight(String, String),
     #[error("merge_keys: keys argument is empty")]
     KeysArgumentEmpty,
+    #[error("get_records_by_authors: issued_by argument is empty")]
+    AuthorsArgumentEmpty,
     #[error(
         "Newer record or tombstone for key_id: {0}, issued_by: {1}, peer_id: {2} already exists"
     )]
     NewerRecordOrTombstoneExists(String, String, String),
+    #[error("Record value for key_id {0} is not valid {1:?}")]
+    InvalidValueEncoding(String, ValueEncoding),
+    #[error("Unknown value encoding {0}, expected one of: None, Utf8, Json, Base64")]
+    UnknownValueEncoding(String),
+    #[error("Key {0} is not owned by {1}")]
+    NotKeyOwner(String, String),
+    #[error("Record for key_id {0} is issued by {1}, expected to be issued by the caller")]
+    RecordNotIssuedByCaller(String, String),
+    #[error("No records to agree on a value for key_id {0}")]
+    NoConsensusRecords(String),
+    #[error("Put rate limit for {0} is exceeded, try again later")]
+    RateLimitExceeded(String),
+    #[error("Key prefix should not be empty")]
+    EmptyKeyPrefix,
+    #[error("Peer {0} is not allowed to call admin functions")]
+    NotAdmin(String),
+    #[error("Invalid key rotation signature of {0} by {1}: {2}")]
+    InvalidKeyRotationSignature(
+        String,
+        String,
+        #[source] fluence_keypair::error::VerificationError,
+    ),
+    #[error("Key rotation can't be issued in the future")]
+    InvalidKeyRotationTimestamp,
+    #[error("Peer {0} can't be rotated to itself")]
+    InvalidKeyRotation(String),
+    #[error("Newer key rotation for {0} exists")]
+    NewerKeyRotationExists(String),
+    #[error("Signature of {0} doesn't match {1} key format")]
+    InvalidSignatureFormat(String, String),
+    #[error("Key {0} can't fit {1} more records, only {2} available")]
+    InsufficientCapacity(String, u32, usize),
+    #[error("Weight {0} exceeds the maximum weight {1}")]
+    WeightTooHigh(u32, u32),
+    #[error(
+        "Unknown record field {0}, expected one of: key_id, issued_by, peer_id, timestamp_issued, value, relay_id, service_id, timestamp_created, weight"
+    )]
+    UnknownField(String),
+    #[error("Record metadata for key_id {0} is {1} bytes, exceeds the maximum of {2} bytes")]
+    MetadataTooLarge(String, usize, usize),
+    #[error("Invalid export blob: {0}")]
+    InvalidExportBlob(String),
+    #[error("Record for key_id {0} issued by {1} has changed since it was read")]
+    RecordChangedSince(String, String),
+    #[error("Record with identity {1} not found for key_id {0}")]
+    RecordIdentityNotFound(String, String),
+    #[error("Key {0} already exists")]
+    KeyAlreadyExists(String),
+    #[error("Storage is read-only, only reads are served")]
+    StorageReadOnly,
+    #[error(
+        "Unknown eviction policy {0}, expected one of: LowestWeight, Oldest, LeastRecentlyAccessed"
+    )]
+    UnknownEvictionPolicy(String),
+    #[error("Histogram bucket size should be positive")]
+    InvalidBucketSize,
+    #[error("Export blob schema version {0} is not supported, expected {1}")]
+    IncompatibleExportSchema(u32, u32),
+    #[error("Page limit should be positive")]
+    InvalidPageLimit,
+    #[error("Expected a weight for each of {0} records, got {1}")]
+    WeightsCountMismatch(usize, usize),
+    #[error("Record value is {0} bytes, exceeds the maximum of {1} bytes")]
+    ValueTooLarge(usize, usize),
+    #[error("Record has {1} {0} entries, exceeds the maximum of {2}")]
+    TooManyIds(String, usize, usize),
+    #[error("Host signing key is not configured")]
+    SigningKeyNotConfigured,
+    #[error("Invalid host signing key: {0}")]
+    InvalidSigningKey(String),
+    #[error("Signing failed: {0}")]
+    SigningFailed(String),
+    #[error("Display label is {0} bytes, exceeds the maximum of {1} bytes")]
+    DisplayLabelTooLong(usize, usize),
+    #[error("Timestamp {0} is too far in the future, the latest accepted is {1}")]
+    InvalidTimestampFuture(u64, u64),
+    #[error("Timestamp {0} is too old, the earliest accepted is {1}")]
+    InvalidTimestampTooOld(u64, u64),
+    #[error("Record for key_id {0} issued by {1} was expected to be created at {2}, stored one is created at {3}")]
+    CasConflict(String, String, u64, u64),
+    #[error("Invalid time window: from {0} is after to {1}")]
+    InvalidTimeWindow(u64, u64),
+    #[error("Weight {0} is below the minimum weight {1} to put records")]
+    WeightTooLow(u32, u32),
+    #[error("Record for key_id {0} has no relay_id, it is required on this node")]
+    MissingRelayId(String),
+    #[error("Record with value {2} issued by {1} not found for key_id {0}")]
+    RecordNotFound(String, String, String),
+    #[error("Storage schema version {0} is newer than the supported version {1}")]
+    UnsupportedSchemaVersion(u64, u64),
+    #[error("Key {0} already has records of {1} issuers, new issuers are rejected")]
+    TooManyAuthors(String, u64),
+    #[error("move_records: records argument is empty")]
+    RecordsArgumentEmpty,
+    #[error("Key {0} is immutable, its records can't be swapped")]
+    KeyImmutable(String),
+    #[error("Key {0} has {1} host records, more than the records limit on their own")]
+    HostRecordsLimitExceeded(String, u64),
+}
+
+impl ServiceError {
+    /// Stable numeric code of the error kind to branch on instead of the message, 0 is reserved for success.
+    /// Codes follow the order of variants and are never reused, new variants get the next code
+    pub fn code(&self) -> i64 {
+        use ServiceError::*;
+
+        match self {
+            SqliteError(..) => 1,
+            KeyNotExists(..) => 2,
+            KeyAlreadyExistsNewerTimestamp(..) => 3,
+            ValuesLimitExceeded(..) => 4,
+            HostValueNotFound(..) => 5,
+            InvalidSetHostValueResult => 6,
+            InternalError(..) => 7,
+            InvalidTimestampTetraplet(..) => 8,
+            InvalidSetHostValueTetraplet(..) => 9,
+            InvalidWeightTetraplet(..) => 10,
+            InvalidWeightPeerId(..) => 11,
+            InvalidKeySignature(..) => 12,
+            InvalidRecordMetadataSignature(..) => 13,
+            InvalidRecordSignature(..) => 14,
+            InvalidTombstoneSignature(..) => 15,
+            InvalidKeyTimestamp => 16,
+            InvalidRecordMetadataTimestamp => 17,
+            InvalidRecordTimestamp => 18,
+            RecordAlreadyExpired => 19,
+            InvalidTombstoneTimestamp => 20,
+            RecordsPublishingError => 21,
+            TombstonesPublishingError => 22,
+            PeerIdParseError(..) => 23,
+            PublicKeyExtractionError(..) => 24,
+            PublicKeyDecodeError(..) => 25,
+            MissingRecordWeight(..) => 26,
+            KeysArgumentEmpty => 27,
+            AuthorsArgumentEmpty => 28,
+            NewerRecordOrTombstoneExists(..) => 29,
+            InvalidValueEncoding(..) => 30,
+            UnknownValueEncoding(..) => 31,
+            NotKeyOwner(..) => 32,
+            RecordNotIssuedByCaller(..) => 33,
+            NoConsensusRecords(..) => 34,
+            RateLimitExceeded(..) => 35,
+            EmptyKeyPrefix => 36,
+            NotAdmin(..) => 37,
+            InvalidKeyRotationSignature(..) => 38,
+            InvalidKeyRotationTimestamp => 39,
+            InvalidKeyRotation(..) => 40,
+            NewerKeyRotationExists(..) => 41,
+            InvalidSignatureFormat(..) => 42,
+            InsufficientCapacity(..) => 43,
+            WeightTooHigh(..) => 44,
+            UnknownField(..) => 45,
+            MetadataTooLarge(..) => 46,
+            InvalidExportBlob(..) => 47,
+            RecordChangedSince(..) => 48,
+            RecordIdentityNotFound(..) => 49,
+            KeyAlreadyExists(..) => 50,
+            StorageReadOnly => 51,
+            UnknownEvictionPolicy(..) => 52,
+            InvalidBucketSize => 53,
+            IncompatibleExportSchema(..) => 54,
+            InvalidPageLimit => 55,
+            WeightsCountMismatch(..) => 56,
+            ValueTooLarge(..) => 57,
+            TooManyIds(..) => 58,
+            SigningKeyNotConfigured => 59,
+            InvalidSigningKey(..) => 60,
+            SigningFailed(..) => 61,
+            DisplayLabelTooLong(..) => 62,
+            InvalidTimestampFuture(..) => 63,
+            InvalidTimestampTooOld(..) => 64,
+            CasConflict(..) => 65,
+            InvalidTimeWindow(..) => 66,
+            WeightTooLow(..) => 67,
+            MissingRelayId(..) => 68,
+            RecordNotFound(..) => 69,
+            UnsupportedSchemaVersion(..) => 70,
+            TooManyAuthors(..) => 71,
+            RecordsArgumentEmpty => 72,
+            KeyImmutable(..) => 73,
+            HostRecordsLimitExceeded(..) => 74,
+        }
+    }
+}
+
+/// SQLITE_READONLY primary result code
+const SQLITE_READONLY: isize = 8;
+
+impl From<SqliteError> for ServiceError {
+    fn from(error: SqliteError) -> Self {
+        // extended result codes keep the primary code in the lower byte
+        if error
+            .code
+            .map_or(false, |code| code & 0xff == SQLITE_READONLY)
+        {
+            ServiceError::StorageReadOnly
+        } else {
+            ServiceError::SqliteError(error)
+        }
+    }
 }
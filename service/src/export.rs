@@ -0,0 +1,100 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::defaults::EXPORT_SCHEMA_VERSION;
+use crate::error::ServiceError;
+use crate::key::{Key, KeyInternal};
+use crate::record::{Record, RecordInternal};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedKey {
+    pub key: Key,
+    pub weight: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedRecord {
+    pub record: Record,
+    pub weight: u32,
+}
+
+/// Keys and actual records of the storage, serialized as JSON by `export_all`
+#[derive(Serialize, Deserialize)]
+pub struct ExportBlob {
+    pub schema_version: u32,
+    pub keys: Vec<ExportedKey>,
+    pub records: Vec<ExportedRecord>,
+}
+
+impl ExportBlob {
+    pub fn new(keys: Vec<KeyInternal>, records: Vec<RecordInternal>) -> Self {
+        Self {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            keys: keys
+                .into_iter()
+                .map(|k| ExportedKey {
+                    key: k.key,
+                    weight: k.weight,
+                })
+                .collect(),
+            records: records
+                .into_iter()
+                .map(|r| ExportedRecord {
+                    record: r.record,
+                    weight: r.weight,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_bytes(blob: &[u8]) -> Result<Self, ServiceError> {
+        serde_json::from_slice(blob).map_err(|e| ServiceError::InvalidExportBlob(e.to_string()))
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ServiceError> {
+        serde_json::to_vec(self).map_err(|e| ServiceError::InternalError(e.to_string()))
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        self.schema_version == EXPORT_SCHEMA_VERSION
+    }
+
+    /// Verify every key and record, return (valid, invalid) counts
+    pub fn verify(&self, current_timestamp_sec: u64) -> (u64, u64) {
+        let results = self
+            .keys
+            .iter()
+            .map(|k| k.key.verify(current_timestamp_sec))
+            .chain(
+                self.records
+                    .iter()
+                    .map(|r| r.record.verify(current_timestamp_sec)),
+            );
+
+        let mut valid = 0u64;
+        let mut invalid = 0u64;
+        for result in results {
+            if result.is_ok() {
+                valid += 1;
+            } else {
+                invalid += 1;
+            }
+        }
+
+        (valid, invalid)
+    }
+}
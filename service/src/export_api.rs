@@ -0,0 +1,196 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use crate::defaults::{EXPORT_SCHEMA_VERSION, HOST_SIGNING_KEY_ENV};
+use crate::error::ServiceError;
+use crate::export::ExportBlob;
+use crate::key::KeyInternal;
+use crate::misc::check_admin;
+use crate::record::RecordInternal;
+use crate::results::{ExportResult, ImportResult, SignedExportResult, ValidateImportResult};
+use crate::storage_impl::get_storage;
+use crate::tetraplets_checkers::check_timestamp_tetraplets;
+use crate::{load_config, wrapped_try};
+use fluence_keypair::{KeyFormat, KeyPair};
+use marine_rs_sdk::marine;
+use sha2::{Digest, Sha256};
+
+/// Export all keys and actual records as a blob. Can be called only by the host or the service creator
+#[marine]
+pub fn export_all(current_timestamp_sec: u64) -> ExportResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        check_admin(&call_parameters)?;
+
+        let storage = get_storage()?;
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let records = storage
+            .get_all_records(0, 0)?
+            .into_iter()
+            .map(|(_, r)| r)
+            .filter(|r| {
+                r.record.timestamp_created > expired_timestamp
+                    && (r.record.expires_at == 0 || r.record.expires_at > current_timestamp_sec)
+            })
+            .collect();
+
+        ExportBlob::new(storage.get_all_keys()?, records).to_bytes()
+    })
+    .into()
+}
+
+/// Export the key with its actual records, attested by the host signing key at `current_timestamp_sec`.
+/// The key is taken from the `HOST_SIGNING_KEY` env of the module set in the deployment config,
+/// so it never travels in particles and isn't kept in the service storage
+#[marine]
+pub fn export_key_signed(key_id: String, current_timestamp_sec: u64) -> SignedExportResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let signing_key = std::env::var(HOST_SIGNING_KEY_ENV)
+            .map_err(|_| ServiceError::SigningKeyNotConfigured)?;
+        let secret_key = bs58::decode(signing_key)
+            .into_vec()
+            .map_err(|e| ServiceError::InvalidSigningKey(e.to_string()))?;
+        let keypair = KeyPair::from_secret_key(secret_key, KeyFormat::Ed25519)
+            .map_err(|e| ServiceError::InvalidSigningKey(e.to_string()))?;
+
+        let storage = get_storage()?;
+        let key = storage.get_internal_key(key_id.clone())?;
+        let records = storage.get_records(key_id, current_timestamp_sec)?;
+        let blob = ExportBlob::new(vec![key], records).to_bytes()?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(current_timestamp_sec.to_be_bytes());
+        hasher.update(&blob);
+        let digest = hasher.finalize().to_vec();
+        let signature = keypair
+            .sign(&digest)
+            .map_err(|e| ServiceError::SigningFailed(e.to_string()))?
+            .to_vec()
+            .to_vec();
+
+        Ok((
+            blob,
+            current_timestamp_sec,
+            digest,
+            signature,
+            keypair.get_peer_id().to_base58(),
+        ))
+    })
+    .into()
+}
+
+/// Parse an `export_all` blob and verify all its keys and records without writing anything.
+/// Blobs of other schema versions are reported as incompatible and not verified
+#[marine]
+pub fn validate_import(blob: Vec<u8>, current_timestamp_sec: u64) -> ValidateImportResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let blob = ExportBlob::from_bytes(&blob)?;
+        if !blob.is_compatible() {
+            return Ok((0, 0, false));
+        }
+
+        let (valid, invalid) = blob.verify(current_timestamp_sec);
+        Ok((valid, invalid, true))
+    })
+    .into()
+}
+
+/// Import keys and records of an `export_all` blob, items failing verification are counted and skipped.
+/// With `only_existing_keys` no new keys are created, records of keys absent locally are skipped and counted.
+/// Weights are taken from the blob as is, so it can be called only by the host or the service creator
+#[marine]
+pub fn import_all(
+    blob: Vec<u8>,
+    only_existing_keys: bool,
+    current_timestamp_sec: u64,
+) -> ImportResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        check_admin(&call_parameters)?;
+        let blob = ExportBlob::from_bytes(&blob)?;
+        if !blob.is_compatible() {
+            return Err(ServiceError::IncompatibleExportSchema(
+                blob.schema_version,
+                EXPORT_SCHEMA_VERSION,
+            ));
+        }
+
+        let storage = get_storage()?;
+        storage.transaction(|storage| {
+            let mut imported_keys = 0u64;
+            let mut invalid = 0u64;
+            for exported in blob.keys.into_iter() {
+                if exported.key.verify(current_timestamp_sec).is_err() {
+                    invalid += 1;
+                    continue;
+                }
+                if only_existing_keys && storage.check_key_existence(&exported.key.id).is_err() {
+                    continue;
+                }
+
+                match storage.update_key(KeyInternal {
+                    key: exported.key,
+                    timestamp_published: 0,
+                    weight: exported.weight,
+                }) {
+                    Ok(()) => imported_keys += 1,
+                    // local key is newer
+                    Err(ServiceError::KeyAlreadyExistsNewerTimestamp(_, _)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+
+            let mut records_by_key: HashMap<String, Vec<RecordInternal>> = HashMap::new();
+            let mut skipped = 0u64;
+            for exported in blob.records.into_iter() {
+                if exported.record.verify(current_timestamp_sec).is_err() {
+                    invalid += 1;
+                    continue;
+                }
+                let key_id = exported.record.metadata.key_id.clone();
+                if storage.check_key_existence(&key_id).is_err() {
+                    skipped += 1;
+                    continue;
+                }
+
+                records_by_key
+                    .entry(key_id)
+                    .or_default()
+                    .push(RecordInternal {
+                        record: exported.record,
+                        weight: exported.weight,
+                    });
+            }
+
+            let mut imported_records = 0u64;
+            for (key_id, records) in records_by_key.into_iter() {
+                imported_records += records.len() as u64;
+                storage.merge_and_update_records(key_id, records, current_timestamp_sec)?;
+            }
+
+            Ok((imported_keys, imported_records, skipped, invalid))
+        })
+    })
+    .into()
+}
@@ -14,14 +14,16 @@
  * limitations under the License.
  */
 
+use crate::defaults::KEY_SIGNATURE_DOMAIN;
 use crate::error::ServiceError;
-use crate::misc::extract_public_key;
+use crate::misc::{extract_public_key, push_field, verify_with_legacy};
 use fluence_keypair::Signature;
 use marine_rs_sdk::marine;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
 #[marine]
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Key {
     /// base58-encoded sha256(concat(label, owner_peer_id))
     pub id: String,
@@ -78,6 +80,21 @@ impl Key {
 
     pub fn signature_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
+        push_field(&mut bytes, KEY_SIGNATURE_DOMAIN.as_bytes());
+        push_field(&mut bytes, self.label.as_bytes());
+        push_field(&mut bytes, self.owner_peer_id.as_bytes());
+        bytes.extend(self.timestamp_created.to_le_bytes());
+        push_field(&mut bytes, &self.challenge);
+        push_field(&mut bytes, self.challenge_type.as_bytes());
+
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        hasher.finalize().to_vec()
+    }
+
+    /// Layout without the domain tag and with one byte lengths, accepted only if legacy signatures are enabled
+    pub fn legacy_signature_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
 
         bytes.push(self.label.len() as u8);
         bytes.extend(self.label.as_bytes());
@@ -110,7 +127,7 @@ impl Key {
         let pk = extract_public_key(self.owner_peer_id.clone())?;
         let bytes = self.signature_bytes();
         let signature = Signature::from_bytes(pk.get_key_format(), self.signature.clone());
-        pk.verify(&bytes, &signature).map_err(|e| {
+        verify_with_legacy(&pk, &signature, &bytes, || self.legacy_signature_bytes()).map_err(|e| {
             ServiceError::InvalidKeySignature(
                 self.id.clone(),
                 self.label.clone(),
@@ -13,14 +13,20 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::defaults::{MAX_DISPLAY_LABEL_LEN, MAX_WEIGHT};
 use crate::error::ServiceError;
 use crate::key::{Key, KeyInternal};
-use crate::misc::check_weight_result;
-use crate::results::{GetKeyMetadataResult, MergeKeysResult, RegisterKeyResult, RegistryResult};
+use crate::misc::{check_signature_format, check_weight_result, resolve_weight};
+use crate::results::{
+    CollisionResult, CountResult, GetKeyMetadataResult, GetKeysResult, KeyExistenceResult,
+    KeyIdCollision, KeysMetadataResult, MergeKeysResult, OwnerResult, RegisterKeyResult,
+    RegistryResult, RemoveKeysResult, RepublishKeysResult,
+};
 use crate::storage_impl::get_storage;
 use crate::tetraplets_checkers::{check_timestamp_tetraplets, check_weight_tetraplets};
-use crate::{wrapped_try, WeightResult};
+use crate::{load_config, wrapped_try, WeightResult};
 use marine_rs_sdk::marine;
+use std::collections::{BTreeMap, HashMap};
 
 #[marine]
 pub fn get_key_bytes(
@@ -62,13 +68,15 @@ pub fn register_key(
 ) -> RegisterKeyResult {
     wrapped_try(|| {
         let call_parameters = marine_rs_sdk::get_call_parameters();
-        check_weight_tetraplets(&call_parameters, 6, 0)?;
         check_timestamp_tetraplets(&call_parameters, 7)?;
         let owner_peer_id = owner_peer_id
             .get(0)
             .unwrap_or(&call_parameters.init_peer_id)
             .clone();
-        check_weight_result(&owner_peer_id, &weight)?;
+        let weight = resolve_weight(&call_parameters, 6, &owner_peer_id, &weight)?;
+        if load_config().strict_signatures {
+            check_signature_format(&owner_peer_id, &signature)?;
+        }
         let key = Key::new(
             label,
             owner_peer_id,
@@ -80,7 +88,6 @@ pub fn register_key(
         key.verify(current_timestamp_sec)?;
 
         let key_id = key.id.clone();
-        let weight = weight.weight;
         let storage = get_storage()?;
         storage.update_key(KeyInternal {
             key,
@@ -93,9 +100,133 @@ pub fn register_key(
     .into()
 }
 
+/// Return the stored key without its records, so its signature can be verified independently.
+/// Counts as an access of the key like `get_records`, the display label set by the owner is returned too
+#[marine]
+pub fn get_key_metadata(key_id: String, current_timestamp_sec: u64) -> GetKeyMetadataResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        match storage.register_key_access(&key_id, current_timestamp_sec) {
+            // access tracking shouldn't break reads
+            Err(ServiceError::StorageReadOnly) => {}
+            other => other?,
+        }
+        let display_label = storage.get_key_display_label(&key_id)?;
+        storage.get_key(key_id).map(|key| (key, display_label))
+    })
+    .into()
+}
+
+/// Set a human-readable label of the key for operator tooling, an empty label removes it.
+/// It doesn't affect the key id, only the key owner can set it
+#[marine]
+pub fn set_key_display_label(key_id: String, display_label: String) -> RegistryResult {
+    wrapped_try(|| {
+        if display_label.len() > MAX_DISPLAY_LABEL_LEN {
+            return Err(ServiceError::DisplayLabelTooLong(
+                display_label.len(),
+                MAX_DISPLAY_LABEL_LEN,
+            ));
+        }
+
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        let storage = get_storage()?;
+        storage.check_key_owner(&key_id, &call_parameters.init_peer_id)?;
+        storage.set_key_display_label(&key_id, display_label)
+    })
+    .into()
+}
+
+/// Pin or unpin the key, pinned keys are never removed by `clear_stale_keys` and `clear_expired`.
+/// Only the key owner can change it, the key counts as accessed
+#[marine]
+pub fn set_key_pinned(key_id: String, pinned: bool, current_timestamp_sec: u64) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_owner(&key_id, &call_parameters.init_peer_id)?;
+        storage.set_key_pinned(&key_id, pinned)?;
+        storage.register_key_access(&key_id, current_timestamp_sec)
+    })
+    .into()
+}
+
+/// Make the key immutable, so `cas_record` can't swap its records. It can't be reverted,
+/// only the key owner can do it and the key counts as accessed
+#[marine]
+pub fn set_key_immutable(key_id: String, current_timestamp_sec: u64) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_owner(&key_id, &call_parameters.init_peer_id)?;
+        storage.set_key_immutable(&key_id, true)?;
+        storage.register_key_access(&key_id, current_timestamp_sec)
+    })
+    .into()
+}
+
+/// Return whether each of `key_ids` exists, aligned with the input order.
+/// Doesn't count as an access of the keys
 #[marine]
-pub fn get_key_metadata(key_id: String) -> GetKeyMetadataResult {
-    wrapped_try(|| get_storage()?.get_key(key_id)).into()
+pub fn check_keys_existence(key_ids: Vec<String>) -> KeyExistenceResult {
+    wrapped_try(|| {
+        let existing = get_storage()?.get_existing_key_ids(&key_ids)?;
+        Ok(key_ids.iter().map(|id| existing.contains(id)).collect())
+    })
+    .into()
+}
+
+/// Return up to `limit` keys (`0` means all) whose records were read by `get_records` within the stale timeout,
+/// the most recently accessed first, keys accessed at the same time are ordered by read count
+#[marine]
+pub fn get_hot_keys(limit: u32, current_timestamp_sec: u64) -> GetKeysResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let since = current_timestamp_sec.saturating_sub(load_config().stale_timeout);
+        get_storage()?.get_hot_keys(limit, since)
+    })
+    .into()
+}
+
+/// Return metadata of `key_ids` in one call, `found[i]` is false and `keys[i]` is empty if `key_ids[i]` doesn't exist
+#[marine]
+pub fn get_keys_metadata(key_ids: Vec<String>, current_timestamp_sec: u64) -> KeysMetadataResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        if key_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let keys: HashMap<String, Key> = get_storage()?
+            .get_keys(&key_ids, current_timestamp_sec)?
+            .into_iter()
+            .map(|key| (key.id.clone(), key))
+            .collect();
+
+        Ok(key_ids
+            .iter()
+            .map(|key_id| keys.get(key_id).cloned())
+            .collect())
+    })
+    .into()
+}
+
+/// Check whether the key exists and is owned by `peer_id`
+#[marine]
+pub fn is_key_owner(key_id: String, peer_id: String) -> OwnerResult {
+    wrapped_try(|| match get_storage()?.get_key(key_id) {
+        Ok(key) => Ok((true, key.owner_peer_id == peer_id)),
+        Err(ServiceError::KeyNotExists(_)) => Ok((false, false)),
+        Err(error) => Err(error),
+    })
+    .into()
 }
 
 /// Used for replication, same as register_key, updates timestamp_accessed
@@ -129,6 +260,150 @@ pub fn republish_key(
     .into()
 }
 
+/// Batch counterpart of `republish_key` for replication: each key is verified and stored unless a version
+/// with the same or greater `timestamp_created` is already stored. Keys keep their local weight (0 for new keys),
+/// pinning and display labels. Returns the number of stored keys
+#[marine]
+pub fn republish_keys(keys: Vec<Key>, current_timestamp_sec: u64) -> RepublishKeysResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.transaction(|storage| {
+            let mut updated = 0u64;
+            for mut key in keys.into_iter() {
+                key.verify(current_timestamp_sec)?;
+                key.id = Key::get_id(&key.label, &key.owner_peer_id);
+
+                let weight = match storage.get_internal_key(key.id.clone()) {
+                    Ok(existing) if existing.key.timestamp_created >= key.timestamp_created => {
+                        continue
+                    }
+                    Ok(existing) => existing.weight,
+                    Err(ServiceError::KeyNotExists(_)) => 0,
+                    Err(err) => return Err(err),
+                };
+                storage.write_key(KeyInternal {
+                    key,
+                    timestamp_published: current_timestamp_sec,
+                    weight,
+                })?;
+                updated += 1;
+            }
+
+            Ok(updated)
+        })
+    })
+    .into()
+}
+
+/// Set weight of the key owned by the caller, `weight` should be the owner's weight not exceeding `MAX_WEIGHT`
+#[marine]
+pub fn set_key_weight(
+    key_id: String,
+    weight: WeightResult,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_weight_tetraplets(&call_parameters, 1, 0)?;
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_owner(&key_id, &call_parameters.init_peer_id)?;
+        check_weight_result(&call_parameters.init_peer_id, &weight)?;
+        if weight.weight > MAX_WEIGHT {
+            return Err(ServiceError::WeightTooHigh(weight.weight, MAX_WEIGHT));
+        }
+
+        storage.set_key_weight(&key_id, weight.weight, current_timestamp_sec)
+    })
+    .into()
+}
+
+/// Replace the caller's key `old_label` with a key `new_label` signed by `new_signature`, challenge, pinning
+/// and display label are kept. Records of the old key are moved under the new key id but are still signed
+/// for the old key id, so they fail verification on other peers until their issuers put them again for the new key
+#[marine]
+pub fn rename_key(
+    old_label: String,
+    new_label: String,
+    new_signature: Vec<u8>,
+    timestamp_created: u64,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 4)?;
+        let owner_peer_id = call_parameters.init_peer_id;
+        let storage = get_storage()?;
+        let old_key = storage.get_internal_key(Key::get_id(&old_label, &owner_peer_id))?;
+        let new_key = Key::new(
+            new_label,
+            owner_peer_id,
+            timestamp_created,
+            old_key.key.challenge.clone(),
+            old_key.key.challenge_type.clone(),
+            new_signature,
+        );
+        new_key.verify(current_timestamp_sec)?;
+        if storage.check_key_existence(&new_key.id).is_ok() {
+            return Err(ServiceError::KeyAlreadyExists(new_key.id));
+        }
+
+        storage.transaction(|storage| {
+            // deleting the old key clears its pinning, immutability and display label
+            let pinned = storage.is_key_pinned(&old_key.key.id)?;
+            let immutable = storage.is_key_immutable(&old_key.key.id)?;
+            let display_label = storage.get_key_display_label(&old_key.key.id)?;
+            storage.rehome_records(&old_key.key.id, &new_key.id)?;
+            storage.delete_key(old_key.key.id.clone())?;
+            storage.write_key(KeyInternal {
+                key: new_key.clone(),
+                timestamp_published: old_key.timestamp_published,
+                weight: old_key.weight,
+            })?;
+            storage.set_key_pinned(&new_key.id, pinned)?;
+            storage.set_key_immutable(&new_key.id, immutable)?;
+            storage.set_key_display_label(&new_key.id, display_label)
+        })
+    })
+    .into()
+}
+
+/// Return the number of stored keys, cheaper than fetching them
+#[marine]
+pub fn count_keys() -> CountResult {
+    wrapped_try(|| get_storage()?.count_keys()).into()
+}
+
+/// Remove all keys of the caller whose label starts with `prefix`, along with their records.
+/// Pinned keys are skipped unless `force` is set
+#[marine]
+pub fn remove_keys_by_prefix(
+    prefix: String,
+    force: bool,
+    current_timestamp_sec: u64,
+) -> RemoveKeysResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        if prefix.is_empty() {
+            return Err(ServiceError::EmptyKeyPrefix);
+        }
+
+        let storage = get_storage()?;
+        storage.transaction(|storage| {
+            storage.remove_keys_by_prefix(
+                &prefix,
+                &call_parameters.init_peer_id,
+                force,
+                current_timestamp_sec,
+            )
+        })
+    })
+    .into()
+}
+
 /// merge key and return the latest
 #[marine]
 pub fn merge_keys(keys: Vec<Key>) -> MergeKeysResult {
@@ -137,3 +412,46 @@ pub fn merge_keys(keys: Vec<Key>) -> MergeKeysResult {
         .ok_or(ServiceError::KeysArgumentEmpty)
         .into()
 }
+
+/// Diagnostic: report key ids that distinct (label, owner_peer_id) pairs of keys created up to `current_timestamp_sec` map to.
+/// `get_key_id` hashes the plain concatenation, so e.g. ("ab", "c") and ("a", "bc") collide. Keys replaced by
+/// a colliding key are recorded on write and reported along with the key currently stored under their id
+#[marine]
+pub fn find_key_id_collisions(current_timestamp_sec: u64) -> CollisionResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        let storage = get_storage()?;
+
+        let mut keys_by_id: BTreeMap<String, Vec<Key>> = BTreeMap::new();
+        for key in storage.get_collided_keys()?.into_iter() {
+            keys_by_id.entry(key.id.clone()).or_default().push(key);
+        }
+
+        let mut collisions: Vec<KeyIdCollision> = vec![];
+        for (key_id, mut collided) in keys_by_id.into_iter() {
+            match storage.get_key(key_id.clone()) {
+                Ok(key) => collided.push(key),
+                Err(ServiceError::KeyNotExists(_)) => {}
+                Err(err) => return Err(err),
+            }
+
+            let mut keys: Vec<Key> = vec![];
+            for key in collided.into_iter() {
+                if key.timestamp_created <= current_timestamp_sec
+                    && !keys
+                        .iter()
+                        .any(|k| k.label == key.label && k.owner_peer_id == key.owner_peer_id)
+                {
+                    keys.push(key);
+                }
+            }
+            if keys.len() > 1 {
+                collisions.push(KeyIdCollision { key_id, keys });
+            }
+        }
+
+        Ok(collisions)
+    })
+    .into()
+}
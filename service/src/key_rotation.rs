@@ -0,0 +1,81 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::error::ServiceError;
+use crate::misc::extract_public_key;
+use fluence_keypair::Signature;
+use marine_rs_sdk::marine;
+use sha2::{Digest, Sha256};
+
+/// Announcement that `old_peer_id` identity is now served by `new_peer_id` key
+#[marine]
+#[derive(Debug, Default, Clone)]
+pub struct KeyRotation {
+    /// rotated peer id in base58
+    pub old_peer_id: String,
+    /// peer id of the new key in base58
+    pub new_peer_id: String,
+    /// timestamp in seconds
+    pub timestamp_issued: u64,
+    /// encoded and hashed previous fields signed by `old_peer_id`
+    pub old_signature: Vec<u8>,
+    /// encoded and hashed previous fields signed by `new_peer_id`
+    pub new_signature: Vec<u8>,
+}
+
+impl KeyRotation {
+    pub fn signature_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.push(self.old_peer_id.len() as u8);
+        bytes.extend(self.old_peer_id.as_bytes());
+
+        bytes.push(self.new_peer_id.len() as u8);
+        bytes.extend(self.new_peer_id.as_bytes());
+
+        bytes.extend(self.timestamp_issued.to_le_bytes());
+
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        hasher.finalize().to_vec()
+    }
+
+    fn verify_signature(&self, peer_id: &str, signature: &[u8]) -> Result<(), ServiceError> {
+        let pk = extract_public_key(peer_id.to_string())?;
+        let bytes = self.signature_bytes();
+        let signature = Signature::from_bytes(pk.get_key_format(), signature.to_vec());
+        pk.verify(&bytes, &signature).map_err(|e| {
+            ServiceError::InvalidKeyRotationSignature(
+                self.old_peer_id.clone(),
+                peer_id.to_string(),
+                e,
+            )
+        })
+    }
+
+    /// Rotation is accepted only if signed by both old and new keys
+    pub fn verify(&self, current_timestamp_sec: u64) -> Result<(), ServiceError> {
+        if self.timestamp_issued > current_timestamp_sec {
+            return Err(ServiceError::InvalidKeyRotationTimestamp);
+        }
+
+        if self.old_peer_id == self.new_peer_id {
+            return Err(ServiceError::InvalidKeyRotation(self.old_peer_id.clone()));
+        }
+
+        self.verify_signature(&self.old_peer_id, &self.old_signature)?;
+        self.verify_signature(&self.new_peer_id, &self.new_signature)
+    }
+}
@@ -0,0 +1,74 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::key_rotation::KeyRotation;
+use crate::results::{RegistryResult, RotationResult};
+use crate::storage_impl::get_storage;
+use crate::tetraplets_checkers::check_timestamp_tetraplets;
+use crate::wrapped_try;
+use marine_rs_sdk::marine;
+
+#[marine]
+pub fn get_key_rotation_bytes(
+    old_peer_id: String,
+    new_peer_id: String,
+    timestamp_issued: u64,
+) -> Vec<u8> {
+    KeyRotation {
+        old_peer_id,
+        new_peer_id,
+        timestamp_issued,
+        ..Default::default()
+    }
+    .signature_bytes()
+}
+
+/// Announce that `old_peer_id` is rotated to `new_peer_id`, both keys should sign `get_key_rotation_bytes`
+#[marine]
+pub fn add_key_rotation(
+    old_peer_id: String,
+    new_peer_id: String,
+    timestamp_issued: u64,
+    old_signature: Vec<u8>,
+    new_signature: Vec<u8>,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let cp = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&cp, 5)?;
+        let rotation = KeyRotation {
+            old_peer_id,
+            new_peer_id,
+            timestamp_issued,
+            old_signature,
+            new_signature,
+        };
+        rotation.verify(current_timestamp_sec)?;
+
+        get_storage()?.write_key_rotation(rotation)
+    })
+    .into()
+}
+
+/// Resolve the current peer id of `peer_id` identity following stored rotations
+#[marine]
+pub fn get_key_rotation(peer_id: String) -> RotationResult {
+    wrapped_try(|| {
+        let current = get_storage()?.resolve_key_rotation(peer_id.clone())?;
+        let rotated = current != peer_id;
+        Ok((current, rotated))
+    })
+    .into()
+}
@@ -0,0 +1,99 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::defaults::KEY_ROTATIONS_TABLE_NAME;
+use crate::error::ServiceError;
+use crate::key_rotation::KeyRotation;
+use crate::storage_impl::Storage;
+use marine_sqlite_connector::{State, Statement, Value};
+
+pub fn read_key_rotation(statement: &Statement) -> Result<KeyRotation, ServiceError> {
+    Ok(KeyRotation {
+        old_peer_id: statement.read::<String>(0)?,
+        new_peer_id: statement.read::<String>(1)?,
+        timestamp_issued: statement.read::<i64>(2)? as u64,
+        old_signature: statement.read::<Vec<u8>>(3)?,
+        new_signature: statement.read::<Vec<u8>>(4)?,
+    })
+}
+
+impl Storage {
+    pub fn create_key_rotations_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {KEY_ROTATIONS_TABLE_NAME} (
+                old_peer_id TEXT PRIMARY KEY,
+                new_peer_id TEXT,
+                timestamp_issued INTEGER NOT NULL,
+                old_signature BLOB NOT NULL,
+                new_signature BLOB NOT NULL
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_key_rotations_table error: {}", error);
+        }
+    }
+
+    pub fn get_key_rotation(&self, old_peer_id: &str) -> Result<Option<KeyRotation>, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT old_peer_id, new_peer_id, timestamp_issued, old_signature, new_signature \
+             FROM {KEY_ROTATIONS_TABLE_NAME} WHERE old_peer_id = ?"
+        ))?;
+        statement.bind(1, &Value::String(old_peer_id.to_string()))?;
+
+        if let State::Row = statement.next()? {
+            read_key_rotation(&statement).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// insert rotation of `old_peer_id` if it does not exist or replace if it has lower `timestamp_issued`
+    pub fn write_key_rotation(&self, rotation: KeyRotation) -> Result<(), ServiceError> {
+        if let Some(existing) = self.get_key_rotation(&rotation.old_peer_id)? {
+            if existing.timestamp_issued > rotation.timestamp_issued {
+                return Err(ServiceError::NewerKeyRotationExists(rotation.old_peer_id));
+            }
+        }
+
+        let mut statement = self.connection.prepare(f!(
+            "INSERT OR REPLACE INTO {KEY_ROTATIONS_TABLE_NAME} VALUES (?, ?, ?, ?, ?);"
+        ))?;
+        statement.bind(1, &Value::String(rotation.old_peer_id))?;
+        statement.bind(2, &Value::String(rotation.new_peer_id))?;
+        statement.bind(3, &Value::Integer(rotation.timestamp_issued as i64))?;
+        statement.bind(4, &Value::Binary(rotation.old_signature))?;
+        statement.bind(5, &Value::Binary(rotation.new_signature))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    /// Follow rotations starting from `peer_id` and return the latest peer id of the identity
+    pub fn resolve_key_rotation(&self, peer_id: String) -> Result<String, ServiceError> {
+        let mut visited = vec![peer_id.clone()];
+        let mut current = peer_id;
+        while let Some(rotation) = self.get_key_rotation(&current)? {
+            // stop on cycles like A -> B -> A
+            if visited.contains(&rotation.new_peer_id) {
+                break;
+            }
+            visited.push(rotation.new_peer_id.clone());
+            current = rotation.new_peer_id;
+        }
+
+        Ok(current)
+    }
+}
@@ -14,13 +14,19 @@
  * limitations under the License.
  */
 
-use crate::defaults::KEYS_TABLE_NAME;
+use crate::config::load_config;
+use crate::defaults::{
+    IMMUTABLE_KEYS_TABLE_NAME, KEYS_TABLE_NAME, KEY_ACCESS_TABLE_NAME,
+    KEY_ID_COLLISIONS_TABLE_NAME, KEY_LABELS_TABLE_NAME, PINNED_KEYS_TABLE_NAME,
+    RECORDS_TABLE_NAME, RECORD_ACCESS_TABLE_NAME,
+};
 
 use crate::error::ServiceError;
 use crate::error::ServiceError::{InternalError, KeyNotExists};
 use crate::key::{Key, KeyInternal};
 use crate::storage_impl::Storage;
 use marine_sqlite_connector::{State, Statement, Value};
+use std::collections::HashSet;
 
 impl Storage {
     pub fn create_key_tables(&self) {
@@ -52,6 +58,287 @@ impl Storage {
         }
     }
 
+    pub fn create_key_access_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {KEY_ACCESS_TABLE_NAME} (
+                key_id TEXT PRIMARY KEY,
+                read_count INTEGER NOT NULL,
+                timestamp_accessed INTEGER NOT NULL
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_key_access_table error: {}", error);
+        }
+    }
+
+    pub fn create_pinned_keys_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {PINNED_KEYS_TABLE_NAME} (
+                key_id TEXT PRIMARY KEY
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_pinned_keys_table error: {}", error);
+        }
+    }
+
+    /// Pinned keys are kept in a separate table, so republishing the key doesn't reset the flag
+    pub fn set_key_pinned(&self, key_id: &str, pinned: bool) -> Result<(), ServiceError> {
+        let query = if pinned {
+            f!("INSERT OR IGNORE INTO {PINNED_KEYS_TABLE_NAME} VALUES (?)")
+        } else {
+            f!("DELETE FROM {PINNED_KEYS_TABLE_NAME} WHERE key_id = ?")
+        };
+        let mut statement = self.connection.prepare(query)?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    pub fn is_key_pinned(&self, key_id: &str) -> Result<bool, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT EXISTS(SELECT 1 FROM {PINNED_KEYS_TABLE_NAME} WHERE key_id = ?)"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+
+        if let State::Row = statement.next()? {
+            Ok(statement.read::<i64>(0)? == 1)
+        } else {
+            Err(InternalError(
+                "EXISTS should always return something".to_string(),
+            ))
+        }
+    }
+
+    pub fn create_immutable_keys_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {IMMUTABLE_KEYS_TABLE_NAME} (
+                key_id TEXT PRIMARY KEY
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_immutable_keys_table error: {}", error);
+        }
+    }
+
+    /// Immutable keys are kept in a separate table like pinned ones, the flag is cleared only with the key
+    pub fn set_key_immutable(&self, key_id: &str, immutable: bool) -> Result<(), ServiceError> {
+        let query = if immutable {
+            f!("INSERT OR IGNORE INTO {IMMUTABLE_KEYS_TABLE_NAME} VALUES (?)")
+        } else {
+            f!("DELETE FROM {IMMUTABLE_KEYS_TABLE_NAME} WHERE key_id = ?")
+        };
+        let mut statement = self.connection.prepare(query)?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    pub fn is_key_immutable(&self, key_id: &str) -> Result<bool, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT EXISTS(SELECT 1 FROM {IMMUTABLE_KEYS_TABLE_NAME} WHERE key_id = ?)"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+
+        if let State::Row = statement.next()? {
+            Ok(statement.read::<i64>(0)? == 1)
+        } else {
+            Err(InternalError(
+                "EXISTS should always return something".to_string(),
+            ))
+        }
+    }
+
+    pub fn create_key_labels_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {KEY_LABELS_TABLE_NAME} (
+                key_id TEXT PRIMARY KEY,
+                display_label TEXT NOT NULL
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_key_labels_table error: {}", error);
+        }
+    }
+
+    pub fn create_key_id_collisions_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {KEY_ID_COLLISIONS_TABLE_NAME} (
+                key_id TEXT,
+                label TEXT,
+                owner_peer_id TEXT,
+                timestamp_created INTEGER,
+                challenge BLOB,
+                challenge_type TEXT,
+                signature BLOB NOT NULL,
+                PRIMARY KEY (key_id, label, owner_peer_id)
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_key_id_collisions_table error: {}", error);
+        }
+    }
+
+    /// Keep the stored key replaced by a key with the same id but another (label, owner_peer_id) pair
+    fn log_key_id_collision(&self, key: Key) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "INSERT OR REPLACE INTO {KEY_ID_COLLISIONS_TABLE_NAME} VALUES (?, ?, ?, ?, ?, ?, ?)"
+        ))?;
+        statement.bind(1, &Value::String(key.id))?;
+        statement.bind(2, &Value::String(key.label))?;
+        statement.bind(3, &Value::String(key.owner_peer_id))?;
+        statement.bind(4, &Value::Integer(key.timestamp_created as i64))?;
+        statement.bind(5, &Value::Binary(key.challenge))?;
+        statement.bind(6, &Value::String(key.challenge_type))?;
+        statement.bind(7, &Value::Binary(key.signature))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    /// Keys replaced by colliding keys, ordered by key id
+    pub fn get_collided_keys(&self) -> Result<Vec<Key>, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, label, owner_peer_id, timestamp_created, challenge, challenge_type, signature \
+             FROM {KEY_ID_COLLISIONS_TABLE_NAME} ORDER BY key_id"
+        ))?;
+
+        let mut keys: Vec<Key> = vec![];
+        while let State::Row = statement.next()? {
+            keys.push(read_key(&statement)?);
+        }
+
+        Ok(keys)
+    }
+
+    /// Set the display label of the key, an empty label removes it
+    pub fn set_key_display_label(
+        &self,
+        key_id: &str,
+        display_label: String,
+    ) -> Result<(), ServiceError> {
+        let mut statement = if display_label.is_empty() {
+            self.connection
+                .prepare(f!("DELETE FROM {KEY_LABELS_TABLE_NAME} WHERE key_id = ?"))?
+        } else {
+            let mut statement = self.connection.prepare(f!(
+                "INSERT OR REPLACE INTO {KEY_LABELS_TABLE_NAME} VALUES (?, ?)"
+            ))?;
+            statement.bind(2, &Value::String(display_label))?;
+            statement
+        };
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    /// Display label of the key, empty if it isn't set
+    pub fn get_key_display_label(&self, key_id: &str) -> Result<String, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT display_label FROM {KEY_LABELS_TABLE_NAME} WHERE key_id = ?"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+
+        if let State::Row = statement.next()? {
+            Ok(statement.read::<String>(0)?)
+        } else {
+            Ok("".to_string())
+        }
+    }
+
+    /// Count a read of the key records at `timestamp_accessed`, the records are marked as accessed too
+    pub fn register_key_access(
+        &self,
+        key_id: &str,
+        timestamp_accessed: u64,
+    ) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "INSERT INTO {KEY_ACCESS_TABLE_NAME} VALUES (?, 1, ?) ON CONFLICT(key_id) DO UPDATE \
+             SET read_count = read_count + 1, timestamp_accessed = max(timestamp_accessed, excluded.timestamp_accessed)"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::Integer(timestamp_accessed as i64))?;
+        statement.next().map(drop)?;
+
+        self.register_records_access(key_id, timestamp_accessed)
+    }
+
+    /// Mark the key as accessed at `timestamp_accessed` without counting a read or touching its records,
+    /// later accesses are kept
+    pub fn touch_key_access(
+        &self,
+        key_id: &str,
+        timestamp_accessed: u64,
+    ) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "INSERT INTO {KEY_ACCESS_TABLE_NAME} VALUES (?, 0, ?) ON CONFLICT(key_id) DO UPDATE \
+             SET timestamp_accessed = max(timestamp_accessed, excluded.timestamp_accessed)"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::Integer(timestamp_accessed as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    /// Return up to `limit` keys (`0` means all) accessed since `since`, the most recently accessed first
+    pub fn get_hot_keys(&self, limit: u32, since: u64) -> Result<Vec<Key>, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT k.key_id, k.label, k.owner_peer_id, k.timestamp_created, k.challenge, k.challenge_type, k.signature \
+             FROM {KEYS_TABLE_NAME} k JOIN {KEY_ACCESS_TABLE_NAME} a ON k.key_id = a.key_id \
+             WHERE a.timestamp_accessed >= ? ORDER BY a.timestamp_accessed DESC, a.read_count DESC LIMIT ?"
+        ))?;
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        statement.bind(1, &Value::Integer(since as i64))?;
+        statement.bind(2, &Value::Integer(limit))?;
+
+        let mut keys: Vec<Key> = vec![];
+        while let State::Row = statement.next()? {
+            keys.push(read_key(&statement)?);
+        }
+
+        Ok(keys)
+    }
+
+    /// Return up to `limit` keys (`0` means all) created up to `current_timestamp_sec` without actual records,
+    /// oldest first, along with whether they are pinned
+    pub fn get_empty_keys(
+        &self,
+        limit: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<(Key, bool)>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let mut statement = self.connection.prepare(f!(
+            "SELECT k.key_id, k.label, k.owner_peer_id, k.timestamp_created, k.challenge, k.challenge_type, k.signature, \
+                    p.key_id IS NOT NULL \
+             FROM {KEYS_TABLE_NAME} k \
+             LEFT JOIN {RECORDS_TABLE_NAME} r \
+             ON r.key_id = k.key_id AND r.is_tombstoned = 0 AND r.timestamp_created > ? \
+             LEFT JOIN {PINNED_KEYS_TABLE_NAME} p ON p.key_id = k.key_id \
+             WHERE r.key_id IS NULL AND k.timestamp_created <= ? \
+             ORDER BY k.timestamp_created, k.key_id LIMIT ?"
+        ))?;
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        statement.bind(1, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(2, &Value::Integer(current_timestamp_sec as i64))?;
+        statement.bind(3, &Value::Integer(limit))?;
+
+        let mut keys = vec![];
+        while let State::Row = statement.next()? {
+            keys.push((read_key(&statement)?, statement.read::<i64>(7)? != 0));
+        }
+
+        Ok(keys)
+    }
+
     pub fn get_key(&self, key_id: String) -> Result<Key, ServiceError> {
         let mut statement = self.connection.prepare(f!(
             "SELECT key_id, label, owner_peer_id, timestamp_created, challenge, challenge_type, signature \
@@ -66,7 +353,77 @@ impl Storage {
         }
     }
 
+    /// Return existing keys among `key_ids` created not later than `current_timestamp_sec`
+    pub fn get_keys(
+        &self,
+        key_ids: &[String],
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<Key>, ServiceError> {
+        let placeholders = vec!["?"; key_ids.len()].join(", ");
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, label, owner_peer_id, timestamp_created, challenge, challenge_type, signature \
+             FROM {KEYS_TABLE_NAME} WHERE timestamp_created <= ? AND key_id IN ({placeholders})"
+        ))?;
+        statement.bind(1, &Value::Integer(current_timestamp_sec as i64))?;
+        for (i, key_id) in key_ids.iter().enumerate() {
+            statement.bind(i + 2, &Value::String(key_id.clone()))?;
+        }
+
+        let mut keys: Vec<Key> = vec![];
+        while let State::Row = statement.next()? {
+            keys.push(read_key(&statement)?);
+        }
+
+        Ok(keys)
+    }
+
+    /// Return which of `key_ids` exist in a single query
+    pub fn get_existing_key_ids(
+        &self,
+        key_ids: &[String],
+    ) -> Result<HashSet<String>, ServiceError> {
+        let mut existing = HashSet::new();
+        if key_ids.is_empty() {
+            return Ok(existing);
+        }
+
+        let placeholders = vec!["?"; key_ids.len()].join(", ");
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id FROM {KEYS_TABLE_NAME} WHERE key_id IN ({placeholders})"
+        ))?;
+        for (i, key_id) in key_ids.iter().enumerate() {
+            statement.bind(i + 1, &Value::String(key_id.clone()))?;
+        }
+
+        while let State::Row = statement.next()? {
+            existing.insert(statement.read::<String>(0)?);
+        }
+
+        Ok(existing)
+    }
+
+    pub fn get_internal_key(&self, key_id: String) -> Result<KeyInternal, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, label, owner_peer_id, timestamp_created, challenge, challenge_type, signature, timestamp_published, weight \
+                              FROM {KEYS_TABLE_NAME} WHERE key_id = ?"
+        ))?;
+        statement.bind(1, &Value::String(key_id.clone()))?;
+
+        if let State::Row = statement.next()? {
+            read_internal_key(&statement)
+        } else {
+            Err(KeyNotExists(key_id))
+        }
+    }
+
     pub fn write_key(&self, key: KeyInternal) -> Result<(), ServiceError> {
+        // ids hash the plain concatenation of label and owner_peer_id, so another pair may map to the stored key
+        if let Ok(existing) = self.get_key(key.key.id.clone()) {
+            if existing.label != key.key.label || existing.owner_peer_id != key.key.owner_peer_id {
+                self.log_key_id_collision(existing)?;
+            }
+        }
+
         let mut statement = self.connection.prepare(f!("
              INSERT OR REPLACE INTO {KEYS_TABLE_NAME} VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?);
          "))?;
@@ -117,6 +474,64 @@ impl Storage {
         }
     }
 
+    /// Set weight of the existing key, the key counts as published at `timestamp_published`
+    pub fn set_key_weight(
+        &self,
+        key_id: &str,
+        weight: u32,
+        timestamp_published: u64,
+    ) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "UPDATE {KEYS_TABLE_NAME} SET weight = ?, timestamp_published = ? WHERE key_id = ?"
+        ))?;
+        statement.bind(1, &Value::Integer(weight as i64))?;
+        statement.bind(2, &Value::Integer(timestamp_published as i64))?;
+        statement.bind(3, &Value::String(key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    pub fn check_key_owner(&self, key_id: &str, peer_id: &str) -> Result<(), ServiceError> {
+        let key = self.get_key(key_id.to_string())?;
+        if key.owner_peer_id == peer_id {
+            Ok(())
+        } else {
+            Err(ServiceError::NotKeyOwner(
+                key_id.to_string(),
+                peer_id.to_string(),
+            ))
+        }
+    }
+
+    pub fn get_all_keys(&self) -> Result<Vec<KeyInternal>, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, label, owner_peer_id, timestamp_created, challenge, challenge_type, signature, timestamp_published, weight \
+                              FROM {KEYS_TABLE_NAME}"
+        ))?;
+
+        let mut keys: Vec<KeyInternal> = vec![];
+        while let State::Row = statement.next()? {
+            keys.push(read_internal_key(&statement)?);
+        }
+
+        Ok(keys)
+    }
+
+    pub fn count_keys(&self) -> Result<u64, ServiceError> {
+        let mut statement = self
+            .connection
+            .prepare(f!("SELECT COUNT(*) FROM {KEYS_TABLE_NAME}"))?;
+
+        if let State::Row = statement.next()? {
+            Ok(statement.read::<i64>(0)? as u64)
+        } else {
+            Err(InternalError(f!(
+                "count_keys: something went totally wrong"
+            )))
+        }
+    }
+
     pub fn get_stale_keys(&self, stale_timestamp: u64) -> Result<Vec<KeyInternal>, ServiceError> {
         let mut statement = self.connection.prepare(f!(
             "SELECT key_id, label, owner_peer_id, timestamp_created, challenge, challenge_type, signature, timestamp_published, weight \
@@ -140,15 +555,94 @@ impl Storage {
         statement.next().map(drop)?;
 
         if self.connection.changes() == 1 {
-            Ok(())
+            self.set_key_pinned(&key_id, false)?;
+            self.set_key_immutable(&key_id, false)?;
+            self.set_key_display_label(&key_id, "".to_string())
         } else {
             Err(KeyNotExists(key_id))
         }
     }
 
+    /// Remove keys of `owner_peer_id` created up to `current_timestamp` whose label starts with `prefix`,
+    /// with all their records and tombstones. Pinned keys are kept unless `force` is set.
+    /// Return number of removed keys and records
+    pub fn remove_keys_by_prefix(
+        &self,
+        prefix: &str,
+        owner_peer_id: &str,
+        force: bool,
+        current_timestamp: u64,
+    ) -> Result<(u64, u64), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id FROM {KEYS_TABLE_NAME} \
+             WHERE owner_peer_id = ? AND substr(label, 1, length(?)) = ? AND timestamp_created <= ? \
+             AND (? OR key_id NOT IN (SELECT key_id FROM {PINNED_KEYS_TABLE_NAME}))"
+        ))?;
+        statement.bind(1, &Value::String(owner_peer_id.to_string()))?;
+        statement.bind(2, &Value::String(prefix.to_string()))?;
+        statement.bind(3, &Value::String(prefix.to_string()))?;
+        statement.bind(4, &Value::Integer(current_timestamp as i64))?;
+        statement.bind(5, &Value::Integer(force as i64))?;
+
+        let mut key_ids: Vec<String> = vec![];
+        while let State::Row = statement.next()? {
+            key_ids.push(statement.read::<String>(0)?);
+        }
+
+        let mut removed_records = 0;
+        for key_id in key_ids.iter() {
+            removed_records += self.delete_records_by_key(key_id)?;
+            self.delete_key(key_id.clone())?;
+        }
+
+        Ok((key_ids.len() as u64, removed_records))
+    }
+
+    /// Remove keys not created, republished or read since `inactive_timestamp`
+    /// with all their records and tombstones, pinned keys are kept. Return number of removed keys and records
+    pub fn remove_inactive_keys(
+        &self,
+        inactive_timestamp: u64,
+    ) -> Result<(u64, u64), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT k.key_id FROM {KEYS_TABLE_NAME} k LEFT JOIN {KEY_ACCESS_TABLE_NAME} a ON k.key_id = a.key_id \
+             WHERE max(k.timestamp_created, k.timestamp_published, coalesce(a.timestamp_accessed, 0)) < ? \
+             AND k.key_id NOT IN (SELECT key_id FROM {PINNED_KEYS_TABLE_NAME})"
+        ))?;
+        statement.bind(1, &Value::Integer(inactive_timestamp as i64))?;
+
+        let mut key_ids: Vec<String> = vec![];
+        while let State::Row = statement.next()? {
+            key_ids.push(statement.read::<String>(0)?);
+        }
+
+        let mut removed_records = 0;
+        for key_id in key_ids.iter() {
+            removed_records += self.delete_records_by_key(key_id)?;
+            self.delete_key_access(key_id)?;
+            self.delete_key(key_id.clone())?;
+        }
+
+        Ok((key_ids.len() as u64, removed_records))
+    }
+
+    /// Forget access tracking of the key and its records
+    fn delete_key_access(&self, key_id: &str) -> Result<(), ServiceError> {
+        for table in [KEY_ACCESS_TABLE_NAME, RECORD_ACCESS_TABLE_NAME].iter() {
+            let mut statement = self
+                .connection
+                .prepare(f!("DELETE FROM {table} WHERE key_id = ?"))?;
+            statement.bind(1, &Value::String(key_id.to_string()))?;
+            statement.next().map(drop)?;
+        }
+
+        Ok(())
+    }
+
     pub fn clear_expired_keys(&self, expired_timestamp: u64) -> Result<u64, ServiceError> {
         let mut statement = self.connection.prepare(f!(
-            "SELECT key_id FROM {KEYS_TABLE_NAME} WHERE timestamp_created <= ?"
+            "SELECT key_id FROM {KEYS_TABLE_NAME} WHERE timestamp_created <= ? \
+             AND key_id NOT IN (SELECT key_id FROM {PINNED_KEYS_TABLE_NAME})"
         ))?;
         statement.bind(1, &Value::Integer(expired_timestamp as i64))?;
 
@@ -18,18 +18,32 @@
 use marine_rs_sdk::marine;
 use marine_rs_sdk::module_manifest;
 
-use crate::config::{create_config, load_config, write_config};
-use crate::results::{ClearExpiredResult, EvictStaleResult};
+use crate::config::{create_config, load_config, write_config, EvictionPolicy, ValueEncoding};
+use crate::defaults::MAX_WEIGHT;
+use crate::error::ServiceError;
+use crate::results::{
+    ClearExpiredResult, DbSizeResult, EvictStaleResult, RateLimitResult, RegistryResult,
+    StatsResult,
+};
 use crate::storage_impl::get_storage;
 use crate::tetraplets_checkers::check_timestamp_tetraplets;
 
 mod config;
 mod defaults;
 mod error;
+mod export;
+mod export_api;
 mod key;
 mod key_api;
+mod key_rotation;
+mod key_rotation_api;
+mod key_rotation_storage_impl;
 mod key_storage_impl;
+mod maintenance_api;
+mod merkle;
 mod misc;
+mod overwrite_log;
+mod rate_limit;
 mod record;
 mod record_api;
 mod record_storage_impl;
@@ -64,8 +78,18 @@ pub struct WeightResult {
 
 fn main() {
     let storage = get_storage().unwrap();
+    storage.migrate().expect("failed to migrate storage schema");
     storage.create_key_tables();
     storage.create_records_table();
+    storage.create_records_seq_table();
+    storage.create_overwrite_log_table();
+    storage.create_key_rotations_table();
+    storage.create_key_access_table();
+    storage.create_record_access_table();
+    storage.create_pinned_keys_table();
+    storage.create_immutable_keys_table();
+    storage.create_key_labels_table();
+    storage.create_key_id_collisions_table();
     create_config();
 }
 
@@ -89,6 +113,40 @@ pub fn evict_stale(current_timestamp_sec: u64) -> EvictStaleResult {
     .into()
 }
 
+/// Make sure all written data hits the disk, may be expensive so call it only after critical writes
+#[marine]
+pub fn flush_storage() -> RegistryResult {
+    wrapped_try(|| get_storage()?.flush()).into()
+}
+
+/// Return the storage size on disk in bytes
+#[marine]
+pub fn get_db_size_bytes() -> DbSizeResult {
+    wrapped_try(|| get_storage()?.get_db_size_bytes()).into()
+}
+
+/// Return counts of stored keys and records for monitoring, doesn't count as an access of anything.
+/// `oldest_key_timestamp` is 0 if there are no keys
+#[marine]
+pub fn get_stats() -> StatsResult {
+    wrapped_try(|| {
+        let host_id = marine_rs_sdk::get_call_parameters().host_id;
+        get_storage()?.get_stats(host_id)
+    })
+    .into()
+}
+
+/// Return how many puts `peer_id` has left in the current rate limit window
+#[marine]
+pub fn get_rate_limit_status(peer_id: String, current_timestamp_sec: u64) -> RateLimitResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        Ok(rate_limit::get_status(&peer_id, current_timestamp_sec))
+    })
+    .into()
+}
+
 #[marine]
 pub fn set_expired_timeout(timeout_sec: u64) {
     let mut config = load_config();
@@ -96,9 +154,124 @@ pub fn set_expired_timeout(timeout_sec: u64) {
     write_config(config);
 }
 
+/// Log identities of records replaced by newer versions, see `get_overwrite_log`
+#[marine]
+pub fn set_overwrite_log(enabled: bool) {
+    let mut config = load_config();
+    config.overwrite_log = enabled;
+    write_config(config);
+}
+
+/// Records created up to `skew_sec` in the future are accepted by put_record and republish_records
+#[marine]
+pub fn set_max_clock_skew(skew_sec: u64) {
+    let mut config = load_config();
+    config.max_clock_skew_sec = skew_sec;
+    write_config(config);
+}
+
+/// Records created more than `max_age_sec` ago are rejected by put_record and republish_records, 0 disables it
+#[marine]
+pub fn set_max_record_age(max_age_sec: u64) {
+    let mut config = load_config();
+    config.max_record_age_sec = max_age_sec;
+    write_config(config);
+}
+
+/// Check signatures format in put_record and register_key before verification
+#[marine]
+pub fn set_strict_signatures(enabled: bool) {
+    let mut config = load_config();
+    config.strict_signatures = enabled;
+    write_config(config);
+}
+
+/// Let get_records delete orphaned records it finds under a missing key
+#[marine]
+pub fn set_self_heal(enabled: bool) {
+    let mut config = load_config();
+    config.self_heal = enabled;
+    write_config(config);
+}
+
+/// Reject puts of records with empty relay_id, for deployments reaching peers only via relays
+#[marine]
+pub fn set_require_relay_id(enabled: bool) {
+    let mut config = load_config();
+    config.require_relay_id = enabled;
+    write_config(config);
+}
+
+/// Accept keys and record metadata signed over the layout without domain tags, disabled by default
+#[marine]
+pub fn set_legacy_signatures(enabled: bool) {
+    let mut config = load_config();
+    config.legacy_signatures = enabled;
+    write_config(config);
+}
+
+/// Records of issuers with weight below `min_weight` are rejected by puts and skipped by republish_records,
+/// puts initiated by the host itself aren't limited
+#[marine]
+pub fn set_min_weight_for_put(min_weight: u32) {
+    let mut config = load_config();
+    config.min_weight_for_put = min_weight;
+    write_config(config);
+}
+
+/// Reject puts of issuers without records under a key that already has records of `max_authors` issuers
+#[marine]
+pub fn set_max_authors_per_key(max_authors: u64) {
+    let mut config = load_config();
+    config.max_authors_per_key = max_authors;
+    write_config(config);
+}
+
+/// Let put_record and register_key accept `WeightResult` with default fields as `weight`.
+/// Such weights aren't verified, so enable it only in deployments without a trusted weight service
+#[marine]
+pub fn set_default_weight(enabled: bool, weight: u32) -> RegistryResult {
+    wrapped_try(|| {
+        if weight > MAX_WEIGHT {
+            return Err(ServiceError::WeightTooHigh(weight, MAX_WEIGHT));
+        }
+
+        let mut config = load_config();
+        config.default_weight_enabled = enabled;
+        config.default_weight = weight;
+        write_config(config);
+        Ok(())
+    })
+    .into()
+}
+
 #[marine]
 pub fn set_stale_timeout(timeout_sec: u64) {
     let mut config = load_config();
     config.stale_timeout = timeout_sec;
     write_config(config);
 }
+
+/// `policy` is one of: LowestWeight, Oldest, LeastRecentlyAccessed
+#[marine]
+pub fn set_eviction_policy(policy: String) -> RegistryResult {
+    wrapped_try(|| {
+        let mut config = load_config();
+        config.eviction_policy = policy.parse::<EvictionPolicy>()?;
+        write_config(config);
+        Ok(())
+    })
+    .into()
+}
+
+/// `encoding` is one of: None, Utf8, Json, Base64
+#[marine]
+pub fn set_value_encoding(encoding: String) -> RegistryResult {
+    wrapped_try(|| {
+        let mut config = load_config();
+        config.value_encoding = encoding.parse::<ValueEncoding>()?;
+        write_config(config);
+        Ok(())
+    })
+    .into()
+}
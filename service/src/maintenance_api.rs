@@ -0,0 +1,51 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::misc::check_admin;
+use crate::results::{EmptyKeysResult, RemoveKeysResult};
+use crate::storage_impl::get_storage;
+use crate::tetraplets_checkers::check_timestamp_tetraplets;
+use crate::wrapped_try;
+use marine_rs_sdk::marine;
+
+/// Remove keys that weren't created, republished or read within `stale_age_sec`, cascading to their records.
+/// Keys pinned with `set_key_pinned` are kept.
+/// Meant for periodic GC, so it can be called only by the host or the service creator
+#[marine]
+pub fn clear_stale_keys(current_timestamp_sec: u64, stale_age_sec: u64) -> RemoveKeysResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        check_admin(&call_parameters)?;
+
+        let storage = get_storage()?;
+        let inactive_timestamp = current_timestamp_sec.saturating_sub(stale_age_sec);
+        storage.transaction(|storage| storage.remove_inactive_keys(inactive_timestamp))
+    })
+    .into()
+}
+
+/// Return up to `limit` keys (`0` means all) without actual records to review before GC, oldest first.
+/// Pinned keys are listed too, `pinned[i]` tells whether `result[i]` is pinned
+#[marine]
+pub fn get_empty_keys(limit: u32, current_timestamp_sec: u64) -> EmptyKeysResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        get_storage()?.get_empty_keys(limit, current_timestamp_sec)
+    })
+    .into()
+}
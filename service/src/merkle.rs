@@ -0,0 +1,123 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use marine_rs_sdk::marine;
+use sha2::{Digest, Sha256};
+
+/// Sibling of a node on the path from a leaf to the root
+#[marine]
+#[derive(Debug, Default, Clone)]
+pub struct MerkleProofStep {
+    /// base58-encoded sibling hash
+    pub hash: String,
+    /// whether the sibling is hashed on the left
+    pub is_left: bool,
+}
+
+fn leaf_hash(identity: &str) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([0u8]);
+    hasher.update(identity.as_bytes());
+    hasher.finalize().to_vec()
+}
+
+fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+fn next_level(level: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            // the last odd node is carried to the next level as is
+            [node] => node.clone(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Merkle tree over sorted identities, leaves and nodes are domain separated.
+/// Returns tree levels from the leaves to the root
+fn build_levels(identities: &mut [String]) -> Vec<Vec<Vec<u8>>> {
+    identities.sort();
+    let mut levels = vec![identities
+        .iter()
+        .map(|id| leaf_hash(id))
+        .collect::<Vec<_>>()];
+    while levels.last().map_or(false, |level| level.len() > 1) {
+        let level = next_level(levels.last().unwrap());
+        levels.push(level);
+    }
+
+    levels
+}
+
+/// base58-encoded root of the tree over `identities`, empty for no identities
+pub fn merkle_root(mut identities: Vec<String>) -> String {
+    build_levels(&mut identities)
+        .last()
+        .and_then(|level| level.first())
+        .map(|root| bs58::encode(root).into_string())
+        .unwrap_or_default()
+}
+
+/// Root and inclusion path of `identity` in the tree over `identities`, None if it isn't there
+pub fn merkle_proof(
+    mut identities: Vec<String>,
+    identity: &str,
+) -> Option<(String, Vec<MerkleProofStep>)> {
+    let levels = build_levels(&mut identities);
+    let mut index = identities
+        .binary_search_by(|id| id.as_str().cmp(identity))
+        .ok()?;
+    let mut proof = vec![];
+    for level in levels.iter().take(levels.len() - 1) {
+        let sibling = index ^ 1;
+        if sibling < level.len() {
+            proof.push(MerkleProofStep {
+                hash: bs58::encode(&level[sibling]).into_string(),
+                is_left: sibling < index,
+            });
+        }
+        index /= 2;
+    }
+
+    let root = bs58::encode(&levels.last()?[0]).into_string();
+    Some((root, proof))
+}
+
+/// Check that `proof` leads from `identity` to `root`
+pub fn verify_merkle_proof(root: &str, identity: &str, proof: &[MerkleProofStep]) -> bool {
+    let mut hash = leaf_hash(identity);
+    for step in proof {
+        let sibling = match bs58::decode(&step.hash).into_vec() {
+            Ok(sibling) => sibling,
+            Err(_) => return false,
+        };
+        hash = if step.is_left {
+            node_hash(&sibling, &hash)
+        } else {
+            node_hash(&hash, &sibling)
+        };
+    }
+
+    bs58::encode(hash).into_string() == root
+}
@@ -14,11 +14,20 @@
  * limitations under the License.
  */
 
+use crate::config::{load_config, ValueEncoding};
+use crate::defaults::{
+    MAX_METADATA_BYTES, MAX_RELAY_IDS, MAX_SERVICE_IDS, MAX_VALUE_SIZE_BYTES, MAX_WEIGHT,
+};
 use crate::error::ServiceError;
+use crate::record::{Record, RecordMetadata};
+use crate::tetraplets_checkers::check_weight_tetraplets;
 use crate::WeightResult;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use boolinator::Boolinator;
-use fluence_keypair::PublicKey;
+use fluence_keypair::error::VerificationError;
+use fluence_keypair::{KeyFormat, PublicKey, Signature};
 use libp2p_identity::PeerId;
+use marine_rs_sdk::CallParameters;
 use std::convert::TryFrom;
 use std::str::FromStr;
 
@@ -34,9 +43,198 @@ pub fn extract_public_key(peer_id: String) -> Result<PublicKey, ServiceError> {
     .map_err(ServiceError::PublicKeyDecodeError)
 }
 
+/// Check that the record isn't issued or created more than `max_clock_skew_sec` in the future
+/// and isn't created earlier than `max_record_age_sec` ago or already past its `expires_at`.
+/// Return the latest accepted timestamp, the record should be verified against it
+pub fn check_record_timestamps(
+    record: &Record,
+    current_timestamp_sec: u64,
+) -> Result<u64, ServiceError> {
+    let config = load_config();
+    let skewed_timestamp_sec = current_timestamp_sec.saturating_add(config.max_clock_skew_sec);
+    for timestamp in [record.timestamp_created, record.metadata.timestamp_issued].iter() {
+        if *timestamp > skewed_timestamp_sec {
+            return Err(ServiceError::InvalidTimestampFuture(
+                *timestamp,
+                skewed_timestamp_sec,
+            ));
+        }
+    }
+
+    if config.max_record_age_sec != 0 {
+        let cutoff = current_timestamp_sec.saturating_sub(config.max_record_age_sec);
+        if record.timestamp_created < cutoff {
+            return Err(ServiceError::InvalidTimestampTooOld(
+                record.timestamp_created,
+                cutoff,
+            ));
+        }
+    }
+
+    // not skewed, a record expiring in the skew window is still alive
+    if record.expires_at != 0 && record.expires_at <= current_timestamp_sec {
+        return Err(ServiceError::RecordAlreadyExpired);
+    }
+
+    Ok(skewed_timestamp_sec)
+}
+
+/// Append `field` prefixed with its length, so adjacent fields can't be shifted into each other
+pub fn push_field(bytes: &mut Vec<u8>, field: &[u8]) {
+    bytes.extend((field.len() as u64).to_le_bytes());
+    bytes.extend(field);
+}
+
+/// Verify `signature` of `bytes`, falling back to `legacy_bytes` if legacy signatures are enabled.
+/// The error of the current layout is returned if both fail
+pub fn verify_with_legacy(
+    pk: &PublicKey,
+    signature: &Signature,
+    bytes: &[u8],
+    legacy_bytes: impl FnOnce() -> Vec<u8>,
+) -> Result<(), VerificationError> {
+    pk.verify(bytes, signature).or_else(|e| {
+        if load_config().legacy_signatures {
+            pk.verify(&legacy_bytes(), signature).map_err(|_| e)
+        } else {
+            Err(e)
+        }
+    })
+}
+
 pub fn check_weight_result(peer_id: &str, weight: &WeightResult) -> Result<(), ServiceError> {
     (weight.success && weight.peer_id.eq(peer_id)).as_result(
         (),
         ServiceError::InvalidWeightPeerId(peer_id.to_string(), weight.peer_id.clone()),
     )
 }
+
+/// "No weight" sentinel is a failed weight result without peer id and error, i.e. `WeightResult::default()`
+fn is_no_weight(weight: &WeightResult) -> bool {
+    !weight.success && weight.weight == 0 && weight.peer_id.is_empty() && weight.error.is_empty()
+}
+
+/// Check that `weight` passed as `arg_number` argument is the trust-graph weight of `peer_id` and return it.
+/// If the default weight is enabled by `set_default_weight`, the "no weight" sentinel resolves to the default weight
+/// without any checks: it's meant only for deployments without a weight service
+pub fn resolve_weight(
+    call_parameters: &CallParameters,
+    arg_number: usize,
+    peer_id: &str,
+    weight: &WeightResult,
+) -> Result<u32, ServiceError> {
+    let config = load_config();
+    if config.default_weight_enabled && is_no_weight(weight) {
+        return if config.default_weight > MAX_WEIGHT {
+            Err(ServiceError::WeightTooHigh(
+                config.default_weight,
+                MAX_WEIGHT,
+            ))
+        } else {
+            Ok(config.default_weight)
+        };
+    }
+
+    check_weight_tetraplets(call_parameters, arg_number, 0)?;
+    check_weight_result(peer_id, weight)?;
+    Ok(weight.weight)
+}
+
+/// Check that a record of an issuer with `weight` may be stored, see `set_min_weight_for_put`
+pub fn check_min_weight(weight: u32) -> Result<(), ServiceError> {
+    let min_weight = load_config().min_weight_for_put;
+    (weight >= min_weight).as_result((), ServiceError::WeightTooLow(weight, min_weight))
+}
+
+/// `check_min_weight` for puts, the host putting records itself isn't limited
+pub fn check_put_weight(call_parameters: &CallParameters, weight: u32) -> Result<(), ServiceError> {
+    if call_parameters.init_peer_id == call_parameters.host_id {
+        return Ok(());
+    }
+
+    check_min_weight(weight)
+}
+
+pub fn check_value_encoding(
+    key_id: &str,
+    value: &str,
+    encoding: ValueEncoding,
+) -> Result<(), ServiceError> {
+    let is_valid = match encoding {
+        ValueEncoding::None => true,
+        ValueEncoding::Utf8 => !value.chars().any(char::is_control),
+        ValueEncoding::Json => serde_json::from_str::<serde_json::Value>(value).is_ok(),
+        ValueEncoding::Base64 => STANDARD.decode(value).is_ok(),
+    };
+
+    is_valid.as_result(
+        (),
+        ServiceError::InvalidValueEncoding(key_id.to_string(), encoding),
+    )
+}
+
+pub fn check_metadata_size(metadata: &RecordMetadata) -> Result<(), ServiceError> {
+    let size = metadata.serialized_size();
+    (size <= MAX_METADATA_BYTES).as_result(
+        (),
+        ServiceError::MetadataTooLarge(metadata.key_id.clone(), size, MAX_METADATA_BYTES),
+    )
+}
+
+/// Check caller-controlled sizes of the record, cheap enough to run before signature verification
+pub fn check_record_limits(metadata: &RecordMetadata) -> Result<(), ServiceError> {
+    let value_size = metadata.value.len();
+    if value_size > MAX_VALUE_SIZE_BYTES {
+        return Err(ServiceError::ValueTooLarge(
+            value_size,
+            MAX_VALUE_SIZE_BYTES,
+        ));
+    }
+    if metadata.relay_id.len() > MAX_RELAY_IDS {
+        return Err(ServiceError::TooManyIds(
+            "relay_id".to_string(),
+            metadata.relay_id.len(),
+            MAX_RELAY_IDS,
+        ));
+    }
+    if metadata.service_id.len() > MAX_SERVICE_IDS {
+        return Err(ServiceError::TooManyIds(
+            "service_id".to_string(),
+            metadata.service_id.len(),
+            MAX_SERVICE_IDS,
+        ));
+    }
+
+    check_metadata_size(metadata)
+}
+
+/// Admin functions can be called only by the host or the service creator
+pub fn check_admin(call_parameters: &CallParameters) -> Result<(), ServiceError> {
+    (call_parameters.init_peer_id == call_parameters.host_id
+        || call_parameters.init_peer_id == call_parameters.service_creator_peer_id)
+        .as_result(
+            (),
+            ServiceError::NotAdmin(call_parameters.init_peer_id.clone()),
+        )
+}
+
+/// Check that `signature` is shaped as a signature of `peer_id` key format
+pub fn check_signature_format(peer_id: &str, signature: &[u8]) -> Result<(), ServiceError> {
+    let key_format = extract_public_key(peer_id.to_string())?.get_key_format();
+    let is_valid = match key_format {
+        KeyFormat::Ed25519 => signature.len() == 64,
+        // DER-encoded sequence
+        KeyFormat::Secp256k1 => {
+            signature.len() > 2
+                && signature[0] == 0x30
+                && signature[1] as usize == signature.len() - 2
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        KeyFormat::Rsa => !signature.is_empty(),
+    };
+
+    is_valid.as_result(
+        (),
+        ServiceError::InvalidSignatureFormat(peer_id.to_string(), key_format.into()),
+    )
+}
@@ -0,0 +1,98 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::defaults::{OVERWRITE_LOG_LIMIT, OVERWRITE_LOG_TABLE_NAME};
+use crate::error::ServiceError;
+use crate::storage_impl::Storage;
+use marine_rs_sdk::marine;
+use marine_sqlite_connector::{State, Value};
+
+/// Identity of a record version superseded by a newer one
+#[marine]
+#[derive(Debug, Default, Clone)]
+pub struct OverwriteLogEntry {
+    pub key_id: String,
+    pub issued_by: String,
+    pub peer_id: String,
+    pub timestamp_issued: u64,
+    pub timestamp_created: u64,
+}
+
+impl Storage {
+    pub fn create_overwrite_log_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {OVERWRITE_LOG_TABLE_NAME} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                key_id TEXT,
+                issued_by TEXT,
+                peer_id TEXT,
+                timestamp_issued INTEGER,
+                timestamp_created INTEGER
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_overwrite_log_table error: {}", error);
+        }
+    }
+
+    /// Append an entry and drop the oldest ones beyond `OVERWRITE_LOG_LIMIT`
+    pub fn log_overwrite(&self, entry: OverwriteLogEntry) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "INSERT INTO {OVERWRITE_LOG_TABLE_NAME} (key_id, issued_by, peer_id, timestamp_issued, timestamp_created) \
+             VALUES (?, ?, ?, ?, ?)"
+        ))?;
+        statement.bind(1, &Value::String(entry.key_id))?;
+        statement.bind(2, &Value::String(entry.issued_by))?;
+        statement.bind(3, &Value::String(entry.peer_id))?;
+        statement.bind(4, &Value::Integer(entry.timestamp_issued as i64))?;
+        statement.bind(5, &Value::Integer(entry.timestamp_created as i64))?;
+        statement.next().map(drop)?;
+
+        self.connection.execute(f!(
+            "DELETE FROM {OVERWRITE_LOG_TABLE_NAME} \
+             WHERE id <= (SELECT MAX(id) FROM {OVERWRITE_LOG_TABLE_NAME}) - {OVERWRITE_LOG_LIMIT}"
+        ))?;
+        Ok(())
+    }
+
+    /// Return up to `limit` latest overwrites for `key_id`, newest first
+    pub fn get_overwrite_log(
+        &self,
+        key_id: String,
+        limit: u32,
+    ) -> Result<Vec<OverwriteLogEntry>, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, issued_by, peer_id, timestamp_issued, timestamp_created \
+             FROM {OVERWRITE_LOG_TABLE_NAME} WHERE key_id = ? ORDER BY id DESC LIMIT ?"
+        ))?;
+        statement.bind(1, &Value::String(key_id))?;
+        statement.bind(2, &Value::Integer(limit as i64))?;
+
+        let mut result = vec![];
+        while let State::Row = statement.next()? {
+            result.push(OverwriteLogEntry {
+                key_id: statement.read::<String>(0)?,
+                issued_by: statement.read::<String>(1)?,
+                peer_id: statement.read::<String>(2)?,
+                timestamp_issued: statement.read::<i64>(3)? as u64,
+                timestamp_created: statement.read::<i64>(4)? as u64,
+            });
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,58 @@
+/*
+ * Copyright 2021 Fluence Labs Limited
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::defaults::{PUT_RATE_LIMIT, RATE_LIMIT_WINDOW_SEC};
+use crate::error::ServiceError;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+thread_local! {
+    /// peer_id -> (window start timestamp, puts made in the window)
+    static PUT_WINDOWS: RefCell<HashMap<String, (u64, u32)>> = RefCell::new(HashMap::new());
+}
+
+fn is_window_expired(window_start: u64, current_timestamp_sec: u64) -> bool {
+    current_timestamp_sec >= window_start + RATE_LIMIT_WINDOW_SEC
+}
+
+/// Count a put made by `peer_id` or return error if it has no puts left in the current window
+pub fn register_put(peer_id: &str, current_timestamp_sec: u64) -> Result<(), ServiceError> {
+    PUT_WINDOWS.with(|windows| {
+        let mut windows = windows.borrow_mut();
+        windows.retain(|_, (start, _)| !is_window_expired(*start, current_timestamp_sec));
+
+        let (_, count) = windows
+            .entry(peer_id.to_string())
+            .or_insert((current_timestamp_sec, 0));
+        if *count >= PUT_RATE_LIMIT {
+            return Err(ServiceError::RateLimitExceeded(peer_id.to_string()));
+        }
+
+        *count += 1;
+        Ok(())
+    })
+}
+
+/// Return puts left for `peer_id` in the current window and seconds until the window resets
+pub fn get_status(peer_id: &str, current_timestamp_sec: u64) -> (u32, u64) {
+    PUT_WINDOWS.with(|windows| match windows.borrow().get(peer_id) {
+        Some((start, count)) if !is_window_expired(*start, current_timestamp_sec) => (
+            PUT_RATE_LIMIT.saturating_sub(*count),
+            start + RATE_LIMIT_WINDOW_SEC - current_timestamp_sec,
+        ),
+        _ => (PUT_RATE_LIMIT, 0),
+    })
+}
@@ -14,14 +14,26 @@
  * limitations under the License.
  */
 
-use crate::misc::extract_public_key;
-use crate::{defaults::DEFAULT_EXPIRED_AGE, error::ServiceError};
+use crate::defaults::{
+    DEFAULT_EXPIRED_AGE, RECORD_METADATA_SIGNATURE_DOMAIN, RECORD_SIGNATURE_DOMAIN,
+};
+use crate::error::ServiceError;
+use crate::misc::{extract_public_key, push_field, verify_with_legacy};
 use fluence_keypair::Signature;
 use marine_rs_sdk::marine;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+/// Sorted and deduplicated ids, so signatures don't depend on the order clients list them in
+fn canonical_ids(ids: &[String]) -> Vec<&String> {
+    let mut ids: Vec<&String> = ids.iter().collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
 #[marine]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RecordMetadata {
     /// base58-encoded key id
     pub key_id: String,
@@ -45,6 +57,30 @@ pub struct RecordMetadata {
 
 impl RecordMetadata {
     pub fn signature_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_field(&mut bytes, RECORD_METADATA_SIGNATURE_DOMAIN.as_bytes());
+        push_field(&mut bytes, self.key_id.as_bytes());
+        push_field(&mut bytes, self.issued_by.as_bytes());
+        push_field(&mut bytes, self.peer_id.as_bytes());
+        bytes.extend(self.timestamp_issued.to_le_bytes());
+        push_field(&mut bytes, &self.solution);
+        push_field(&mut bytes, self.value.as_bytes());
+
+        for ids in [&self.relay_id, &self.service_id] {
+            let ids = canonical_ids(ids);
+            bytes.extend((ids.len() as u64).to_le_bytes());
+            for id in ids {
+                push_field(&mut bytes, id.as_bytes());
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        hasher.finalize().to_vec()
+    }
+
+    /// Layout without the domain tag and with one byte lengths, accepted only if legacy signatures are enabled
+    pub fn legacy_signature_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.push(self.key_id.len() as u8);
         bytes.extend(self.key_id.as_bytes());
@@ -80,6 +116,19 @@ impl RecordMetadata {
         hasher.finalize().to_vec()
     }
 
+    /// Total size of the metadata fields, timestamp counts as 8 bytes
+    pub fn serialized_size(&self) -> usize {
+        self.key_id.len()
+            + self.issued_by.len()
+            + self.peer_id.len()
+            + 8
+            + self.solution.len()
+            + self.value.len()
+            + self.relay_id.iter().map(String::len).sum::<usize>()
+            + self.service_id.iter().map(String::len).sum::<usize>()
+            + self.issuer_signature.len()
+    }
+
     pub fn verify(&self, current_timestamp_sec: u64) -> Result<(), ServiceError> {
         if self.timestamp_issued > current_timestamp_sec {
             return Err(ServiceError::InvalidRecordMetadataTimestamp);
@@ -88,7 +137,7 @@ impl RecordMetadata {
         let pk = extract_public_key(self.issued_by.clone())?;
         let bytes = self.signature_bytes();
         let signature = Signature::from_bytes(pk.get_key_format(), self.issuer_signature.clone());
-        pk.verify(&bytes, &signature).map_err(|e| {
+        verify_with_legacy(&pk, &signature, &bytes, || self.legacy_signature_bytes()).map_err(|e| {
             ServiceError::InvalidRecordMetadataSignature(
                 self.key_id.clone(),
                 self.issued_by.clone(),
@@ -99,35 +148,82 @@ impl RecordMetadata {
 }
 
 #[marine]
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+// records exported before `expires_at` don't have it
+#[serde(default)]
 pub struct Record {
     /// record metadata
     pub metadata: RecordMetadata,
     /// timestamp in seconds
     pub timestamp_created: u64,
+    /// timestamp in seconds the record expires at, 0 means it expires only by age
+    pub expires_at: u64,
     /// encoded and hashed previous fields signed by `metadata.peer_id`
     pub signature: Vec<u8>,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct RecordInternal {
     pub record: Record,
     pub weight: u32,
 }
 
+impl RecordInternal {
+    /// JSON object with only `fields` of the record, optional fields are null if missing
+    pub fn project(&self, fields: &[String]) -> Result<serde_json::Value, ServiceError> {
+        let metadata = &self.record.metadata;
+        let mut object = serde_json::Map::new();
+        for field in fields {
+            let value = match field.as_str() {
+                "key_id" => metadata.key_id.clone().into(),
+                "issued_by" => metadata.issued_by.clone().into(),
+                "peer_id" => metadata.peer_id.clone().into(),
+                "timestamp_issued" => metadata.timestamp_issued.into(),
+                "value" => metadata.value.clone().into(),
+                "relay_id" => metadata.relay_id.first().cloned().into(),
+                "service_id" => metadata.service_id.first().cloned().into(),
+                "timestamp_created" => self.record.timestamp_created.into(),
+                "weight" => self.weight.into(),
+                _ => return Err(ServiceError::UnknownField(field.clone())),
+            };
+            object.insert(field.clone(), value);
+        }
+
+        Ok(serde_json::Value::Object(object))
+    }
+}
+
 impl Record {
-    pub fn signature_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        let mut metadata = self.metadata.signature_bytes();
-        metadata.push(self.metadata.issuer_signature.len() as u8);
-        metadata.extend(&self.metadata.issuer_signature);
+    /// Canonical record identity, records with the same identity replace each other
+    pub fn get_identity(key_id: &str, issued_by: &str, peer_id: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [key_id, issued_by, peer_id] {
+            hasher.update([part.len() as u8]);
+            hasher.update(part.as_bytes());
+        }
+        bs58::encode(hasher.finalize()).into_string()
+    }
 
-        bytes.push(metadata.len() as u8);
-        bytes.append(&mut metadata);
+    pub fn identity(&self) -> String {
+        Self::get_identity(
+            &self.metadata.key_id,
+            &self.metadata.issued_by,
+            &self.metadata.peer_id,
+        )
+    }
 
+    /// Bytes signed by `peer_id`, they embed the metadata layout and the issuer signature,
+    /// so `issued_by` can't be rewritten without invalidating the `peer_id` signature
+    pub fn signature_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_field(&mut bytes, RECORD_SIGNATURE_DOMAIN.as_bytes());
+        push_field(&mut bytes, &self.metadata.signature_bytes());
+        push_field(&mut bytes, &self.metadata.issuer_signature);
         bytes.extend(self.timestamp_created.to_le_bytes());
+        bytes.extend(self.expires_at.to_le_bytes());
+
         let mut hasher = Sha256::new();
-        hasher.update(metadata);
+        hasher.update(bytes);
         hasher.finalize().to_vec()
     }
 
@@ -145,6 +241,7 @@ impl Record {
         let pk = extract_public_key(self.metadata.peer_id.clone())?;
         let bytes = self.signature_bytes();
         let signature = Signature::from_bytes(pk.get_key_format(), self.signature.clone());
+        // only the metadata layout has a legacy fallback, the legacy record layout didn't bind its fields
         pk.verify(&bytes, &signature).map_err(|e| {
             ServiceError::InvalidRecordSignature(
                 self.metadata.key_id.clone(),
@@ -13,16 +13,36 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+
+use std::cmp::Ordering;
+
+use crate::defaults::MAX_FEED_PAGE_LIMIT;
 use crate::error::ServiceError;
 use crate::error::ServiceError::MissingRecordWeight;
-use crate::misc::check_weight_result;
+use crate::merkle::{merkle_proof, merkle_root};
+use crate::misc::{
+    check_admin, check_min_weight, check_put_weight, check_record_limits, check_record_timestamps,
+    check_signature_format, check_value_encoding, check_weight_result, push_field, resolve_weight,
+};
+use crate::rate_limit;
 use crate::record::{Record, RecordInternal, RecordMetadata};
-use crate::record_storage_impl::merge_records;
-use crate::results::{GetRecordsResult, MergeResult, RegistryResult, RepublishRecordsResult};
-use crate::storage_impl::get_storage;
-use crate::tetraplets_checkers::{check_timestamp_tetraplets, check_weight_tetraplets};
+use crate::record_storage_impl::{
+    consensus_value, merge_records, merge_records_by_weight, rank_records, record_precedence,
+};
+use crate::results::{
+    AgePercentilesResult, AuthorTrustResult, BatchClearResult, CompareResult, ConsensusResult,
+    DigestResult, GetOverwriteLogResult, GetRecordsFreshnessResult, GetRecordsJsonResult,
+    GetRecordsOrEmptyResult, GetRecordsPageResult, GetRecordsResult, GetRecordsVerifiedResult,
+    HistogramResult, KeyClearItem, MergeResult, MerkleProofResult, MerkleResult,
+    PurgeInvalidResult, PurgeResult, PutIfBetterResult, PutRecordError, PutRecordsResult,
+    RegistryResult, RepublishDiffResult, RepublishMergedResult, RepublishRecordsResult,
+    ServiceIdsResult, WeightBucket,
+};
+use crate::storage_impl::{get_storage, Storage};
+use crate::tetraplets_checkers::{check_batch_tetraplets, check_timestamp_tetraplets};
 use crate::{load_config, wrapped_try, WeightResult};
 use marine_rs_sdk::marine;
+use sha2::{Digest, Sha256};
 
 #[marine]
 pub fn get_record_metadata_bytes(
@@ -84,6 +104,27 @@ pub fn get_record_bytes(metadata: RecordMetadata, timestamp_created: u64) -> Vec
     .signature_bytes()
 }
 
+/// Bytes to sign by the host for a record that expires at `expires_at`, see `put_expiring_record`
+#[marine]
+pub fn get_expiring_record_bytes(
+    metadata: RecordMetadata,
+    timestamp_created: u64,
+    expires_at: u64,
+) -> Vec<u8> {
+    Record {
+        metadata,
+        timestamp_created,
+        expires_at,
+        ..Default::default()
+    }
+    .signature_bytes()
+}
+
+#[marine]
+pub fn get_record_identity(key_id: String, issued_by: String, peer_id: String) -> String {
+    Record::get_identity(&key_id, &issued_by, &peer_id)
+}
+
 #[marine]
 pub fn put_record(
     metadata: RecordMetadata,
@@ -94,34 +135,400 @@ pub fn put_record(
 ) -> RegistryResult {
     wrapped_try(|| {
         let cp = marine_rs_sdk::get_call_parameters();
-        check_weight_tetraplets(&cp, 3, 0)?;
+        let weight = resolve_weight(&cp, 3, &cp.init_peer_id, &weight)?;
+        check_put_weight(&cp, weight)?;
         check_timestamp_tetraplets(&cp, 4)?;
-        check_weight_result(&cp.init_peer_id, &weight)?;
         let record = Record {
             metadata,
             timestamp_created,
+            expires_at: 0,
+            signature,
+        };
+        put_record_impl(&cp.init_peer_id, record, weight, current_timestamp_sec)
+    })
+    .into()
+}
+
+/// Like `put_record` but the record is dropped by `get_records` and `clear_expired` once `expires_at` is reached.
+/// `signature` should be made over `get_expiring_record_bytes`
+#[marine]
+pub fn put_expiring_record(
+    metadata: RecordMetadata,
+    timestamp_created: u64,
+    expires_at: u64,
+    signature: Vec<u8>,
+    weight: WeightResult,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let cp = marine_rs_sdk::get_call_parameters();
+        let weight = resolve_weight(&cp, 4, &cp.init_peer_id, &weight)?;
+        check_put_weight(&cp, weight)?;
+        check_timestamp_tetraplets(&cp, 5)?;
+        let record = Record {
+            metadata,
+            timestamp_created,
+            expires_at,
             signature,
         };
-        record.verify(current_timestamp_sec)?;
+        put_record_impl(&cp.init_peer_id, record, weight, current_timestamp_sec)
+    })
+    .into()
+}
+
+fn put_record_impl(
+    init_peer_id: &str,
+    record: Record,
+    weight: u32,
+    current_timestamp_sec: u64,
+) -> Result<(), ServiceError> {
+    let record = check_record_to_put(record, current_timestamp_sec)?;
+    rate_limit::register_put(init_peer_id, current_timestamp_sec)?;
+
+    let storage = get_storage()?;
+    storage.check_key_existence(&record.metadata.key_id)?;
+    let metadata = record.metadata.clone();
+    storage.update_record(RecordInternal { record, weight }, current_timestamp_sec)?;
+    storage.register_record_access(
+        &metadata.key_id,
+        &metadata.issued_by,
+        &metadata.peer_id,
+        current_timestamp_sec,
+    )
+}
+
+/// Like `put_record` but the record is stored only if it takes precedence over the stored record
+/// of the same identity: it's newer, or as new and heavier. Otherwise it's a no-op with `improved: false`
+#[marine]
+pub fn put_record_if_better(
+    metadata: RecordMetadata,
+    timestamp_created: u64,
+    signature: Vec<u8>,
+    weight: WeightResult,
+    current_timestamp_sec: u64,
+) -> PutIfBetterResult {
+    wrapped_try(|| {
+        let cp = marine_rs_sdk::get_call_parameters();
+        let weight = resolve_weight(&cp, 3, &cp.init_peer_id, &weight)?;
+        check_put_weight(&cp, weight)?;
+        check_timestamp_tetraplets(&cp, 4)?;
+        let record = check_record_to_put(
+            Record {
+                metadata,
+                timestamp_created,
+                expires_at: 0,
+                signature,
+            },
+            current_timestamp_sec,
+        )?;
+        rate_limit::register_put(&cp.init_peer_id, current_timestamp_sec)?;
 
         let storage = get_storage()?;
-        storage.check_key_existence(&record.metadata.key_id)?;
-        storage.update_record(RecordInternal {
-            record,
-            weight: weight.weight,
+        let metadata = record.metadata.clone();
+        storage.check_key_existence(&metadata.key_id)?;
+        storage.transaction(|storage| {
+            let record = RecordInternal { record, weight };
+            let stored =
+                storage.get_record(&metadata.key_id, &metadata.issued_by, &metadata.peer_id)?;
+            if let Some(stored) = stored {
+                if record_precedence(&record, &stored) != Ordering::Greater {
+                    return Ok(false);
+                }
+            }
+
+            storage.update_record(record, current_timestamp_sec)?;
+            storage.register_record_access(
+                &metadata.key_id,
+                &metadata.issued_by,
+                &metadata.peer_id,
+                current_timestamp_sec,
+            )?;
+            Ok(true)
         })
     })
     .into()
 }
 
-/// Return all values by key
+/// Put several records of one key in a single transaction. Each record is checked like in `put_record`
+/// against its weight `weights[i]`, rejected records are reported by index and don't abort the batch
+#[marine]
+pub fn put_records(
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> PutRecordsResult {
+    wrapped_try(|| {
+        if records.is_empty() {
+            return Ok((0, vec![]));
+        }
+        if weights.len() != records.len() {
+            return Err(ServiceError::WeightsCountMismatch(
+                records.len(),
+                weights.len(),
+            ));
+        }
+
+        let cp = marine_rs_sdk::get_call_parameters();
+        check_batch_tetraplets(&cp, 2, Some(1), records.len())?;
+        let key_id = records[0].metadata.key_id.clone();
+        if records.iter().any(|r| r.metadata.key_id != key_id) {
+            return Err(ServiceError::RecordsPublishingError);
+        }
+
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage.transaction(|storage| {
+            put_records_impl(
+                storage,
+                &cp.init_peer_id,
+                records,
+                weights,
+                current_timestamp_sec,
+            )
+        })
+    })
+    .into()
+}
+
+/// Verify and store each valid record, records may belong to different keys.
+/// Each record is checked like in `put_record` against its weight `weights[i]` and stored in its own savepoint
+/// of a single transaction, so rejected records are reported by index and the valid ones are committed
+#[marine]
+pub fn store_records_best_effort(
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> PutRecordsResult {
+    wrapped_try(|| {
+        if weights.len() != records.len() {
+            return Err(ServiceError::WeightsCountMismatch(
+                records.len(),
+                weights.len(),
+            ));
+        }
+
+        let cp = marine_rs_sdk::get_call_parameters();
+        check_batch_tetraplets(&cp, 2, Some(1), records.len())?;
+        get_storage()?.transaction(|storage| {
+            put_records_impl(
+                storage,
+                &cp.init_peer_id,
+                records,
+                weights,
+                current_timestamp_sec,
+            )
+        })
+    })
+    .into()
+}
+
+/// Put each of `records` in its own savepoint, return the number of stored records and errors of rejected ones
+fn put_records_impl(
+    storage: &Storage,
+    init_peer_id: &str,
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> Result<(u64, Vec<PutRecordError>), ServiceError> {
+    let call_parameters = marine_rs_sdk::get_call_parameters();
+    let mut stored = 0u64;
+    let mut errors = vec![];
+    for (i, (record, weight)) in records.into_iter().zip(weights.iter()).enumerate() {
+        let result = storage.savepoint(|storage| {
+            check_weight_result(&record.metadata.issued_by, weight)?;
+            check_put_weight(&call_parameters, weight.weight)?;
+            storage.check_key_existence(&record.metadata.key_id)?;
+            let record = check_record_to_put(record, current_timestamp_sec)?;
+            rate_limit::register_put(init_peer_id, current_timestamp_sec)?;
+            let metadata = record.metadata.clone();
+            storage.update_record(
+                RecordInternal {
+                    record,
+                    weight: weight.weight,
+                },
+                current_timestamp_sec,
+            )?;
+            storage.register_record_access(
+                &metadata.key_id,
+                &metadata.issued_by,
+                &metadata.peer_id,
+                current_timestamp_sec,
+            )
+        });
+
+        match result {
+            Ok(()) => stored += 1,
+            Err(error) => errors.push(PutRecordError {
+                index: i as u32,
+                error: error.to_string(),
+                error_code: error.code(),
+            }),
+        }
+    }
+
+    Ok((stored, errors))
+}
+
+/// Build a record to put and run all checks `put_record` applies to it, including the signatures
+fn check_record_to_put(record: Record, current_timestamp_sec: u64) -> Result<Record, ServiceError> {
+    let config = load_config();
+    let metadata = &record.metadata;
+    check_record_limits(metadata)?;
+    check_value_encoding(&metadata.key_id, &metadata.value, config.value_encoding)?;
+    if config.require_relay_id && metadata.relay_id.is_empty() {
+        return Err(ServiceError::MissingRelayId(metadata.key_id.clone()));
+    }
+    if config.strict_signatures {
+        check_signature_format(&metadata.issued_by, &metadata.issuer_signature)?;
+        check_signature_format(&metadata.peer_id, &record.signature)?;
+    }
+    let skewed_timestamp_sec = check_record_timestamps(&record, current_timestamp_sec)?;
+    record.verify(skewed_timestamp_sec)?;
+
+    Ok(record)
+}
+
+/// Replace the issuer's record only if it still holds `expected_value`, signatures of the new record are verified
+/// and the compare and the swap run in one transaction.
+/// Fails with `KeyImmutable` if the key is made immutable by `set_key_immutable`,
+/// with `RecordNotIssuedByCaller` if `expected_value` is held only by records of other authors,
+/// and with `RecordChangedSince` if no record holds it
+#[marine]
+pub fn cas_record(
+    metadata: RecordMetadata,
+    timestamp_created: u64,
+    signature: Vec<u8>,
+    weight: WeightResult,
+    expected_value: String,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let cp = marine_rs_sdk::get_call_parameters();
+        let weight = resolve_weight(&cp, 3, &cp.init_peer_id, &weight)?;
+        check_put_weight(&cp, weight)?;
+        check_timestamp_tetraplets(&cp, 5)?;
+        let record = check_record_to_put(
+            Record {
+                metadata,
+                timestamp_created,
+                expires_at: 0,
+                signature,
+            },
+            current_timestamp_sec,
+        )?;
+        rate_limit::register_put(&cp.init_peer_id, current_timestamp_sec)?;
+
+        let storage = get_storage()?;
+        let metadata = record.metadata.clone();
+        storage.check_key_existence(&metadata.key_id)?;
+        storage.transaction(|storage| {
+            if storage.is_key_immutable(&metadata.key_id)? {
+                return Err(ServiceError::KeyImmutable(metadata.key_id.clone()));
+            }
+            let holders = storage.get_records_by_value(
+                metadata.key_id.clone(),
+                expected_value,
+                current_timestamp_sec,
+            )?;
+            let is_current = holders.iter().any(|r| {
+                r.record.metadata.issued_by == metadata.issued_by
+                    && r.record.metadata.peer_id == metadata.peer_id
+            });
+            if !is_current {
+                return Err(match holders.first() {
+                    Some(other) => ServiceError::RecordNotIssuedByCaller(
+                        metadata.key_id.clone(),
+                        other.record.metadata.issued_by.clone(),
+                    ),
+                    None => ServiceError::RecordChangedSince(
+                        metadata.key_id.clone(),
+                        metadata.issued_by.clone(),
+                    ),
+                });
+            }
+
+            storage.update_record(RecordInternal { record, weight }, current_timestamp_sec)?;
+            storage.register_record_access(
+                &metadata.key_id,
+                &metadata.issued_by,
+                &metadata.peer_id,
+                current_timestamp_sec,
+            )
+        })
+    })
+    .into()
+}
+
+/// Put the record only if the stored record with its identity is created at `expected_timestamp_created`,
+/// `0` expects no stored record. All checks of `put_record` run before the comparison,
+/// on mismatch it fails with `CasConflict` reporting the stored `timestamp_created`
+#[marine]
+pub fn put_record_cas(
+    record: Record,
+    weight: WeightResult,
+    expected_timestamp_created: u64,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let cp = marine_rs_sdk::get_call_parameters();
+        let weight = resolve_weight(&cp, 1, &cp.init_peer_id, &weight)?;
+        check_put_weight(&cp, weight)?;
+        check_timestamp_tetraplets(&cp, 3)?;
+        let record = check_record_to_put(record, current_timestamp_sec)?;
+        rate_limit::register_put(&cp.init_peer_id, current_timestamp_sec)?;
+
+        let storage = get_storage()?;
+        let metadata = record.metadata.clone();
+        storage.check_key_existence(&metadata.key_id)?;
+        storage.transaction(|storage| {
+            let stored_timestamp_created = storage
+                .get_record(&metadata.key_id, &metadata.issued_by, &metadata.peer_id)?
+                .map_or(0, |r| r.record.timestamp_created);
+            if stored_timestamp_created != expected_timestamp_created {
+                return Err(ServiceError::CasConflict(
+                    metadata.key_id.clone(),
+                    metadata.issued_by.clone(),
+                    expected_timestamp_created,
+                    stored_timestamp_created,
+                ));
+            }
+
+            storage.update_record(RecordInternal { record, weight }, current_timestamp_sec)?;
+            storage.register_record_access(
+                &metadata.key_id,
+                &metadata.issued_by,
+                &metadata.peer_id,
+                current_timestamp_sec,
+            )
+        })
+    })
+    .into()
+}
+
+/// Return all values by key. With `self_heal` enabled records left under a missing key are deleted
 #[marine]
 pub fn get_records(key_id: String, current_timestamp_sec: u64) -> GetRecordsResult {
     wrapped_try(|| {
         let call_parameters = marine_rs_sdk::get_call_parameters();
         check_timestamp_tetraplets(&call_parameters, 1)?;
         let storage = get_storage()?;
-        storage.check_key_existence(&key_id)?;
+        if let Err(error) = storage.check_key_existence(&key_id) {
+            if load_config().self_heal {
+                match storage.delete_records_by_key(&key_id) {
+                    // healing shouldn't break reads
+                    Err(ServiceError::StorageReadOnly) => {}
+                    other => {
+                        other?;
+                    }
+                }
+            }
+            return Err(error);
+        }
+        match storage.register_key_access(&key_id, current_timestamp_sec) {
+            // access tracking shouldn't break reads
+            Err(ServiceError::StorageReadOnly) => {}
+            other => other?,
+        }
         storage
             .get_records(key_id, current_timestamp_sec)
             .map(|records| records.into_iter().map(|r| r.record).collect())
@@ -129,94 +536,1271 @@ pub fn get_records(key_id: String, current_timestamp_sec: u64) -> GetRecordsResu
     .into()
 }
 
-/// Return all values by key
+/// `get_records` that succeeds with no records if the key doesn't exist, saves a `key_exists` probe
 #[marine]
-pub fn get_stale_local_records(current_timestamp_sec: u64) -> GetRecordsResult {
+pub fn get_records_or_empty(key_id: String, current_timestamp_sec: u64) -> GetRecordsOrEmptyResult {
     wrapped_try(|| {
         let call_parameters = marine_rs_sdk::get_call_parameters();
-        check_timestamp_tetraplets(&call_parameters, 0)?;
+        check_timestamp_tetraplets(&call_parameters, 1)?;
         let storage = get_storage()?;
+        match storage.check_key_existence(&key_id) {
+            Err(ServiceError::KeyNotExists(_)) => return Ok((false, vec![])),
+            other => other?,
+        }
+        match storage.register_key_access(&key_id, current_timestamp_sec) {
+            // access tracking shouldn't break reads
+            Err(ServiceError::StorageReadOnly) => {}
+            other => other?,
+        }
+        let records = storage.get_records(key_id, current_timestamp_sec)?;
+        Ok((true, records.into_iter().map(|r| r.record).collect()))
+    })
+    .into()
+}
 
-        // TODO: add some meaningful constant for expiring local records
-        let stale_timestamp_sec = current_timestamp_sec - load_config().expired_timeout + 100;
-        storage
-            .get_local_stale_records(stale_timestamp_sec)
-            .map(|records| records.into_iter().map(|r| r.record).collect())
+/// Return the total and average weight of actual records issued by `peer_id` across all keys,
+/// a coarse reputation signal of the author
+#[marine]
+pub fn get_author_trust(peer_id: String, current_timestamp_sec: u64) -> AuthorTrustResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        get_storage()?.get_author_weight_sum(&peer_id, current_timestamp_sec)
     })
     .into()
 }
 
-/// If the key exists, then merge new records with existing (last-write-wins) and put
+/// Return p50, p90 and p99 of ages of actual records of the key, useful for tuning the expiration timeout
 #[marine]
-pub fn republish_records(
-    records: Vec<Record>,
-    weights: Vec<WeightResult>,
-    current_timestamp_sec: u64,
-) -> RepublishRecordsResult {
+pub fn get_age_percentiles(key_id: String, current_timestamp_sec: u64) -> AgePercentilesResult {
     wrapped_try(|| {
-        if records.is_empty() {
-            return Ok(0);
-        }
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let mut ages: Vec<u64> = storage
+            .get_records(key_id, current_timestamp_sec)?
+            .into_iter()
+            .map(|r| current_timestamp_sec.saturating_sub(r.record.timestamp_created))
+            .collect();
+        ages.sort_unstable();
+        Ok(ages)
+    })
+    .into()
+}
 
-        let key_id = records[0].metadata.key_id.clone();
+/// Return counts of actual records of the key per weight bucket of `bucket_size`, empty buckets are omitted
+#[marine]
+pub fn get_weight_histogram(
+    key_id: String,
+    bucket_size: u32,
+    current_timestamp_sec: u64,
+) -> HistogramResult {
+    wrapped_try(|| {
         let call_parameters = marine_rs_sdk::get_call_parameters();
         check_timestamp_tetraplets(&call_parameters, 2)?;
-        let mut records_to_merge = vec![];
+        if bucket_size == 0 {
+            return Err(ServiceError::InvalidBucketSize);
+        }
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_weight_histogram(&key_id, bucket_size, current_timestamp_sec)
+            .map(|buckets| {
+                buckets
+                    .into_iter()
+                    .map(|(lower_bound, count)| WeightBucket { lower_bound, count })
+                    .collect()
+            })
+    })
+    .into()
+}
 
-        for (i, record) in records.into_iter().enumerate() {
-            record.verify(current_timestamp_sec)?;
-            check_weight_tetraplets(&call_parameters, 1, i)?;
-            let weight_result = weights.get(i).ok_or_else(|| {
-                MissingRecordWeight(
-                    record.metadata.peer_id.clone(),
-                    record.metadata.issued_by.clone(),
-                )
-            })?;
-            check_weight_result(&record.metadata.issued_by, weight_result)?;
-            if record.metadata.key_id != key_id {
-                return Err(ServiceError::RecordsPublishingError);
-            }
+/// Return up to `limit` (`0` means all) actual records hosted by this node across all keys starting from `offset`
+/// and their total number, e.g. to re-announce them after a restart.
+/// Unlike `get_records` it doesn't count as an access of the keys, so it doesn't keep them from GC
+#[marine]
+pub fn get_host_records(
+    current_timestamp_sec: u64,
+    offset: u32,
+    limit: u32,
+) -> GetRecordsPageResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        get_storage()?
+            .get_host_records(
+                call_parameters.host_id,
+                current_timestamp_sec,
+                offset,
+                limit,
+            )
+            .map(|(records, total)| (records.into_iter().map(|r| r.record).collect(), total))
+    })
+    .into()
+}
 
-            records_to_merge.push(RecordInternal {
-                record,
-                weight: weight_result.weight,
-            });
+/// Return `limit` actual records of the key starting from `offset` and the total number of actual records.
+/// Records are ordered by weight desc, timestamp_created desc, then peer_id and issued_by, so pages are consistent
+#[marine]
+pub fn get_records_page(
+    key_id: String,
+    current_timestamp_sec: u64,
+    offset: u32,
+    limit: u32,
+) -> GetRecordsPageResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        if limit == 0 {
+            return Err(ServiceError::InvalidPageLimit);
         }
-
         let storage = get_storage()?;
         storage.check_key_existence(&key_id)?;
-        storage.merge_and_update_records(key_id, records_to_merge, current_timestamp_sec)
+        match storage.register_key_access(&key_id, current_timestamp_sec) {
+            // access tracking shouldn't break reads
+            Err(ServiceError::StorageReadOnly) => {}
+            other => other?,
+        }
+
+        let mut records = storage.get_records(key_id, current_timestamp_sec)?;
+        records.sort_by(|l, r| {
+            r.weight
+                .cmp(&l.weight)
+                .then_with(|| r.record.timestamp_created.cmp(&l.record.timestamp_created))
+                .then_with(|| l.record.metadata.peer_id.cmp(&r.record.metadata.peer_id))
+                .then_with(|| {
+                    l.record
+                        .metadata
+                        .issued_by
+                        .cmp(&r.record.metadata.issued_by)
+                })
+        });
+        let total = records.len() as u32;
+        let page = records
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|r| r.record)
+            .collect();
+
+        Ok((page, total))
     })
     .into()
 }
 
+/// Return actual records with their signatures re-verified at read time, so the caller doesn't have to trust the storage.
+/// Records are verified like puts, so ones created within the clock skew window are verified too.
+/// Verifies every record, so it's heavier than `get_records`
 #[marine]
-pub fn merge_two(a: Vec<Record>, b: Vec<Record>) -> MergeResult {
-    merge_records(
-        a.into_iter()
-            .chain(b)
-            .map(|record| RecordInternal {
-                record,
-                ..Default::default()
+pub fn get_records_verified(
+    key_id: String,
+    current_timestamp_sec: u64,
+) -> GetRecordsVerifiedResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let skewed_timestamp_sec =
+            current_timestamp_sec.saturating_add(load_config().max_clock_skew_sec);
+        storage
+            .get_records(key_id, current_timestamp_sec)
+            .map(|records| {
+                records
+                    .into_iter()
+                    .map(|r| {
+                        let verified = r.record.verify(skewed_timestamp_sec).is_ok();
+                        (r.record, verified)
+                    })
+                    .collect()
             })
-            .collect(),
-    )
-    .map(|recs| recs.into_iter().map(|r| r.record).collect())
+    })
     .into()
 }
 
+/// Return the heaviest records until their total weight reaches `weight_budget`
 #[marine]
-pub fn merge(records: Vec<Vec<Record>>) -> MergeResult {
-    merge_records(
-        records
-            .into_iter()
-            .flatten()
-            .map(|record| RecordInternal {
-                record,
-                ..Default::default()
-            })
-            .collect(),
-    )
-    .map(|recs| recs.into_iter().map(|r| r.record).collect())
+pub fn get_records_weight_budget(
+    key_id: String,
+    weight_budget: u32,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+
+        let mut total_weight = 0u64;
+        let mut result = vec![];
+        // records are sorted by weight descending
+        for record in storage.get_records(key_id, current_timestamp_sec)? {
+            if total_weight >= weight_budget as u64 {
+                break;
+            }
+            total_weight += record.weight as u64;
+            result.push(record.record);
+        }
+
+        Ok(result)
+    })
+    .into()
+}
+
+/// Return all values by key and whether the key is stale, so the client may ask another node
+#[marine]
+pub fn get_records_with_freshness(
+    key_id: String,
+    current_timestamp_sec: u64,
+) -> GetRecordsFreshnessResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        let key = storage.get_internal_key(key_id.clone())?;
+        let stale_timestamp = current_timestamp_sec.saturating_sub(load_config().stale_timeout);
+        let records = storage
+            .get_records(key_id, current_timestamp_sec)?
+            .into_iter()
+            .map(|r| r.record)
+            .collect();
+
+        Ok((records, key.timestamp_published <= stale_timestamp))
+    })
+    .into()
+}
+
+/// Return the value supported by the highest total weight of records
+#[marine]
+pub fn get_consensus_value(key_id: String, current_timestamp_sec: u64) -> ConsensusResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let records = storage.get_records(key_id.clone(), current_timestamp_sec)?;
+        consensus_value(records).ok_or(ServiceError::NoConsensusRecords(key_id))
+    })
+    .into()
+}
+
+/// Return values by key issued by any of `issued_by` peers
+#[marine]
+pub fn get_records_by_authors(
+    key_id: String,
+    issued_by: Vec<String>,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        if issued_by.is_empty() {
+            return Err(ServiceError::AuthorsArgumentEmpty);
+        }
+
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records_by_authors(key_id, issued_by, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return values by key with a service id among `service_id` issued by any of `issued_by`,
+/// an empty list doesn't filter. Counts as an access of the key like `get_records`
+#[marine]
+pub fn get_records_filtered(
+    key_id: String,
+    current_timestamp_sec: u64,
+    service_id: Vec<String>,
+    issued_by: Vec<String>,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        match storage.register_key_access(&key_id, current_timestamp_sec) {
+            // access tracking shouldn't break reads
+            Err(ServiceError::StorageReadOnly) => {}
+            other => other?,
+        }
+        storage
+            .get_records_filtered(key_id, current_timestamp_sec, service_id, issued_by)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return values by key except ones issued by `exclude_issued_by`, e.g. a blocked peer
+#[marine]
+pub fn get_records_excluding_author(
+    key_id: String,
+    exclude_issued_by: String,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records_excluding_author(key_id, exclude_issued_by, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return `limit` actual records across all keys starting from `offset`, newest first.
+/// `limit` is capped by `MAX_FEED_PAGE_LIMIT`, reads don't count as accesses
+#[marine]
+pub fn get_global_feed(offset: u32, limit: u32, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        if limit == 0 {
+            return Err(ServiceError::InvalidPageLimit);
+        }
+        get_storage()?
+            .get_global_feed(
+                offset,
+                limit.min(MAX_FEED_PAGE_LIMIT),
+                current_timestamp_sec,
+            )
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return a page of actual records issued by `issued_by` across all keys, e.g. to find spammy issuers.
+/// `limit` is capped by `MAX_FEED_PAGE_LIMIT`, key access isn't tracked
+#[marine]
+pub fn get_records_by_issuer(
+    issued_by: String,
+    current_timestamp_sec: u64,
+    offset: u32,
+    limit: u32,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        if limit == 0 {
+            return Err(ServiceError::InvalidPageLimit);
+        }
+        get_storage()?
+            .get_records_by_issuer(
+                issued_by,
+                offset,
+                limit.min(MAX_FEED_PAGE_LIMIT),
+                current_timestamp_sec,
+            )
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Diagnostic read returning values by key in the order they were written to the storage,
+/// independent of their signed timestamps
+#[marine]
+pub fn get_records_by_insertion(key_id: String, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records_by_insertion(key_id, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return values by key created within `[from_ts, to_ts]`, e.g. what was published yesterday
+#[marine]
+pub fn get_records_in_window(
+    key_id: String,
+    from_ts: u64,
+    to_ts: u64,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 3)?;
+        if from_ts > to_ts {
+            return Err(ServiceError::InvalidTimeWindow(from_ts, to_ts));
+        }
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        match storage.register_key_access(&key_id, current_timestamp_sec) {
+            // access tracking shouldn't break reads
+            Err(ServiceError::StorageReadOnly) => {}
+            other => other?,
+        }
+        storage
+            .get_records_in_window(key_id, from_ts, to_ts, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return actual records of the key as a JSON array of objects with only `fields` of each record
+#[marine]
+pub fn get_records_json(
+    key_id: String,
+    fields: Vec<String>,
+    current_timestamp_sec: u64,
+) -> GetRecordsJsonResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let records = storage
+            .get_records(key_id, current_timestamp_sec)?
+            .iter()
+            .map(|record| record.project(&fields))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(serde_json::Value::Array(records).to_string())
+    })
+    .into()
+}
+
+/// Merkle root over sorted identities of the key's actual records
+#[marine]
+pub fn get_key_merkle_root(key_id: String, current_timestamp_sec: u64) -> MerkleResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let identities = get_record_identities(&key_id, current_timestamp_sec)?;
+        let leaves_count = identities.len() as u64;
+        Ok((merkle_root(identities), leaves_count))
+    })
+    .into()
+}
+
+/// Inclusion path of the record with `identity` leading to the root returned by `get_key_merkle_root`
+#[marine]
+pub fn get_record_merkle_proof(
+    key_id: String,
+    identity: String,
+    current_timestamp_sec: u64,
+) -> MerkleProofResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let identities = get_record_identities(&key_id, current_timestamp_sec)?;
+        merkle_proof(identities, &identity)
+            .ok_or(ServiceError::RecordIdentityNotFound(key_id, identity))
+    })
+    .into()
+}
+
+fn get_record_identities(
+    key_id: &str,
+    current_timestamp_sec: u64,
+) -> Result<Vec<String>, ServiceError> {
+    let storage = get_storage()?;
+    storage.check_key_existence(key_id)?;
+    Ok(storage
+        .get_records(key_id.to_string(), current_timestamp_sec)?
+        .into_iter()
+        .map(|r| r.record.identity())
+        .collect())
+}
+
+/// Digest of versions of the key's actual records: unlike the merkle root, it changes when a record is replaced
+fn get_key_digest(key_id: &str, current_timestamp_sec: u64) -> Result<String, ServiceError> {
+    let storage = get_storage()?;
+    storage.check_key_existence(key_id)?;
+    let records = storage.get_records(key_id.to_string(), current_timestamp_sec)?;
+    Ok(records_digest(records))
+}
+
+fn records_digest(mut records: Vec<RecordInternal>) -> String {
+    records.sort_by_cached_key(|r| r.record.identity());
+
+    let mut bytes = Vec::new();
+    for record in records.iter() {
+        push_field(&mut bytes, record.record.identity().as_bytes());
+        bytes.extend(record.record.timestamp_created.to_le_bytes());
+        push_field(&mut bytes, &record.record.signature);
+    }
+    bs58::encode(Sha256::digest(&bytes)).into_string()
+}
+
+/// Merkle root over all keys, each leaf commits to the key version and the digest of its actual records.
+/// Equal on replicas holding the same data regardless of the order it was stored in, empty if there are no keys
+#[marine]
+pub fn get_store_digest(current_timestamp_sec: u64) -> DigestResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        let storage = get_storage()?;
+        let mut leaves = vec![];
+        for key in storage.get_all_keys()?.into_iter() {
+            let key = key.key;
+            let records = storage.get_records(key.id.clone(), current_timestamp_sec)?;
+            leaves.push(format!(
+                "{}:{}:{}",
+                key.id,
+                key.timestamp_created,
+                records_digest(records)
+            ));
+        }
+        Ok(merkle_root(leaves))
+    })
+    .into()
+}
+
+/// Compare the digest of the key's records with `remote_digest` computed by another node,
+/// a mismatch means the replicas should be synced
+#[marine]
+pub fn compare_digest(
+    key_id: String,
+    remote_digest: String,
+    current_timestamp_sec: u64,
+) -> CompareResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let local_digest = get_key_digest(&key_id, current_timestamp_sec)?;
+        Ok((local_digest == remote_digest, local_digest))
+    })
+    .into()
+}
+
+/// Return records of the key published via `relay_id` and created at or after `since`
+#[marine]
+pub fn get_records_via_relay_since(
+    key_id: String,
+    relay_id: String,
+    since: u64,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 3)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records_via_relay_since(key_id, relay_id, since, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return records of the key created no more than `max_age_sec` before `current_timestamp_sec`
+#[marine]
+pub fn get_records_max_age(
+    key_id: String,
+    max_age_sec: u64,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records_max_age(key_id, max_age_sec, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return records of the key issued by the key owner, for owner-authoritative keys
+#[marine]
+pub fn get_owner_records(key_id: String, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_owner_records(key_id, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return all records of the key with exactly `value`, possibly from different authors
+#[marine]
+pub fn get_records_by_value(
+    key_id: String,
+    value: String,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records_by_value(key_id, value, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return distinct service ids announced in records of all keys, `limit == 0` means no limit
+#[marine]
+pub fn get_all_service_ids(limit: u32, current_timestamp_sec: u64) -> ServiceIdsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        get_storage()?.get_all_service_ids(limit, current_timestamp_sec)
+    })
+    .into()
+}
+
+/// Return only `n` records with the highest weight
+#[marine]
+pub fn get_top_records(key_id: String, n: u32, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_top_records(key_id, n, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return `n` (`0` means all) most recently created records, newest first or oldest first if `ascending`
+#[marine]
+pub fn get_recent_records(
+    key_id: String,
+    n: u32,
+    ascending: bool,
+    current_timestamp_sec: u64,
+) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 3)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let mut records: Vec<Record> = storage
+            .get_recent_records(key_id, n, current_timestamp_sec)?
+            .into_iter()
+            .map(|r| r.record)
+            .collect();
+        if ascending {
+            records.reverse();
+        }
+
+        Ok(records)
+    })
+    .into()
+}
+
+/// Return actual records ranked by `rank_records`: the best record of every author first,
+/// then the rest, both parts ordered by weight and then by recency
+#[marine]
+pub fn get_records_ranked(key_id: String, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage
+            .get_records(key_id, current_timestamp_sec)
+            .map(|records| {
+                rank_records(records)
+                    .into_iter()
+                    .map(|r| r.record)
+                    .collect()
+            })
+    })
+    .into()
+}
+
+/// Return up to `limit` records (`0` means all) of keys due for republishing,
+/// i.e. not republished for the stale timeout. Records are grouped by key
+#[marine]
+pub fn get_republish_queue(limit: u32, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let stale_timestamp = current_timestamp_sec.saturating_sub(load_config().stale_timeout);
+        get_storage()?
+            .get_records_of_stale_keys(limit, stale_timestamp, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return all values by key
+#[marine]
+pub fn get_stale_local_records(current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        let storage = get_storage()?;
+
+        // TODO: add some meaningful constant for expiring local records
+        let stale_timestamp_sec = current_timestamp_sec - load_config().expired_timeout + 100;
+        storage
+            .get_local_stale_records(stale_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// If the key exists, then merge new records with existing (last-write-wins) and put.
+/// Tetraplets are checked once for the whole batch: weights at argument 1, timestamp at argument 2.
+/// `updated` counts only records whose stored state changed, i.e. new records and replaced versions,
+/// records already stored as they are aren't counted.
+/// Records created up to `max_clock_skew_sec` in the future and not earlier than `max_record_age_sec` ago
+/// are accepted, others are skipped and counted.
+/// Lowest-priority non-host records are evicted and counted if the merge exceeds the key's records limit
+#[marine]
+pub fn republish_records(
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> RepublishRecordsResult {
+    wrapped_try(|| {
+        if records.is_empty() {
+            return Ok((0, 0, 0));
+        }
+
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_batch_tetraplets(&call_parameters, 2, Some(1), records.len())?;
+        let storage = get_storage()?;
+        storage.transaction(|storage| {
+            republish_records_impl(storage, records, weights, current_timestamp_sec)
+        })
+    })
+    .into()
+}
+
+/// `republish_records` also returning the key's records stored after the merge, evictions included,
+/// so they can be forwarded further without a `get_records` call
+#[marine]
+pub fn republish_records_merged(
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> RepublishMergedResult {
+    wrapped_try(|| {
+        if records.is_empty() {
+            return Ok(((0, 0, 0), vec![]));
+        }
+
+        let key_id = records[0].metadata.key_id.clone();
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_batch_tetraplets(&call_parameters, 2, Some(1), records.len())?;
+        let storage = get_storage()?;
+        storage.transaction(|storage| {
+            let counts = republish_records_impl(storage, records, weights, current_timestamp_sec)?;
+            let merged = storage
+                .get_records(key_id, current_timestamp_sec)?
+                .into_iter()
+                .map(|r| r.record)
+                .collect();
+            Ok((counts, merged))
+        })
+    })
+    .into()
+}
+
+/// Diagnostic variant of `republish_records` returning identities of the key's records before and after the merge.
+/// Both reads and the merge run in one transaction, so the diff isn't mixed with concurrent writes.
+/// Reads the key's records twice, so it shouldn't be used in the regular republishing flow
+#[marine]
+pub fn republish_records_diff(
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> RepublishDiffResult {
+    wrapped_try(|| {
+        if records.is_empty() {
+            return Ok((vec![], vec![]));
+        }
+
+        let key_id = records[0].metadata.key_id.clone();
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_batch_tetraplets(&call_parameters, 2, Some(1), records.len())?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let get_identities = |storage: &Storage| -> Result<Vec<String>, ServiceError> {
+            Ok(storage
+                .get_records(key_id.clone(), current_timestamp_sec)?
+                .into_iter()
+                .map(|r| r.record.identity())
+                .collect())
+        };
+
+        storage.transaction(|storage| {
+            let before = get_identities(storage)?;
+            republish_records_impl(storage, records, weights, current_timestamp_sec)?;
+            let after = get_identities(storage)?;
+            Ok((before, after))
+        })
+    })
+    .into()
+}
+
+/// Verify and merge non-empty `records` of one key into the storage, return (updated, skipped, evicted) counts
+fn republish_records_impl(
+    storage: &Storage,
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> Result<(u64, u64, u64), ServiceError> {
+    let key_id = records[0].metadata.key_id.clone();
+    let mut records_to_merge = vec![];
+    let mut skipped = 0u64;
+
+    for (i, record) in records.into_iter().enumerate() {
+        let skewed_timestamp_sec = match check_record_timestamps(&record, current_timestamp_sec) {
+            Ok(skewed_timestamp_sec) => skewed_timestamp_sec,
+            Err(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+        check_record_limits(&record.metadata)?;
+        record.verify(skewed_timestamp_sec)?;
+        let weight_result = weights.get(i).ok_or_else(|| {
+            MissingRecordWeight(
+                record.metadata.peer_id.clone(),
+                record.metadata.issued_by.clone(),
+            )
+        })?;
+        check_weight_result(&record.metadata.issued_by, weight_result)?;
+        if record.metadata.key_id != key_id {
+            return Err(ServiceError::RecordsPublishingError);
+        }
+        // replicated records aren't put by the host, so the host bypass doesn't apply
+        if check_min_weight(weight_result.weight).is_err() {
+            skipped += 1;
+            continue;
+        }
+
+        records_to_merge.push(RecordInternal {
+            record,
+            weight: weight_result.weight,
+        });
+    }
+
+    storage.check_key_existence(&key_id)?;
+    storage
+        .merge_and_update_records(key_id, records_to_merge, current_timestamp_sec)
+        .map(|(updated, evicted)| (updated, skipped, evicted))
+}
+
+/// Move records issued by the caller from `from_key_id` to `to_key_id`, both keys should be owned by the caller.
+/// Key id is signed as a part of a record, so `records` are the caller's records re-signed for `to_key_id`.
+/// Only records with the identities of `records` are removed from `from_key_id`, the others stay in place.
+/// Moved records are checked like puts
+#[marine]
+pub fn move_records(
+    from_key_id: String,
+    to_key_id: String,
+    records: Vec<Record>,
+    weights: Vec<WeightResult>,
+    current_timestamp_sec: u64,
+) -> RepublishRecordsResult {
+    wrapped_try(|| {
+        if records.is_empty() {
+            return Err(ServiceError::RecordsArgumentEmpty);
+        }
+
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_batch_tetraplets(&call_parameters, 4, Some(3), records.len())?;
+        let init_peer_id = call_parameters.init_peer_id.clone();
+
+        let storage = get_storage()?;
+        storage.check_key_owner(&from_key_id, &init_peer_id)?;
+        storage.check_key_owner(&to_key_id, &init_peer_id)?;
+
+        let mut records_to_move = vec![];
+        for (i, record) in records.into_iter().enumerate() {
+            if record.metadata.key_id != to_key_id {
+                return Err(ServiceError::RecordsPublishingError);
+            }
+            if record.metadata.issued_by != init_peer_id {
+                return Err(ServiceError::RecordNotIssuedByCaller(
+                    record.metadata.key_id,
+                    record.metadata.issued_by,
+                ));
+            }
+            let record = check_record_to_put(record, current_timestamp_sec)?;
+            let weight_result = weights.get(i).ok_or_else(|| {
+                MissingRecordWeight(
+                    record.metadata.peer_id.clone(),
+                    record.metadata.issued_by.clone(),
+                )
+            })?;
+            check_weight_result(&record.metadata.issued_by, weight_result)?;
+            check_put_weight(&call_parameters, weight_result.weight)?;
+
+            records_to_move.push(RecordInternal {
+                record,
+                weight: weight_result.weight,
+            });
+        }
+
+        storage.transaction(|storage| {
+            let moved = records_to_move.len() as u64;
+            for record in records_to_move.into_iter() {
+                storage.delete_record(
+                    from_key_id.clone(),
+                    record.record.metadata.peer_id.clone(),
+                    record.record.metadata.issued_by.clone(),
+                )?;
+                storage.update_record(record, current_timestamp_sec)?;
+            }
+
+            Ok(moved)
+        })
+    })
+    .into()
+}
+
+/// Remove the caller's records by key created before `before_timestamp_created`.
+/// Pinned keys are exempt, nothing is removed from them
+#[marine]
+pub fn clear_records_before(
+    key_id: String,
+    before_timestamp_created: u64,
+    current_timestamp_sec: u64,
+) -> PurgeResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        // records are created no later than the clock skew window
+        let skewed_timestamp_sec =
+            current_timestamp_sec.saturating_add(load_config().max_clock_skew_sec);
+        let before_timestamp_created =
+            before_timestamp_created.min(skewed_timestamp_sec.saturating_add(1));
+        storage.delete_records_before(
+            &key_id,
+            &call_parameters.init_peer_id,
+            before_timestamp_created,
+        )
+    })
+    .into()
+}
+
+/// Mark the caller's records by key as accessed at `new_timestamp_accessed` without re-signing them,
+/// so they are kept by the LeastRecentlyAccessed eviction. If any record is refreshed, the key is marked
+/// as accessed too, so `clear_stale_keys` keeps it. Signed fields including `timestamp_created`
+/// are not changed, so signed-age expiry still applies and it doesn't prevent `clear_expired`.
+/// Accesses in the future are capped by `current_timestamp_sec`
+#[marine]
+pub fn refresh_peer_records(
+    key_id: String,
+    new_timestamp_accessed: u64,
+    current_timestamp_sec: u64,
+) -> RepublishRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        let timestamp_accessed = new_timestamp_accessed.min(current_timestamp_sec);
+        storage.transaction(|storage| {
+            let refreshed = storage.refresh_records_access(
+                &key_id,
+                &call_parameters.init_peer_id,
+                timestamp_accessed,
+                current_timestamp_sec,
+            )?;
+            if refreshed > 0 {
+                storage.touch_key_access(&key_id, timestamp_accessed)?;
+            }
+
+            Ok(refreshed)
+        })
+    })
+    .into()
+}
+
+/// Remove the caller's records by key with `value`, records of other issuers aren't affected.
+/// Fails with `RecordNotFound` if the caller has no such records
+#[marine]
+pub fn clear_record(key_id: String, value: String, current_timestamp_sec: u64) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let issued_by = call_parameters.init_peer_id;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage.transaction(|storage| {
+            let records: Vec<RecordInternal> = storage
+                .get_records_by_value(key_id.clone(), value.clone(), current_timestamp_sec)?
+                .into_iter()
+                .filter(|r| r.record.metadata.issued_by == issued_by)
+                .collect();
+            if records.is_empty() {
+                return Err(ServiceError::RecordNotFound(
+                    key_id.clone(),
+                    issued_by.clone(),
+                    value.clone(),
+                ));
+            }
+
+            for record in records.into_iter() {
+                let metadata = record.record.metadata;
+                storage.delete_record(metadata.key_id, metadata.peer_id, metadata.issued_by)?;
+            }
+
+            storage.register_key_access(&key_id, current_timestamp_sec)
+        })
+    })
+    .into()
+}
+
+/// Remove the caller's records by key with `value` only if they are still created at `expected_timestamp_created`,
+/// otherwise nothing is removed and `RecordChangedSince` is returned
+#[marine]
+pub fn clear_record_if(
+    key_id: String,
+    value: String,
+    expected_timestamp_created: u64,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 3)?;
+        let issued_by = call_parameters.init_peer_id;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+        storage.transaction(|storage| {
+            let records: Vec<RecordInternal> = storage
+                .get_records_by_value(key_id.clone(), value, current_timestamp_sec)?
+                .into_iter()
+                .filter(|r| r.record.metadata.issued_by == issued_by)
+                .collect();
+            if records.is_empty()
+                || records
+                    .iter()
+                    .any(|r| r.record.timestamp_created != expected_timestamp_created)
+            {
+                return Err(ServiceError::RecordChangedSince(
+                    key_id.clone(),
+                    issued_by.clone(),
+                ));
+            }
+
+            for record in records.into_iter() {
+                let metadata = record.record.metadata;
+                storage.delete_record(metadata.key_id, metadata.peer_id, metadata.issued_by)?;
+            }
+
+            Ok(())
+        })
+    })
+    .into()
+}
+
+#[marine]
+pub fn merge_two(a: Vec<Record>, b: Vec<Record>) -> MergeResult {
+    merge_records(
+        a.into_iter()
+            .chain(b)
+            .map(|record| RecordInternal {
+                record,
+                ..Default::default()
+            })
+            .collect(),
+    )
+    .map(|recs| recs.into_iter().map(|r| r.record).collect())
+    .into()
+}
+
+#[marine]
+pub fn merge(records: Vec<Vec<Record>>) -> MergeResult {
+    merge_records(
+        records
+            .into_iter()
+            .flatten()
+            .map(|record| RecordInternal {
+                record,
+                ..Default::default()
+            })
+            .collect(),
+    )
+    .map(|recs| recs.into_iter().map(|r| r.record).collect())
+    .into()
+}
+
+/// Merge records where the heavier version of a record wins regardless of its timestamp,
+/// `weights[i]` is the weight of `records[i]`
+#[marine]
+pub fn merge_by_weight(records: Vec<Record>, weights: Vec<u32>) -> MergeResult {
+    wrapped_try(|| {
+        if weights.len() != records.len() {
+            return Err(ServiceError::WeightsCountMismatch(
+                records.len(),
+                weights.len(),
+            ));
+        }
+
+        merge_records_by_weight(
+            records
+                .into_iter()
+                .zip(weights)
+                .map(|(record, weight)| RecordInternal { record, weight })
+                .collect(),
+        )
+        .map(|recs| recs.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Return up to `limit` latest superseded record versions of the key, newest first.
+/// Overwrites are logged only while enabled with `set_overwrite_log`
+#[marine]
+pub fn get_overwrite_log(key_id: String, limit: u32) -> GetOverwriteLogResult {
+    wrapped_try(|| get_storage()?.get_overwrite_log(key_id, limit)).into()
+}
+
+/// Re-verify up to `limit` stored records (`0` means all) after `after_rowid` and delete the ones failing verification.
+/// Records are verified like puts, so ones created within the clock skew window stay.
+/// Expired records are left to `clear_expired`. Returns the number of purged records and the rowid to continue from.
+/// Can be called only by the host or the service creator
+#[marine]
+pub fn purge_invalid_records(
+    current_timestamp_sec: u64,
+    after_rowid: u64,
+    limit: u32,
+) -> PurgeInvalidResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        check_admin(&call_parameters)?;
+        let skewed_timestamp_sec =
+            current_timestamp_sec.saturating_add(load_config().max_clock_skew_sec);
+
+        let storage = get_storage()?;
+        storage.transaction(|storage| {
+            let records = storage.get_all_records(after_rowid, limit)?;
+            let next_rowid = match records.last() {
+                Some((rowid, _)) if limit != 0 && records.len() == limit as usize => *rowid,
+                _ => 0,
+            };
+
+            let mut purged = 0u64;
+            for (_, record) in records.into_iter() {
+                match record.record.verify(skewed_timestamp_sec) {
+                    Ok(()) | Err(ServiceError::RecordAlreadyExpired) => {}
+                    Err(_) => {
+                        let metadata = record.record.metadata;
+                        storage.delete_record(
+                            metadata.key_id,
+                            metadata.peer_id,
+                            metadata.issued_by,
+                        )?;
+                        purged += 1;
+                    }
+                }
+            }
+
+            Ok((purged, next_rowid))
+        })
+    })
+    .into()
+}
+
+/// Return up to `limit` actual records (`0` means all) whose key doesn't exist
+#[marine]
+pub fn find_orphaned_records(limit: u32, current_timestamp_sec: u64) -> GetRecordsResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        get_storage()?
+            .find_orphaned_records(limit, current_timestamp_sec)
+            .map(|records| records.into_iter().map(|r| r.record).collect())
+    })
+    .into()
+}
+
+/// Delete all records and tombstones whose key doesn't exist. Can be called only by the host or the service creator
+#[marine]
+pub fn purge_orphaned_records() -> PurgeResult {
+    wrapped_try(|| {
+        check_admin(&marine_rs_sdk::get_call_parameters())?;
+        get_storage()?.purge_orphaned_records()
+    })
+    .into()
+}
+
+/// Delete host records of the caller for every key in `key_ids`.
+/// Each key is processed separately, keys without such records report zero
+#[marine]
+pub fn clear_host_records(key_ids: Vec<String>, current_timestamp_sec: u64) -> BatchClearResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+
+        Ok(key_ids
+            .into_iter()
+            .map(|key_id| {
+                let result = storage.check_key_existence(&key_id).and_then(|_| {
+                    storage.delete_host_records(
+                        &key_id,
+                        &call_parameters.host_id,
+                        &call_parameters.init_peer_id,
+                        current_timestamp_sec + 1,
+                    )
+                });
+                KeyClearItem::new(key_id, result)
+            })
+            .collect())
+    })
+    .into()
+}
+
+/// Delete expired records and old tombstones of the key keeping the rest untouched, can be called by the key owner or admin
+#[marine]
+pub fn compact_key(key_id: String, current_timestamp_sec: u64) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 1)?;
+        let storage = get_storage()?;
+        check_admin(&call_parameters)
+            .or_else(|_| storage.check_key_owner(&key_id, &call_parameters.init_peer_id))?;
+
+        storage.transaction(|storage| storage.compact_key(&key_id, current_timestamp_sec))
+    })
+    .into()
+}
+
+/// Check that `expected_records` more non-host records fit into the key, so writers can fail fast.
+/// Nothing is actually reserved, local host records are not limited
+#[marine]
+pub fn reserve_key_capacity(
+    key_id: String,
+    expected_records: u32,
+    current_timestamp_sec: u64,
+) -> RegistryResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 2)?;
+        let storage = get_storage()?;
+        storage.check_key_existence(&key_id)?;
+
+        let available = storage.get_key_free_capacity(&key_id, current_timestamp_sec)?;
+        if expected_records as usize > available {
+            return Err(ServiceError::InsufficientCapacity(
+                key_id,
+                expected_records,
+                available,
+            ));
+        }
+
+        Ok(())
+    })
     .into()
 }
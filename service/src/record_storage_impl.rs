@@ -14,12 +14,18 @@
  * limitations under the License.
  */
 
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 
-use crate::defaults::{RECORDS_LIMIT, RECORDS_TABLE_NAME};
+use crate::config::EvictionPolicy;
+use crate::defaults::{
+    KEYS_TABLE_NAME, PINNED_KEYS_TABLE_NAME, RECORDS_LIMIT, RECORDS_SEQ_TABLE_NAME,
+    RECORDS_TABLE_NAME, RECORD_ACCESS_TABLE_NAME,
+};
 use crate::error::ServiceError;
 use crate::error::ServiceError::InternalError;
 use crate::load_config;
+use crate::overwrite_log::OverwriteLogEntry;
 use crate::record::{Record, RecordInternal, RecordMetadata};
 use crate::storage_impl::{from_custom_option, get_custom_option, Storage};
 use marine_sqlite_connector::{State, Statement, Value};
@@ -41,27 +47,79 @@ impl Storage {
                 timestamp_created INTEGER,
                 signature BLOB,
                 weight INTEGER,
+                expires_at INTEGER,
+                seq INTEGER,
                 PRIMARY KEY (key_id, issued_by, peer_id)
             );
         ");
 
-        let current_table_schema = self
-            .get_table_schema(RECORDS_TABLE_NAME.to_string())
-            .expect(f!("failed to get {RECORDS_TABLE_NAME} table schema").as_str());
-        if !current_table_schema.is_empty() && table_schema != current_table_schema {
-            self.delete_table(RECORDS_TABLE_NAME.to_string())
-                .expect(f!("failed to delete {RECORDS_TABLE_NAME} table").as_str())
-        }
-
+        // the existing table is brought to this schema by `migrate`
         let result = self.connection.execute(table_schema);
 
         if let Err(error) = result {
             println!("create_records_table error: {}", error);
         }
+
+        for (index, column) in [
+            ("records_weight_index", "weight"),
+            ("records_value_index", "value"),
+        ] {
+            let result = self.connection.execute(f!(
+                "CREATE INDEX IF NOT EXISTS {index} ON {RECORDS_TABLE_NAME} (key_id, {column});"
+            ));
+
+            if let Err(error) = result {
+                println!("create {} error: {}", index, error);
+            }
+        }
+
+        // serves the global feed across keys
+        let result = self.connection.execute(f!(
+            "CREATE INDEX IF NOT EXISTS records_timestamp_created_index ON {RECORDS_TABLE_NAME} (timestamp_created);"
+        ));
+        if let Err(error) = result {
+            println!("create records_timestamp_created_index error: {}", error);
+        }
+
+        // serves lookups of an issuer's records across keys
+        let result = self.connection.execute(f!(
+            "CREATE INDEX IF NOT EXISTS records_issued_by_index ON {RECORDS_TABLE_NAME} (issued_by, key_id, peer_id);"
+        ));
+        if let Err(error) = result {
+            println!("create records_issued_by_index error: {}", error);
+        }
+    }
+
+    /// Single-row counter of record writes, `write_record` increments it and stamps the record's `seq` with it,
+    /// so a deleted record's `seq` isn't handed out again. Seeded from the stored records for existing databases.
+    /// `seq` is still not unique: records written before it was tracked share `0`
+    pub fn create_records_seq_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {RECORDS_SEQ_TABLE_NAME} (
+                seq INTEGER NOT NULL
+            );
+
+            INSERT INTO {RECORDS_SEQ_TABLE_NAME}
+            SELECT COALESCE(MAX(seq), 0) FROM {RECORDS_TABLE_NAME}
+            WHERE NOT EXISTS (SELECT 1 FROM {RECORDS_SEQ_TABLE_NAME});
+        "));
+
+        if let Err(error) = result {
+            println!("create_records_seq_table error: {}", error);
+        }
     }
 
-    pub fn update_record(&self, record: RecordInternal) -> Result<(), ServiceError> {
+    pub fn update_record(
+        &self,
+        record: RecordInternal,
+        current_timestamp_sec: u64,
+    ) -> Result<(), ServiceError> {
         let host_id = marine_rs_sdk::get_call_parameters().host_id;
+        self.check_authors_limit(
+            &record.record.metadata.key_id,
+            &record.record.metadata.issued_by,
+            current_timestamp_sec,
+        )?;
 
         // there is no limits for local service records
         if record.record.metadata.peer_id == host_id {
@@ -71,19 +129,29 @@ impl Storage {
                 self.get_non_host_records_count_by_key(&record.record.metadata.key_id)?;
             // check values limits for non-host values
             if records_count >= RECORDS_LIMIT {
-                let min_weight_record =
-                    self.get_min_weight_non_host_record_by_key(&record.record.metadata.key_id)?;
-
-                if min_weight_record.weight < record.weight
-                    || (min_weight_record.weight == record.weight
-                        && min_weight_record.record.timestamp_created
-                            < record.record.timestamp_created)
-                {
-                    // delete the lightest record if the new one is heavier or newer
+                let policy = load_config().eviction_policy;
+                let candidate =
+                    self.get_eviction_candidate(&record.record.metadata.key_id, policy)?;
+
+                let evict = match policy {
+                    EvictionPolicy::LowestWeight => {
+                        candidate.weight < record.weight
+                            || (candidate.weight == record.weight
+                                && candidate.record.timestamp_created
+                                    < record.record.timestamp_created)
+                    }
+                    EvictionPolicy::Oldest => {
+                        candidate.record.timestamp_created < record.record.timestamp_created
+                    }
+                    // the new record is the most recently accessed one
+                    EvictionPolicy::LeastRecentlyAccessed => true,
+                };
+
+                if evict {
                     self.delete_record(
-                        min_weight_record.record.metadata.key_id,
-                        min_weight_record.record.metadata.peer_id,
-                        min_weight_record.record.metadata.issued_by,
+                        candidate.record.metadata.key_id,
+                        candidate.record.metadata.peer_id,
+                        candidate.record.metadata.issued_by,
                     )?;
                 } else {
                     // return error if limit is exceeded
@@ -97,6 +165,84 @@ impl Storage {
         }
     }
 
+    pub fn create_record_access_table(&self) {
+        let result = self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {RECORD_ACCESS_TABLE_NAME} (
+                key_id TEXT,
+                issued_by TEXT,
+                peer_id TEXT,
+                timestamp_accessed INTEGER NOT NULL,
+                PRIMARY KEY (key_id, issued_by, peer_id)
+            );
+        "));
+
+        if let Err(error) = result {
+            println!("create_record_access_table error: {}", error);
+        }
+    }
+
+    /// Mark the record as accessed at `timestamp_accessed`
+    pub fn register_record_access(
+        &self,
+        key_id: &str,
+        issued_by: &str,
+        peer_id: &str,
+        timestamp_accessed: u64,
+    ) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "INSERT OR REPLACE INTO {RECORD_ACCESS_TABLE_NAME} VALUES (?, ?, ?, ?)"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::String(issued_by.to_string()))?;
+        statement.bind(3, &Value::String(peer_id.to_string()))?;
+        statement.bind(4, &Value::Integer(timestamp_accessed as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    /// Mark all records of the key with access entries as accessed at `timestamp_accessed`
+    pub fn register_records_access(
+        &self,
+        key_id: &str,
+        timestamp_accessed: u64,
+    ) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "UPDATE {RECORD_ACCESS_TABLE_NAME} SET timestamp_accessed = max(timestamp_accessed, ?) WHERE key_id = ?"
+        ))?;
+        statement.bind(1, &Value::Integer(timestamp_accessed as i64))?;
+        statement.bind(2, &Value::String(key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(())
+    }
+
+    /// Mark actual records of the key issued by `issued_by` as accessed at `timestamp_accessed`,
+    /// later accesses are kept. Return the number of refreshed records
+    pub fn refresh_records_access(
+        &self,
+        key_id: &str,
+        issued_by: &str,
+        timestamp_accessed: u64,
+        current_timestamp_sec: u64,
+    ) -> Result<u64, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let mut statement = self.connection.prepare(f!(
+            "INSERT OR REPLACE INTO {RECORD_ACCESS_TABLE_NAME} \
+             SELECT r.key_id, r.issued_by, r.peer_id, max(coalesce(a.timestamp_accessed, 0), ?) \
+             FROM {RECORDS_TABLE_NAME} r LEFT JOIN {RECORD_ACCESS_TABLE_NAME} a \
+             ON r.key_id = a.key_id AND r.issued_by = a.issued_by AND r.peer_id = a.peer_id \
+             WHERE r.key_id = ? AND r.issued_by = ? AND r.is_tombstoned = 0 AND r.timestamp_created > ?"
+        ))?;
+        statement.bind(1, &Value::Integer(timestamp_accessed as i64))?;
+        statement.bind(2, &Value::String(key_id.to_string()))?;
+        statement.bind(3, &Value::String(issued_by.to_string()))?;
+        statement.bind(4, &Value::Integer(expired_timestamp as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
     pub fn check_row(
         &self,
         key_id: String,
@@ -139,8 +285,15 @@ impl Storage {
             record.record.metadata.timestamp_issued,
         )?;
 
+        if load_config().overwrite_log {
+            self.log_record_overwrite(&record)?;
+        }
+
+        self.connection
+            .execute(f!("UPDATE {RECORDS_SEQ_TABLE_NAME} SET seq = seq + 1;"))?;
         let mut statement = self.connection.prepare(f!(
-            "INSERT OR REPLACE INTO {RECORDS_TABLE_NAME} VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);"
+            "INSERT OR REPLACE INTO {RECORDS_TABLE_NAME} VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, \
+             (SELECT seq FROM {RECORDS_SEQ_TABLE_NAME}));"
         ))?;
 
         let is_tombstoned = 0;
@@ -169,12 +322,43 @@ impl Storage {
         statement.bind(11, &Value::Integer(record.record.timestamp_created as i64))?;
         statement.bind(12, &Value::Binary(record.record.signature))?;
         statement.bind(13, &Value::Integer(record.weight as i64))?;
+        statement.bind(14, &Value::Integer(record.record.expires_at as i64))?;
 
         statement.next().map(drop)?;
 
         Ok(())
     }
 
+    /// Log the stored record with the same `(key_id, issued_by, peer_id)` if `record` is a different version of it
+    fn log_record_overwrite(&self, record: &RecordInternal) -> Result<(), ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT timestamp_issued, timestamp_created FROM {RECORDS_TABLE_NAME} \
+             WHERE key_id=? AND issued_by=? AND peer_id=? AND is_tombstoned = 0 \
+             AND (timestamp_issued != ? OR timestamp_created != ? OR value != ?)"
+        ))?;
+        statement.bind(1, &Value::String(record.record.metadata.key_id.clone()))?;
+        statement.bind(2, &Value::String(record.record.metadata.issued_by.clone()))?;
+        statement.bind(3, &Value::String(record.record.metadata.peer_id.clone()))?;
+        statement.bind(
+            4,
+            &Value::Integer(record.record.metadata.timestamp_issued as i64),
+        )?;
+        statement.bind(5, &Value::Integer(record.record.timestamp_created as i64))?;
+        statement.bind(6, &Value::String(record.record.metadata.value.clone()))?;
+
+        if let State::Row = statement.next()? {
+            self.log_overwrite(OverwriteLogEntry {
+                key_id: record.record.metadata.key_id.clone(),
+                issued_by: record.record.metadata.issued_by.clone(),
+                peer_id: record.record.metadata.peer_id.clone(),
+                timestamp_issued: statement.read::<i64>(0)? as u64,
+                timestamp_created: statement.read::<i64>(1)? as u64,
+            })?;
+        }
+
+        Ok(())
+    }
+
     pub fn delete_record(
         &self,
         key_id: String,
@@ -185,24 +369,158 @@ impl Storage {
         let mut statement = self.connection.prepare(f!(
             "DELETE FROM {RECORDS_TABLE_NAME} WHERE key_id=? AND peer_id=? AND issued_by=?"
         ))?;
+        statement.bind(1, &Value::String(key_id.clone()))?;
+        statement.bind(2, &Value::String(peer_id.clone()))?;
+        statement.bind(3, &Value::String(issued_by.clone()))?;
+        statement.next().map(drop)?;
+        let deleted = self.connection.changes() == 1;
+
+        let mut statement = self.connection.prepare(f!(
+            "DELETE FROM {RECORD_ACCESS_TABLE_NAME} WHERE key_id=? AND peer_id=? AND issued_by=?"
+        ))?;
         statement.bind(1, &Value::String(key_id))?;
         statement.bind(2, &Value::String(peer_id))?;
         statement.bind(3, &Value::String(issued_by))?;
         statement.next().map(drop)?;
 
-        Ok(self.connection.changes() == 1)
+        Ok(deleted)
+    }
+
+    /// Delete access entries of records which aren't stored anymore or were replaced by tombstones
+    pub fn delete_stale_records_access(&self) -> Result<u64, ServiceError> {
+        self.connection.execute(f!(
+            "DELETE FROM {RECORD_ACCESS_TABLE_NAME} WHERE NOT EXISTS (\
+                SELECT 1 FROM {RECORDS_TABLE_NAME} r WHERE r.key_id = {RECORD_ACCESS_TABLE_NAME}.key_id \
+                AND r.issued_by = {RECORD_ACCESS_TABLE_NAME}.issued_by AND r.peer_id = {RECORD_ACCESS_TABLE_NAME}.peer_id \
+                AND r.is_tombstoned = 0)"
+        ))?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
+    pub fn delete_records_by_key(&self, key_id: &str) -> Result<u64, ServiceError> {
+        let mut statement = self
+            .connection
+            .prepare(f!("DELETE FROM {RECORDS_TABLE_NAME} WHERE key_id=?"))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
+    /// Move records and tombstones of `from_key_id` to `to_key_id` as is
+    pub fn rehome_records(&self, from_key_id: &str, to_key_id: &str) -> Result<u64, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "UPDATE {RECORDS_TABLE_NAME} SET key_id = ? WHERE key_id = ?"
+        ))?;
+        statement.bind(1, &Value::String(to_key_id.to_string()))?;
+        statement.bind(2, &Value::String(from_key_id.to_string()))?;
+        statement.next().map(drop)?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
+    /// Delete records and tombstones of keys which don't exist
+    pub fn purge_orphaned_records(&self) -> Result<u64, ServiceError> {
+        self.connection.execute(f!(
+            "DELETE FROM {RECORDS_TABLE_NAME} WHERE key_id NOT IN (SELECT key_id FROM {KEYS_TABLE_NAME})"
+        ))?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
+    /// Delete records of the key by `issued_by` created before `before_timestamp_created`,
+    /// records of pinned keys are kept
+    pub fn delete_records_before(
+        &self,
+        key_id: &str,
+        issued_by: &str,
+        before_timestamp_created: u64,
+    ) -> Result<u64, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "DELETE FROM {RECORDS_TABLE_NAME} WHERE key_id=? AND issued_by=? AND timestamp_created < ? AND is_tombstoned = 0 \
+             AND key_id NOT IN (SELECT key_id FROM {PINNED_KEYS_TABLE_NAME})"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::String(issued_by.to_string()))?;
+        statement.bind(3, &Value::Integer(before_timestamp_created as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
+    /// Delete records stored on `host_id` by `issued_by` and created before `before_timestamp_created`
+    pub fn delete_host_records(
+        &self,
+        key_id: &str,
+        host_id: &str,
+        issued_by: &str,
+        before_timestamp_created: u64,
+    ) -> Result<u64, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "DELETE FROM {RECORDS_TABLE_NAME} \
+             WHERE key_id=? AND peer_id=? AND issued_by=? AND timestamp_created < ? AND is_tombstoned = 0"
+        ))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::String(host_id.to_string()))?;
+        statement.bind(3, &Value::String(issued_by.to_string()))?;
+        statement.bind(4, &Value::Integer(before_timestamp_created as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(self.connection.changes() as u64)
+    }
+
+    /// Delete expired records and tombstones of the key older than the expired timeout,
+    /// kept records are not rewritten so their `seq` and order are preserved.
+    /// Local host records never expire by age but are deleted when `expires_at` is reached
+    pub fn compact_key(
+        &self,
+        key_id: &str,
+        current_timestamp_sec: u64,
+    ) -> Result<(), ServiceError> {
+        let host_id = marine_rs_sdk::get_call_parameters().host_id;
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+
+        let mut statement = self
+            .connection
+            .prepare(f!("DELETE FROM {RECORDS_TABLE_NAME} \
+             WHERE key_id = ? AND (\
+                 (is_tombstoned = 0 AND peer_id != ? AND timestamp_created <= ?) \
+                 OR (is_tombstoned = 0 AND expires_at != 0 AND expires_at <= ?) \
+                 OR (is_tombstoned = 1 AND timestamp_issued <= ?))"))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::String(host_id))?;
+        statement.bind(3, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(4, &Value::Integer(current_timestamp_sec as i64))?;
+        statement.bind(5, &Value::Integer(expired_timestamp as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(())
     }
 
-    fn get_min_weight_non_host_record_by_key(
+    /// Non-host record of the key to be evicted first under `policy`
+    fn get_eviction_candidate(
         &self,
         key_id: &str,
+        policy: EvictionPolicy,
     ) -> Result<RecordInternal, ServiceError> {
         let host_id = marine_rs_sdk::get_call_parameters().host_id;
+        let order = match policy {
+            EvictionPolicy::LowestWeight => "r.weight ASC, r.timestamp_created ASC",
+            EvictionPolicy::Oldest => "r.timestamp_created ASC, r.weight ASC",
+            EvictionPolicy::LeastRecentlyAccessed => {
+                "coalesce(a.timestamp_accessed, 0) ASC, r.timestamp_created ASC, r.weight ASC"
+            }
+        };
 
-        // only only non-host values
-        let mut statement = self.connection.prepare(
-            f!("SELECT key_id, value, peer_id, set_by, relay_id, service_id, timestamp_created, signature, weight FROM {RECORDS_TABLE_NAME} \
-                     WHERE key_id = ? AND peer_id != ? AND is_tombstoned = 0 ORDER BY weight ASC LIMIT 1"))?;
+        // only non-host values
+        let mut statement = self.connection.prepare(f!(
+            "SELECT r.key_id, r.issued_by, r.peer_id, r.timestamp_issued, r.solution, r.issuer_signature, \
+                    r.value, r.relay_id, r.service_id, r.timestamp_created, r.signature, r.weight, r.expires_at \
+             FROM {RECORDS_TABLE_NAME} r LEFT JOIN {RECORD_ACCESS_TABLE_NAME} a \
+             ON r.key_id = a.key_id AND r.issued_by = a.issued_by AND r.peer_id = a.peer_id \
+             WHERE r.key_id = ? AND r.peer_id != ? AND r.is_tombstoned = 0 ORDER BY {order} LIMIT 1"
+        ))?;
 
         statement.bind(1, &Value::String(key_id.to_string()))?;
         statement.bind(2, &Value::String(host_id))?;
@@ -240,6 +558,130 @@ impl Storage {
         }
     }
 
+    /// Check that `issued_by` already has actual records under the key or the key has room for one more issuer,
+    /// authors of expired records are not counted like in `get_records`
+    fn check_authors_limit(
+        &self,
+        key_id: &str,
+        issued_by: &str,
+        current_timestamp_sec: u64,
+    ) -> Result<(), ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let mut statement = self.connection.prepare(f!(
+            "SELECT COUNT(DISTINCT issued_by), COALESCE(MAX(issued_by = ?), 0)
+             FROM {RECORDS_TABLE_NAME} WHERE key_id = ? AND is_tombstoned = 0 AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?)"
+        ))?;
+        statement.bind(1, &Value::String(issued_by.to_string()))?;
+        statement.bind(2, &Value::String(key_id.to_string()))?;
+        statement.bind(3, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(4, &Value::Integer(current_timestamp_sec as i64))?;
+
+        if let State::Row = statement.next()? {
+            let authors_count = statement.read::<i64>(0)? as u64;
+            let is_author = statement.read::<i64>(1)? != 0;
+            let max_authors = load_config().max_authors_per_key;
+            if is_author || authors_count < max_authors {
+                Ok(())
+            } else {
+                Err(ServiceError::TooManyAuthors(
+                    key_id.to_string(),
+                    max_authors,
+                ))
+            }
+        } else {
+            Err(InternalError(f!(
+                "check_authors_limit: something went totally wrong"
+            )))
+        }
+    }
+
+    /// Return how many more non-host records fit into the key before `RECORDS_LIMIT` is reached,
+    /// expired records are not counted since `clear_expired` removes them
+    pub fn get_key_free_capacity(
+        &self,
+        key_id: &str,
+        current_timestamp_sec: u64,
+    ) -> Result<usize, ServiceError> {
+        let host_id = marine_rs_sdk::get_call_parameters().host_id;
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+
+        let mut statement =
+            self.connection
+                .prepare(f!("SELECT COUNT(*) FROM {RECORDS_TABLE_NAME} \
+             WHERE key_id = ? AND peer_id != ? AND is_tombstoned = 0 AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?)"))?;
+        statement.bind(1, &Value::String(key_id.to_string()))?;
+        statement.bind(2, &Value::String(host_id))?;
+        statement.bind(3, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(4, &Value::Integer(current_timestamp_sec as i64))?;
+
+        if let State::Row = statement.next()? {
+            let count = statement.read::<i64>(0)? as usize;
+            Ok(RECORDS_LIMIT.saturating_sub(count))
+        } else {
+            Err(InternalError(f!(
+                "get_key_free_capacity: something went totally wrong"
+            )))
+        }
+    }
+
+    /// Count actual records of the key per weight bucket of `bucket_size`,
+    /// returns (bucket lower bound, count) pairs ordered by bucket and skips empty buckets
+    pub fn get_weight_histogram(
+        &self,
+        key_id: &str,
+        bucket_size: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<(u32, u64)>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let mut statement = self.connection.prepare(f!(
+            "SELECT weight / ? AS bucket, COUNT(*) FROM {RECORDS_TABLE_NAME} \
+             WHERE key_id = ? AND is_tombstoned = 0 AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?) GROUP BY bucket ORDER BY bucket"
+        ))?;
+        statement.bind(1, &Value::Integer(bucket_size as i64))?;
+        statement.bind(2, &Value::String(key_id.to_string()))?;
+        statement.bind(3, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(4, &Value::Integer(current_timestamp_sec as i64))?;
+
+        let mut result = vec![];
+        while let State::Row = statement.next()? {
+            let bucket = statement.read::<i64>(0)? as u32;
+            let count = statement.read::<i64>(1)? as u64;
+            result.push((bucket * bucket_size, count));
+        }
+
+        Ok(result)
+    }
+
+    /// Sum of weights and count of actual records issued by `issued_by` across all keys
+    pub fn get_author_weight_sum(
+        &self,
+        issued_by: &str,
+        current_timestamp_sec: u64,
+    ) -> Result<(u64, u64), ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let mut statement = self.connection.prepare(f!(
+            "SELECT coalesce(SUM(weight), 0), COUNT(*) FROM {RECORDS_TABLE_NAME} \
+             WHERE issued_by = ? AND is_tombstoned = 0 AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?)"
+        ))?;
+        statement.bind(1, &Value::String(issued_by.to_string()))?;
+        statement.bind(2, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(3, &Value::Integer(current_timestamp_sec as i64))?;
+
+        if let State::Row = statement.next()? {
+            let total_weight = statement.read::<i64>(0)? as u64;
+            let count = statement.read::<i64>(1)? as u64;
+            Ok((total_weight, count))
+        } else {
+            Err(InternalError(f!(
+                "get_author_weight_sum: something went totally wrong"
+            )))
+        }
+    }
+
     pub fn get_records_count_by_key(&self, key_id: &str) -> Result<u64, ServiceError> {
         println!("get_records_count_by_key");
         let mut statement = self.connection.prepare(f!(
@@ -259,26 +701,70 @@ impl Storage {
         }
     }
 
+    /// Merge `records` into the actual records of the key and write the result.
+    /// Return the number of records whose stored state changed, i.e. inserted or replaced,
+    /// and the number of evicted records. Stored records winning the merge aren't rewritten
     pub fn merge_and_update_records(
         &self,
         key_id: String,
         records: Vec<RecordInternal>,
         current_timestamp_sec: u64,
-    ) -> Result<u64, ServiceError> {
+    ) -> Result<(u64, u64), ServiceError> {
+        let existing = self.get_records(key_id.clone(), current_timestamp_sec)?;
         let records = merge_records(
-            self.get_records(key_id, current_timestamp_sec)?
-                .into_iter()
+            existing
+                .iter()
+                .cloned()
                 .chain(records.into_iter())
                 .collect(),
         )?;
 
         let mut updated = 0u64;
         for record in records.into_iter() {
+            if existing.contains(&record) {
+                continue;
+            }
+            let metadata = record.record.metadata.clone();
             self.write_record(record)?;
             updated += self.connection.changes() as u64;
+            self.register_record_access(
+                &metadata.key_id,
+                &metadata.issued_by,
+                &metadata.peer_id,
+                current_timestamp_sec,
+            )?;
         }
 
-        Ok(updated)
+        let evicted = self.evict_over_limit(&key_id)?;
+        Ok((updated, evicted))
+    }
+
+    /// Evict non-host records of the key under the configured policy until `RECORDS_LIMIT` holds again,
+    /// return the number of evicted records. Host records are never evicted, if they exceed
+    /// `RECORDS_LIMIT` on their own it fails with `HostRecordsLimitExceeded` instead
+    fn evict_over_limit(&self, key_id: &str) -> Result<u64, ServiceError> {
+        let records_count = self.get_non_host_records_count_by_key(key_id)?;
+        let host_records_count = self.get_records_count_by_key(key_id)? - records_count as u64;
+        if host_records_count > RECORDS_LIMIT as u64 {
+            return Err(ServiceError::HostRecordsLimitExceeded(
+                key_id.to_string(),
+                host_records_count,
+            ));
+        }
+
+        let excess = records_count.saturating_sub(RECORDS_LIMIT);
+        let policy = load_config().eviction_policy;
+
+        for _ in 0..excess {
+            let candidate = self.get_eviction_candidate(key_id, policy)?;
+            self.delete_record(
+                candidate.record.metadata.key_id,
+                candidate.record.metadata.peer_id,
+                candidate.record.metadata.issued_by,
+            )?;
+        }
+
+        Ok(excess as u64)
     }
 
     pub fn get_records(
@@ -288,12 +774,56 @@ impl Storage {
     ) -> Result<Vec<RecordInternal>, ServiceError> {
         let mut statement = self.connection.prepare(f!(
             "SELECT key_id, issued_by, peer_id, timestamp_issued, solution, issuer_signature,\
-                    value, relay_id, service_id, timestamp_created, signature \
-             FROM {RECORDS_TABLE_NAME} WHERE key_id = ? AND is_tombstoned = 0 AND timestamp_created > ? ORDER BY weight DESC"
+                    value, relay_id, service_id, timestamp_created, signature, weight, expires_at \
+             FROM {RECORDS_TABLE_NAME} WHERE key_id = ? AND is_tombstoned = 0 AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?) ORDER BY weight DESC"
         ))?;
         let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
         statement.bind(1, &Value::String(key_id))?;
         statement.bind(2, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(3, &Value::Integer(current_timestamp_sec as i64))?;
+
+        let mut result: Vec<RecordInternal> = vec![];
+
+        while let State::Row = statement.next()? {
+            result.push(read_record(&statement)?)
+        }
+
+        Ok(result)
+    }
+
+    /// Select non-tombstoned records matching `condition` which haven't reached `expires_at` by `current_timestamp_sec`,
+    /// `values` are bound to its placeholders in order
+    fn select_records(
+        &self,
+        condition: &str,
+        values: Vec<Value>,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let mut bound = vec![Value::Integer(current_timestamp_sec as i64)];
+        bound.extend(values);
+
+        self.select_stored_records(
+            &f!("(expires_at = 0 OR expires_at > ?) AND {condition}"),
+            bound,
+        )
+    }
+
+    /// Select non-tombstoned records matching `condition` including expired ones,
+    /// `values` are bound to its placeholders in order
+    fn select_stored_records(
+        &self,
+        condition: &str,
+        values: Vec<Value>,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, issued_by, peer_id, timestamp_issued, solution, issuer_signature,\
+                    value, relay_id, service_id, timestamp_created, signature, weight, expires_at \
+             FROM {RECORDS_TABLE_NAME} WHERE is_tombstoned = 0 AND {condition}"
+        ))?;
+        for (i, value) in values.iter().enumerate() {
+            statement.bind(i + 1, value)?;
+        }
 
         let mut result: Vec<RecordInternal> = vec![];
 
@@ -304,6 +834,427 @@ impl Storage {
         Ok(result)
     }
 
+    /// Stored non-tombstoned record with the identity, expired records are returned too
+    pub fn get_record(
+        &self,
+        key_id: &str,
+        issued_by: &str,
+        peer_id: &str,
+    ) -> Result<Option<RecordInternal>, ServiceError> {
+        let mut records = self.select_stored_records(
+            "key_id = ? AND issued_by = ? AND peer_id = ?",
+            vec![
+                Value::String(key_id.to_string()),
+                Value::String(issued_by.to_string()),
+                Value::String(peer_id.to_string()),
+            ],
+        )?;
+
+        Ok(records.pop())
+    }
+
+    pub fn get_records_by_authors(
+        &self,
+        key_id: String,
+        issued_by: Vec<String>,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let placeholders = vec!["?"; issued_by.len()].join(", ");
+        let mut values = vec![
+            Value::String(key_id),
+            Value::Integer(expired_timestamp as i64),
+        ];
+        values.extend(issued_by.into_iter().map(Value::String));
+
+        self.select_records(
+            &f!("key_id = ? AND timestamp_created > ? AND issued_by IN ({placeholders}) ORDER BY weight DESC"),
+            values,
+            current_timestamp_sec,
+        )
+    }
+
+    /// Actual records of the key with a service id among `service_ids` issued by any of `issued_by`,
+    /// an empty list doesn't filter
+    pub fn get_records_filtered(
+        &self,
+        key_id: String,
+        current_timestamp_sec: u64,
+        service_ids: Vec<String>,
+        issued_by: Vec<String>,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let mut condition = "key_id = ? AND timestamp_created > ?".to_string();
+        let mut values = vec![
+            Value::String(key_id),
+            Value::Integer(expired_timestamp as i64),
+        ];
+        for (column, filter) in [("service_id", service_ids), ("issued_by", issued_by)] {
+            if !filter.is_empty() {
+                let placeholders = vec!["?"; filter.len()].join(", ");
+                condition.push_str(&f!(" AND {column} IN ({placeholders})"));
+                values.extend(filter.into_iter().map(Value::String));
+            }
+        }
+        condition.push_str(" ORDER BY weight DESC");
+
+        self.select_records(&condition, values, current_timestamp_sec)
+    }
+
+    pub fn get_records_excluding_author(
+        &self,
+        key_id: String,
+        exclude_issued_by: String,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+
+        self.select_records(
+            "key_id = ? AND timestamp_created > ? AND issued_by != ? ORDER BY weight DESC",
+            vec![
+                Value::String(key_id),
+                Value::Integer(expired_timestamp as i64),
+                Value::String(exclude_issued_by),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return `limit` actual records across all keys starting from `offset`, newest first
+    pub fn get_global_feed(
+        &self,
+        offset: u32,
+        limit: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+
+        self.select_records(
+            "timestamp_created > ? \
+             ORDER BY timestamp_created DESC, key_id, issued_by, peer_id LIMIT ? OFFSET ?",
+            vec![
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(limit as i64),
+                Value::Integer(offset as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return a page of actual records issued by `issued_by` across all keys, ordered by key and peer
+    pub fn get_records_by_issuer(
+        &self,
+        issued_by: String,
+        offset: u32,
+        limit: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+
+        self.select_records(
+            "issued_by = ? AND timestamp_created > ? \
+             ORDER BY key_id, peer_id LIMIT ? OFFSET ?",
+            vec![
+                Value::String(issued_by),
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(limit as i64),
+                Value::Integer(offset as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return actual records of the key in the order they were written, a rewrite moves the record to the end.
+    /// Records written before the order was tracked share `seq` 0 and come first in no particular order
+    pub fn get_records_by_insertion(
+        &self,
+        key_id: String,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+
+        self.select_records(
+            "key_id = ? AND timestamp_created > ? ORDER BY seq",
+            vec![
+                Value::String(key_id),
+                Value::Integer(expired_timestamp as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return actual records created within `[from_ts, to_ts]`
+    pub fn get_records_in_window(
+        &self,
+        key_id: String,
+        from_ts: u64,
+        to_ts: u64,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+
+        self.select_records(
+            "key_id = ? AND timestamp_created > ? AND timestamp_created BETWEEN ? AND ? ORDER BY weight DESC",
+            vec![
+                Value::String(key_id),
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(from_ts as i64),
+                Value::Integer(to_ts as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return up to `limit` distinct service ids of actual records in lexicographic order, `0` means no limit
+    pub fn get_all_service_ids(
+        &self,
+        limit: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<String>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        let mut statement =
+            self.connection
+                .prepare(f!("SELECT DISTINCT service_id FROM {RECORDS_TABLE_NAME} \
+             WHERE is_tombstoned = 0 AND service_id != '' AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?) ORDER BY service_id LIMIT ?"))?;
+        statement.bind(1, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(2, &Value::Integer(current_timestamp_sec as i64))?;
+        statement.bind(3, &Value::Integer(limit))?;
+
+        let mut result = vec![];
+        while let State::Row = statement.next()? {
+            result.extend(get_custom_option(statement.read::<String>(0)?));
+        }
+
+        Ok(result)
+    }
+
+    /// Return up to `limit` stored records (`0` means no limit) with rowid greater than `after_rowid`
+    /// in storage order, each with its rowid to continue from
+    pub fn get_all_records(
+        &self,
+        after_rowid: u64,
+        limit: u32,
+    ) -> Result<Vec<(u64, RecordInternal)>, ServiceError> {
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        let mut statement = self.connection.prepare(f!(
+            "SELECT key_id, issued_by, peer_id, timestamp_issued, solution, issuer_signature,\
+                    value, relay_id, service_id, timestamp_created, signature, weight, expires_at, rowid \
+             FROM {RECORDS_TABLE_NAME} WHERE is_tombstoned = 0 AND rowid > ? ORDER BY rowid LIMIT ?"
+        ))?;
+        statement.bind(1, &Value::Integer(after_rowid as i64))?;
+        statement.bind(2, &Value::Integer(limit))?;
+
+        let mut result = vec![];
+        while let State::Row = statement.next()? {
+            result.push((statement.read::<i64>(13)? as u64, read_record(&statement)?));
+        }
+
+        Ok(result)
+    }
+
+    /// Return up to `limit` actual records (`0` means all) of keys which don't exist
+    pub fn find_orphaned_records(
+        &self,
+        limit: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        self.select_records(
+            &f!(
+                "timestamp_created > ? AND key_id NOT IN (SELECT key_id FROM {KEYS_TABLE_NAME}) \
+                 ORDER BY rowid LIMIT ?"
+            ),
+            vec![
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(limit),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return up to `limit` actual records (`0` means all) of keys published not later than `stale_timestamp`
+    pub fn get_records_of_stale_keys(
+        &self,
+        limit: u32,
+        stale_timestamp: u64,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        self.select_records(
+            &f!("timestamp_created > ? \
+                 AND key_id IN (SELECT key_id FROM {KEYS_TABLE_NAME} WHERE timestamp_published <= ?) \
+                 ORDER BY key_id, weight DESC LIMIT ?"),
+            vec![
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(stale_timestamp as i64),
+                Value::Integer(limit),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return actual records of the key published via `relay_id` and created since `since`
+    pub fn get_records_via_relay_since(
+        &self,
+        key_id: String,
+        relay_id: String,
+        since: u64,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        self.select_records(
+            "key_id = ? AND relay_id = ? AND timestamp_created >= ? AND timestamp_created > ? \
+             ORDER BY weight DESC",
+            vec![
+                Value::String(key_id),
+                Value::String(relay_id),
+                Value::Integer(since as i64),
+                Value::Integer(expired_timestamp as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return actual records of the key created at most `max_age_sec` ago
+    pub fn get_records_max_age(
+        &self,
+        key_id: String,
+        max_age_sec: u64,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let min_timestamp_created = current_timestamp_sec.saturating_sub(max_age_sec);
+        self.select_records(
+            "key_id = ? AND timestamp_created >= ? AND timestamp_created > ? ORDER BY weight DESC",
+            vec![
+                Value::String(key_id),
+                Value::Integer(min_timestamp_created as i64),
+                Value::Integer(expired_timestamp as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return actual records of the key issued by its owner
+    pub fn get_owner_records(
+        &self,
+        key_id: String,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        self.select_records(
+            &f!("key_id = ? AND issued_by = (SELECT owner_peer_id FROM {KEYS_TABLE_NAME} WHERE key_id = ?) \
+                 AND timestamp_created > ? ORDER BY weight DESC"),
+            vec![
+                Value::String(key_id.clone()),
+                Value::String(key_id),
+                Value::Integer(expired_timestamp as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return up to `limit` (`0` means all) actual records hosted by `host_id` across all keys starting from `offset`,
+    /// ordered by key_id and issued_by, and the total number of such records
+    pub fn get_host_records(
+        &self,
+        host_id: String,
+        current_timestamp_sec: u64,
+        offset: u32,
+        limit: u32,
+    ) -> Result<(Vec<RecordInternal>, u32), ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let limit = if limit == 0 { -1 } else { limit as i64 };
+        let records = self.select_records(
+            "peer_id = ? AND timestamp_created > ? ORDER BY key_id, issued_by LIMIT ? OFFSET ?",
+            vec![
+                Value::String(host_id.clone()),
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(limit),
+                Value::Integer(offset as i64),
+            ],
+            current_timestamp_sec,
+        )?;
+
+        let mut statement = self.connection.prepare(f!(
+            "SELECT COUNT(*) FROM {RECORDS_TABLE_NAME} WHERE peer_id = ? AND is_tombstoned = 0 AND timestamp_created > ? \
+             AND (expires_at = 0 OR expires_at > ?)"
+        ))?;
+        statement.bind(1, &Value::String(host_id))?;
+        statement.bind(2, &Value::Integer(expired_timestamp as i64))?;
+        statement.bind(3, &Value::Integer(current_timestamp_sec as i64))?;
+
+        if let State::Row = statement.next()? {
+            Ok((records, statement.read::<i64>(0)? as u32))
+        } else {
+            Err(InternalError(f!(
+                "get_host_records: something went totally wrong"
+            )))
+        }
+    }
+
+    /// Return actual records of the key with exactly `value`
+    pub fn get_records_by_value(
+        &self,
+        key_id: String,
+        value: String,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        self.select_records(
+            "key_id = ? AND value = ? AND timestamp_created > ? ORDER BY weight DESC",
+            vec![
+                Value::String(key_id),
+                Value::String(value),
+                Value::Integer(expired_timestamp as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return up to `n` heaviest actual records of the key
+    pub fn get_top_records(
+        &self,
+        key_id: String,
+        n: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec - load_config().expired_timeout;
+        self.select_records(
+            "key_id = ? AND timestamp_created > ? ORDER BY weight DESC LIMIT ?",
+            vec![
+                Value::String(key_id),
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(n as i64),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
+    /// Return `n` (`0` means all) most recently created actual records of the key, newest first
+    pub fn get_recent_records(
+        &self,
+        key_id: String,
+        n: u32,
+        current_timestamp_sec: u64,
+    ) -> Result<Vec<RecordInternal>, ServiceError> {
+        let expired_timestamp = current_timestamp_sec.saturating_sub(load_config().expired_timeout);
+        let limit = if n == 0 { -1 } else { n as i64 };
+        self.select_records(
+            "key_id = ? AND timestamp_created > ? ORDER BY timestamp_created DESC, issued_by, peer_id LIMIT ?",
+            vec![
+                Value::String(key_id),
+                Value::Integer(expired_timestamp as i64),
+                Value::Integer(limit),
+            ],
+            current_timestamp_sec,
+        )
+    }
+
     pub fn get_local_stale_records(
         &self,
         stale_timestamp_sec: u64,
@@ -311,7 +1262,7 @@ impl Storage {
         let host_id = marine_rs_sdk::get_call_parameters().host_id;
         let mut statement = self.connection.prepare(f!(
             "SELECT key_id, issued_by, peer_id, timestamp_issued, solution, issuer_signature,\
-                    value, relay_id, service_id, timestamp_created, signature \
+                    value, relay_id, service_id, timestamp_created, signature, weight, expires_at \
              FROM {RECORDS_TABLE_NAME} WHERE peer_id = ? AND is_tombstoned = 0 AND timestamp_created < ?"
         ))?;
         statement.bind(1, &Value::String(host_id))?;
@@ -335,6 +1286,14 @@ impl Storage {
         ))?;
         Ok(self.connection.changes() as u64)
     }
+
+    /// Remove records with `expires_at` reached, host records included
+    pub fn clear_expiring_records(&self, current_timestamp_sec: u64) -> Result<u64, ServiceError> {
+        self.connection.execute(f!(
+            "DELETE FROM {RECORDS_TABLE_NAME} WHERE expires_at != 0 AND expires_at <= {current_timestamp_sec} AND is_tombstoned = 0"
+        ))?;
+        Ok(self.connection.changes() as u64)
+    }
 }
 
 pub fn read_record(statement: &Statement) -> Result<RecordInternal, ServiceError> {
@@ -352,14 +1311,50 @@ pub fn read_record(statement: &Statement) -> Result<RecordInternal, ServiceError
                 service_id: get_custom_option(statement.read::<String>(8)?),
             },
             timestamp_created: statement.read::<i64>(9)? as u64,
+            expires_at: statement.read::<i64>(12)? as u64,
             signature: statement.read::<Vec<u8>>(10)?,
         },
         weight: statement.read::<i64>(11)? as u32,
     })
 }
 
-/// Merge values with same peer_id by timestamp_created (last-write-wins)
+/// Total order of versions of the same record, the greatest one survives the merge:
+/// newer first, then heavier, then by larger signature bytes, then by larger value.
+/// It doesn't depend on the input order, so all nodes converge on the same record
+pub fn record_precedence(l: &RecordInternal, r: &RecordInternal) -> Ordering {
+    l.record
+        .timestamp_created
+        .cmp(&r.record.timestamp_created)
+        .then_with(|| l.weight.cmp(&r.weight))
+        .then_with(|| l.record.signature.cmp(&r.record.signature))
+        .then_with(|| l.record.metadata.value.cmp(&r.record.metadata.value))
+}
+
+/// Like `record_precedence` but the heavier version wins regardless of recency, for trust-over-time semantics
+fn weight_precedence(l: &RecordInternal, r: &RecordInternal) -> Ordering {
+    l.weight
+        .cmp(&r.weight)
+        .then_with(|| record_precedence(l, r))
+}
+
+/// Last-write-wins merge: one record per (peer_id, issued_by), see `record_precedence`.
+/// Records of the same peer_id (e.g. records of one host) are ordered by weight and then by issued_by,
+/// so the first one is the same on every replica
 pub fn merge_records(records: Vec<RecordInternal>) -> Result<Vec<RecordInternal>, ServiceError> {
+    merge_records_with(records, record_precedence)
+}
+
+/// Weight-wins merge: one record per (peer_id, issued_by), see `weight_precedence`
+pub fn merge_records_by_weight(
+    records: Vec<RecordInternal>,
+) -> Result<Vec<RecordInternal>, ServiceError> {
+    merge_records_with(records, weight_precedence)
+}
+
+fn merge_records_with(
+    records: Vec<RecordInternal>,
+    precedence: fn(&RecordInternal, &RecordInternal) -> Ordering,
+) -> Result<Vec<RecordInternal>, ServiceError> {
     // key is (peer_id, set_by)
     let mut result: HashMap<(String, String), RecordInternal> = HashMap::new();
 
@@ -370,7 +1365,7 @@ pub fn merge_records(records: Vec<RecordInternal>) -> Result<Vec<RecordInternal>
         );
 
         if let Some(other_rec) = result.get_mut(&key) {
-            if other_rec.record.timestamp_created < rec.record.timestamp_created {
+            if precedence(&rec, other_rec) == Ordering::Greater {
                 *other_rec = rec;
             }
         } else {
@@ -378,5 +1373,56 @@ pub fn merge_records(records: Vec<RecordInternal>) -> Result<Vec<RecordInternal>
         }
     }
 
-    Ok(result.into_values().collect())
+    let mut result: Vec<RecordInternal> = result.into_values().collect();
+    result.sort_by(|l, r| {
+        l.record
+            .metadata
+            .peer_id
+            .cmp(&r.record.metadata.peer_id)
+            .then_with(|| r.weight.cmp(&l.weight))
+            .then_with(|| {
+                l.record
+                    .metadata
+                    .issued_by
+                    .cmp(&r.record.metadata.issued_by)
+            })
+    });
+
+    Ok(result)
+}
+
+/// Pick the value with the highest total weight of its records.
+/// Returns `(value, value_weight, total_weight, is_tie)`, ties are resolved to the smallest value
+pub fn consensus_value(records: Vec<RecordInternal>) -> Option<(String, u64, u64, bool)> {
+    let mut weights: HashMap<String, u64> = HashMap::new();
+    for rec in records.into_iter() {
+        *weights.entry(rec.record.metadata.value).or_default() += rec.weight as u64;
+    }
+
+    let total_weight = weights.values().sum();
+    let mut values: Vec<(String, u64)> = weights.into_iter().collect();
+    values.sort_by(|(lv, lw), (rv, rw)| rw.cmp(lw).then_with(|| lv.cmp(rv)));
+
+    let is_tie = values.len() > 1 && values[0].1 == values[1].1;
+    values
+        .into_iter()
+        .next()
+        .map(|(value, weight)| (value, weight, total_weight, is_tie))
+}
+
+/// Order records by `weight DESC, timestamp_created DESC`, moving the best record of every author
+/// (`issued_by`) ahead of all secondary records. Both blocks keep that order
+pub fn rank_records(mut records: Vec<RecordInternal>) -> Vec<RecordInternal> {
+    records.sort_by(|l, r| {
+        r.weight
+            .cmp(&l.weight)
+            .then_with(|| r.record.timestamp_created.cmp(&l.record.timestamp_created))
+    });
+
+    let mut authors = HashSet::new();
+    let (best, secondary): (Vec<_>, Vec<_>) = records
+        .into_iter()
+        .partition(|r| authors.insert(r.record.metadata.issued_by.clone()));
+
+    best.into_iter().chain(secondary).collect()
 }
@@ -16,7 +16,10 @@
 
 use crate::error::ServiceError;
 use crate::key::Key;
+use crate::merkle::MerkleProofStep;
+use crate::overwrite_log::OverwriteLogEntry;
 use crate::record::Record;
+use crate::storage_impl::StorageStats;
 use crate::tombstone::Tombstone;
 use marine_rs_sdk::marine;
 
@@ -25,6 +28,7 @@ use marine_rs_sdk::marine;
 pub struct RegistryResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
 }
 
 impl From<Result<(), ServiceError>> for RegistryResult {
@@ -33,10 +37,12 @@ impl From<Result<(), ServiceError>> for RegistryResult {
             Ok(_) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
             },
         }
     }
@@ -47,6 +53,7 @@ impl From<Result<(), ServiceError>> for RegistryResult {
 pub struct RegisterKeyResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub key_id: String,
 }
 
@@ -56,11 +63,13 @@ impl From<Result<String, ServiceError>> for RegisterKeyResult {
             Ok(key_id) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 key_id,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 key_id: "".to_string(),
             },
         }
@@ -72,21 +81,171 @@ impl From<Result<String, ServiceError>> for RegisterKeyResult {
 pub struct GetRecordsResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub result: Vec<Record>,
+    /// `identities[i]` is the identity of `result[i]`, can be used to dedup records from several nodes
+    pub identities: Vec<String>,
+    /// `is_host_record[i]` is true if `result[i]` is a host record of the node that returned it
+    pub is_host_record: Vec<bool>,
 }
 
 impl From<Result<Vec<Record>, ServiceError>> for GetRecordsResult {
     fn from(result: Result<Vec<Record>, ServiceError>) -> Self {
         match result {
-            Ok(result) => Self {
+            Ok(result) => {
+                let host_id = marine_rs_sdk::get_call_parameters().host_id;
+                Self {
+                    success: true,
+                    error: "".to_string(),
+                    error_code: 0,
+                    identities: result.iter().map(|r| r.identity()).collect(),
+                    is_host_record: result
+                        .iter()
+                        .map(|r| r.metadata.peer_id == host_id)
+                        .collect(),
+                    result,
+                }
+            }
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: vec![],
+                identities: vec![],
+                is_host_record: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct GetRecordsOrEmptyResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// false if the key isn't registered, `result` is empty then
+    pub key_existed: bool,
+    pub result: Vec<Record>,
+}
+
+impl From<Result<(bool, Vec<Record>), ServiceError>> for GetRecordsOrEmptyResult {
+    fn from(result: Result<(bool, Vec<Record>), ServiceError>) -> Self {
+        match result {
+            Ok((key_existed, result)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                key_existed,
+                result,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                key_existed: false,
+                result: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct GetRecordsVerifiedResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub result: Vec<Record>,
+    /// `verified[i]` is whether `result[i]` signatures are valid
+    pub verified: Vec<bool>,
+}
+
+impl From<Result<Vec<(Record, bool)>, ServiceError>> for GetRecordsVerifiedResult {
+    fn from(result: Result<Vec<(Record, bool)>, ServiceError>) -> Self {
+        match result {
+            Ok(records) => {
+                let (result, verified) = records.into_iter().unzip();
+                Self {
+                    success: true,
+                    error: "".to_string(),
+                    error_code: 0,
+                    result,
+                    verified,
+                }
+            }
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: vec![],
+                verified: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct GetRecordsPageResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub result: Vec<Record>,
+    /// `identities[i]` is the identity of `result[i]`
+    pub identities: Vec<String>,
+    /// number of matching actual records across all pages
+    pub total: u32,
+}
+
+impl From<Result<(Vec<Record>, u32), ServiceError>> for GetRecordsPageResult {
+    fn from(result: Result<(Vec<Record>, u32), ServiceError>) -> Self {
+        match result {
+            Ok((result, total)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                identities: result.iter().map(|r| r.identity()).collect(),
+                result,
+                total,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: vec![],
+                identities: vec![],
+                total: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct GetRecordsFreshnessResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub result: Vec<Record>,
+    /// key wasn't republished during the stale timeout
+    pub stale: bool,
+}
+
+impl From<Result<(Vec<Record>, bool), ServiceError>> for GetRecordsFreshnessResult {
+    fn from(result: Result<(Vec<Record>, bool), ServiceError>) -> Self {
+        match result {
+            Ok((result, stale)) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 result,
+                stale,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 result: vec![],
+                stale: false,
             },
         }
     }
@@ -97,6 +256,7 @@ impl From<Result<Vec<Record>, ServiceError>> for GetRecordsResult {
 pub struct GetTombstonesResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub result: Vec<Tombstone>,
 }
 
@@ -106,11 +266,13 @@ impl From<Result<Vec<Tombstone>, ServiceError>> for GetTombstonesResult {
             Ok(result) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 result,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 result: vec![],
             },
         }
@@ -122,6 +284,7 @@ impl From<Result<Vec<Tombstone>, ServiceError>> for GetTombstonesResult {
 pub struct ClearExpiredResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub count_keys: u64,
     pub count_records: u64,
     pub count_tombstones: u64,
@@ -133,6 +296,7 @@ impl From<Result<(u64, u64, u64), ServiceError>> for ClearExpiredResult {
             Ok((count_keys, count_records, count_tombstones)) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 count_keys,
                 count_records,
                 count_tombstones,
@@ -140,6 +304,7 @@ impl From<Result<(u64, u64, u64), ServiceError>> for ClearExpiredResult {
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 count_keys: 0,
                 count_records: 0,
                 count_tombstones: 0,
@@ -153,6 +318,7 @@ impl From<Result<(u64, u64, u64), ServiceError>> for ClearExpiredResult {
 pub struct GetStaleRecordsResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub result: Vec<Record>,
 }
 
@@ -162,11 +328,13 @@ impl From<Result<Vec<Record>, ServiceError>> for GetStaleRecordsResult {
             Ok(result) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 result,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 result: vec![],
             },
         }
@@ -177,21 +345,121 @@ impl From<Result<Vec<Record>, ServiceError>> for GetStaleRecordsResult {
 pub struct GetKeyMetadataResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub key: Key,
+    /// set by the owner with `set_key_display_label`, empty if not set
+    pub display_label: String,
 }
 
-impl From<Result<Key, ServiceError>> for GetKeyMetadataResult {
-    fn from(result: Result<Key, ServiceError>) -> Self {
+impl From<Result<(Key, String), ServiceError>> for GetKeyMetadataResult {
+    fn from(result: Result<(Key, String), ServiceError>) -> Self {
         match result {
-            Ok(key) => Self {
+            Ok((key, display_label)) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 key,
+                display_label,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 key: Key::default(),
+                display_label: "".to_string(),
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct GetKeysResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub result: Vec<Key>,
+}
+
+impl From<Result<Vec<Key>, ServiceError>> for GetKeysResult {
+    fn from(result: Result<Vec<Key>, ServiceError>) -> Self {
+        match result {
+            Ok(result) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                result,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct EmptyKeysResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub result: Vec<Key>,
+    /// `pinned[i]` is true if `result[i]` is pinned, so GC keeps it
+    pub pinned: Vec<bool>,
+}
+
+impl From<Result<Vec<(Key, bool)>, ServiceError>> for EmptyKeysResult {
+    fn from(result: Result<Vec<(Key, bool)>, ServiceError>) -> Self {
+        match result {
+            Ok(keys) => {
+                let (result, pinned) = keys.into_iter().unzip();
+                Self {
+                    success: true,
+                    error: "".to_string(),
+                    error_code: 0,
+                    result,
+                    pinned,
+                }
+            }
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: vec![],
+                pinned: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct KeysMetadataResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// `keys[i]` is metadata of the i-th requested key, empty if it's not found
+    pub keys: Vec<Key>,
+    /// `found[i]` is whether the i-th requested key exists
+    pub found: Vec<bool>,
+}
+
+impl From<Result<Vec<Option<Key>>, ServiceError>> for KeysMetadataResult {
+    fn from(result: Result<Vec<Option<Key>>, ServiceError>) -> Self {
+        match result {
+            Ok(keys) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                found: keys.iter().map(Option::is_some).collect(),
+                keys: keys.into_iter().map(Option::unwrap_or_default).collect(),
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                keys: vec![],
+                found: vec![],
             },
         }
     }
@@ -201,21 +469,108 @@ impl From<Result<Key, ServiceError>> for GetKeyMetadataResult {
 pub struct RepublishRecordsResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub updated: u64,
+    /// records failing timestamp checks or below the minimum weight for puts
+    pub skipped: u64,
+    /// records evicted to keep the key within the records limit
+    pub evicted: u64,
 }
 
 impl From<Result<u64, ServiceError>> for RepublishRecordsResult {
     fn from(result: Result<u64, ServiceError>) -> Self {
+        result.map(|count| (count, 0, 0)).into()
+    }
+}
+
+impl From<Result<(u64, u64, u64), ServiceError>> for RepublishRecordsResult {
+    fn from(result: Result<(u64, u64, u64), ServiceError>) -> Self {
         match result {
-            Ok(count) => Self {
+            Ok((updated, skipped, evicted)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                updated,
+                skipped,
+                evicted,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                updated: 0,
+                skipped: 0,
+                evicted: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct RepublishMergedResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub updated: u64,
+    pub skipped: u64,
+    pub evicted: u64,
+    /// records of the key stored after the merge
+    pub result: Vec<Record>,
+}
+
+impl From<Result<((u64, u64, u64), Vec<Record>), ServiceError>> for RepublishMergedResult {
+    fn from(result: Result<((u64, u64, u64), Vec<Record>), ServiceError>) -> Self {
+        match result {
+            Ok(((updated, skipped, evicted), result)) => Self {
                 success: true,
                 error: "".to_string(),
-                updated: count,
+                error_code: 0,
+                updated,
+                skipped,
+                evicted,
+                result,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 updated: 0,
+                skipped: 0,
+                evicted: 0,
+                result: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct RepublishDiffResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// identities of the key's records before the merge
+    pub before: Vec<String>,
+    /// identities of the key's records after the merge
+    pub after: Vec<String>,
+}
+
+impl From<Result<(Vec<String>, Vec<String>), ServiceError>> for RepublishDiffResult {
+    fn from(result: Result<(Vec<String>, Vec<String>), ServiceError>) -> Self {
+        match result {
+            Ok((before, after)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                before,
+                after,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                before: vec![],
+                after: vec![],
             },
         }
     }
@@ -232,6 +587,7 @@ pub struct EvictStaleItem {
 pub struct EvictStaleResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub results: Vec<EvictStaleItem>,
 }
 
@@ -241,11 +597,13 @@ impl From<Result<Vec<EvictStaleItem>, ServiceError>> for EvictStaleResult {
             Ok(results) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 results,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 results: vec![],
             },
         }
@@ -257,6 +615,7 @@ impl From<Result<Vec<EvictStaleItem>, ServiceError>> for EvictStaleResult {
 pub struct MergeResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub result: Vec<Record>,
 }
 
@@ -266,11 +625,13 @@ impl From<Result<Vec<Record>, ServiceError>> for MergeResult {
             Ok(result) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 result,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 result: vec![],
             },
         }
@@ -281,6 +642,7 @@ impl From<Result<Vec<Record>, ServiceError>> for MergeResult {
 pub struct MergeKeysResult {
     pub success: bool,
     pub error: String,
+    pub error_code: i64,
     pub key: Key,
 }
 
@@ -290,13 +652,1024 @@ impl From<Result<Key, ServiceError>> for MergeKeysResult {
             Ok(key) => Self {
                 success: true,
                 error: "".to_string(),
+                error_code: 0,
                 key,
             },
             Err(err) => Self {
                 success: false,
                 error: err.to_string(),
+                error_code: err.code(),
                 key: Key::default(),
             },
         }
     }
 }
+
+#[marine]
+pub struct PurgeResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub count: u64,
+}
+
+impl From<Result<u64, ServiceError>> for PurgeResult {
+    fn from(result: Result<u64, ServiceError>) -> Self {
+        match result {
+            Ok(count) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                count,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct PurgeInvalidResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub count: u64,
+    /// rowid to pass as `after_rowid` to continue, `0` when all records are checked
+    pub next_rowid: u64,
+}
+
+impl From<Result<(u64, u64), ServiceError>> for PurgeInvalidResult {
+    fn from(result: Result<(u64, u64), ServiceError>) -> Self {
+        match result {
+            Ok((count, next_rowid)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                count,
+                next_rowid,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                count: 0,
+                next_rowid: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct CountResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub count: u64,
+}
+
+impl From<Result<u64, ServiceError>> for CountResult {
+    fn from(result: Result<u64, ServiceError>) -> Self {
+        match result {
+            Ok(count) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                count,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct ConsensusResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub value: String,
+    /// total weight of records with `value`
+    pub weight: u64,
+    /// `weight` divided by the total weight of all records, 0 if all records have zero weight
+    pub support: f64,
+    /// another value has the same total weight
+    pub tie: bool,
+}
+
+impl From<Result<(String, u64, u64, bool), ServiceError>> for ConsensusResult {
+    fn from(result: Result<(String, u64, u64, bool), ServiceError>) -> Self {
+        match result {
+            Ok((value, weight, total_weight, tie)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                value,
+                weight,
+                support: if total_weight == 0 {
+                    0.0
+                } else {
+                    weight as f64 / total_weight as f64
+                },
+                tie,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                value: "".to_string(),
+                weight: 0,
+                support: 0.0,
+                tie: false,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct GetRecordsJsonResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// JSON array of projected records
+    pub result: String,
+}
+
+impl From<Result<String, ServiceError>> for GetRecordsJsonResult {
+    fn from(result: Result<String, ServiceError>) -> Self {
+        match result {
+            Ok(result) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                result,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: "".to_string(),
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct KeyIdCollision {
+    pub key_id: String,
+    /// current and replaced keys with distinct (label, owner_peer_id) pairs hashing to `key_id`
+    pub keys: Vec<Key>,
+}
+
+#[marine]
+pub struct CollisionResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub collisions: Vec<KeyIdCollision>,
+}
+
+impl From<Result<Vec<KeyIdCollision>, ServiceError>> for CollisionResult {
+    fn from(result: Result<Vec<KeyIdCollision>, ServiceError>) -> Self {
+        match result {
+            Ok(collisions) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                collisions,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                collisions: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct AuthorTrustResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub total_weight: u64,
+    /// 0 if the author has no records
+    pub average_weight: f64,
+    pub record_count: u64,
+}
+
+impl From<Result<(u64, u64), ServiceError>> for AuthorTrustResult {
+    fn from(result: Result<(u64, u64), ServiceError>) -> Self {
+        match result {
+            Ok((total_weight, record_count)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                total_weight,
+                average_weight: if record_count == 0 {
+                    0.0
+                } else {
+                    total_weight as f64 / record_count as f64
+                },
+                record_count,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                total_weight: 0,
+                average_weight: 0.0,
+                record_count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug, Clone)]
+pub struct WeightBucket {
+    /// lowest weight of the bucket, the bucket holds weights in [lower_bound, lower_bound + bucket_size)
+    pub lower_bound: u32,
+    pub count: u64,
+}
+
+#[marine]
+pub struct HistogramResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub buckets: Vec<WeightBucket>,
+}
+
+impl From<Result<Vec<WeightBucket>, ServiceError>> for HistogramResult {
+    fn from(result: Result<Vec<WeightBucket>, ServiceError>) -> Self {
+        match result {
+            Ok(buckets) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                buckets,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                buckets: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct ExportResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub blob: Vec<u8>,
+}
+
+impl From<Result<Vec<u8>, ServiceError>> for ExportResult {
+    fn from(result: Result<Vec<u8>, ServiceError>) -> Self {
+        match result {
+            Ok(blob) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                blob,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                blob: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct ImportResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub imported_keys: u64,
+    /// records merged into the storage
+    pub imported_records: u64,
+    /// records skipped because their key is absent locally
+    pub skipped_records: u64,
+    /// keys and records failing verification
+    pub invalid_count: u64,
+}
+
+impl From<Result<(u64, u64, u64, u64), ServiceError>> for ImportResult {
+    fn from(result: Result<(u64, u64, u64, u64), ServiceError>) -> Self {
+        match result {
+            Ok((imported_keys, imported_records, skipped_records, invalid_count)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                imported_keys,
+                imported_records,
+                skipped_records,
+                invalid_count,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                imported_keys: 0,
+                imported_records: 0,
+                skipped_records: 0,
+                invalid_count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct SignedExportResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// `export_all` format blob with the key and its actual records
+    pub blob: Vec<u8>,
+    pub timestamp_sec: u64,
+    /// sha256 of big-endian `timestamp_sec` followed by `blob`
+    pub digest: Vec<u8>,
+    /// signature of `digest` by `signer_peer_id`
+    pub signature: Vec<u8>,
+    pub signer_peer_id: String,
+}
+
+impl From<Result<(Vec<u8>, u64, Vec<u8>, Vec<u8>, String), ServiceError>> for SignedExportResult {
+    fn from(result: Result<(Vec<u8>, u64, Vec<u8>, Vec<u8>, String), ServiceError>) -> Self {
+        match result {
+            Ok((blob, timestamp_sec, digest, signature, signer_peer_id)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                blob,
+                timestamp_sec,
+                digest,
+                signature,
+                signer_peer_id,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                blob: vec![],
+                timestamp_sec: 0,
+                digest: vec![],
+                signature: vec![],
+                signer_peer_id: "".to_string(),
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct ValidateImportResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// keys and records passing verification
+    pub valid_count: u64,
+    /// keys and records failing verification
+    pub invalid_count: u64,
+    /// whether the blob schema version is supported
+    pub schema_compatible: bool,
+}
+
+impl From<Result<(u64, u64, bool), ServiceError>> for ValidateImportResult {
+    fn from(result: Result<(u64, u64, bool), ServiceError>) -> Self {
+        match result {
+            Ok((valid_count, invalid_count, schema_compatible)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                valid_count,
+                invalid_count,
+                schema_compatible,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                valid_count: 0,
+                invalid_count: 0,
+                schema_compatible: false,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct MerkleResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// base58-encoded root, empty if the key has no records
+    pub root: String,
+    pub leaves_count: u64,
+}
+
+impl From<Result<(String, u64), ServiceError>> for MerkleResult {
+    fn from(result: Result<(String, u64), ServiceError>) -> Self {
+        match result {
+            Ok((root, leaves_count)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                root,
+                leaves_count,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                root: "".to_string(),
+                leaves_count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct MerkleProofResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// base58-encoded root the proof leads to
+    pub root: String,
+    /// siblings from the leaf to the root
+    pub proof: Vec<MerkleProofStep>,
+}
+
+impl From<Result<(String, Vec<MerkleProofStep>), ServiceError>> for MerkleProofResult {
+    fn from(result: Result<(String, Vec<MerkleProofStep>), ServiceError>) -> Self {
+        match result {
+            Ok((root, proof)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                root,
+                proof,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                root: "".to_string(),
+                proof: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct RateLimitResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// puts left in the current window
+    pub remaining: u32,
+    /// seconds until the current window resets
+    pub reset_in_sec: u64,
+}
+
+impl From<Result<(u32, u64), ServiceError>> for RateLimitResult {
+    fn from(result: Result<(u32, u64), ServiceError>) -> Self {
+        match result {
+            Ok((remaining, reset_in_sec)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                remaining,
+                reset_in_sec,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                remaining: 0,
+                reset_in_sec: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct RemoveKeysResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub keys_count: u64,
+    pub records_count: u64,
+}
+
+impl From<Result<(u64, u64), ServiceError>> for RemoveKeysResult {
+    fn from(result: Result<(u64, u64), ServiceError>) -> Self {
+        match result {
+            Ok((keys_count, records_count)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                keys_count,
+                records_count,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                keys_count: 0,
+                records_count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct GetOverwriteLogResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub result: Vec<OverwriteLogEntry>,
+}
+
+impl From<Result<Vec<OverwriteLogEntry>, ServiceError>> for GetOverwriteLogResult {
+    fn from(result: Result<Vec<OverwriteLogEntry>, ServiceError>) -> Self {
+        match result {
+            Ok(result) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                result,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                result: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct OwnerResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub exists: bool,
+    pub is_owner: bool,
+}
+
+impl From<Result<(bool, bool), ServiceError>> for OwnerResult {
+    fn from(result: Result<(bool, bool), ServiceError>) -> Self {
+        match result {
+            Ok((exists, is_owner)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                exists,
+                is_owner,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                exists: false,
+                is_owner: false,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct DbSizeResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub size: u64,
+}
+
+impl From<Result<u64, ServiceError>> for DbSizeResult {
+    fn from(result: Result<u64, ServiceError>) -> Self {
+        match result {
+            Ok(size) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                size,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                size: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct StatsResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub keys_count: u64,
+    /// Not tombstoned records including expired ones not cleared yet
+    pub records_count: u64,
+    pub host_records_count: u64,
+    pub pinned_keys_count: u64,
+    pub oldest_key_timestamp: u64,
+}
+
+impl From<Result<StorageStats, ServiceError>> for StatsResult {
+    fn from(result: Result<StorageStats, ServiceError>) -> Self {
+        match result {
+            Ok(stats) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                keys_count: stats.keys_count,
+                records_count: stats.records_count,
+                host_records_count: stats.host_records_count,
+                pinned_keys_count: stats.pinned_keys_count,
+                oldest_key_timestamp: stats.oldest_key_timestamp,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                keys_count: 0,
+                records_count: 0,
+                host_records_count: 0,
+                pinned_keys_count: 0,
+                oldest_key_timestamp: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct KeyClearItem {
+    pub key_id: String,
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub count: u64,
+}
+
+impl KeyClearItem {
+    pub fn new(key_id: String, result: Result<u64, ServiceError>) -> Self {
+        match result {
+            Ok(count) => Self {
+                key_id,
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                count,
+            },
+            Err(err) => Self {
+                key_id,
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug, Clone)]
+pub struct PutRecordError {
+    /// index of the rejected record in `records`
+    pub index: u32,
+    pub error: String,
+    pub error_code: i64,
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct PutIfBetterResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// false if the stored record wasn't superseded, nothing is written then
+    pub improved: bool,
+}
+
+impl From<Result<bool, ServiceError>> for PutIfBetterResult {
+    fn from(result: Result<bool, ServiceError>) -> Self {
+        match result {
+            Ok(improved) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                improved,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                improved: false,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct PutRecordsResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub stored_count: u64,
+    /// records rejected individually, the rest of the batch is stored anyway
+    pub errors: Vec<PutRecordError>,
+}
+
+impl From<Result<(u64, Vec<PutRecordError>), ServiceError>> for PutRecordsResult {
+    fn from(result: Result<(u64, Vec<PutRecordError>), ServiceError>) -> Self {
+        match result {
+            Ok((stored_count, errors)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                stored_count,
+                errors,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                stored_count: 0,
+                errors: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct BatchClearResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub results: Vec<KeyClearItem>,
+}
+
+impl From<Result<Vec<KeyClearItem>, ServiceError>> for BatchClearResult {
+    fn from(result: Result<Vec<KeyClearItem>, ServiceError>) -> Self {
+        match result {
+            Ok(results) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                results,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                results: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct RotationResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// current peer id of the identity
+    pub peer_id: String,
+    /// whether the identity has been rotated at all
+    pub rotated: bool,
+}
+
+impl From<Result<(String, bool), ServiceError>> for RotationResult {
+    fn from(result: Result<(String, bool), ServiceError>) -> Self {
+        match result {
+            Ok((peer_id, rotated)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                peer_id,
+                rotated,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                peer_id: "".to_string(),
+                rotated: false,
+            },
+        }
+    }
+}
+
+#[marine]
+#[derive(Debug)]
+pub struct ServiceIdsResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub service_ids: Vec<String>,
+}
+
+impl From<Result<Vec<String>, ServiceError>> for ServiceIdsResult {
+    fn from(result: Result<Vec<String>, ServiceError>) -> Self {
+        match result {
+            Ok(service_ids) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                service_ids,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                service_ids: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct AgePercentilesResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// ages in seconds, 0 if the key has no actual records
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub record_count: u64,
+}
+
+/// Nearest-rank percentile of ascending `sorted` values
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len() + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
+
+/// `ages` should be sorted in ascending order
+impl From<Result<Vec<u64>, ServiceError>> for AgePercentilesResult {
+    fn from(result: Result<Vec<u64>, ServiceError>) -> Self {
+        match result {
+            Ok(ages) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                p50: percentile(&ages, 50),
+                p90: percentile(&ages, 90),
+                p99: percentile(&ages, 99),
+                record_count: ages.len() as u64,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                p50: 0,
+                p90: 0,
+                p99: 0,
+                record_count: 0,
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct CompareResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub matches: bool,
+    pub local_digest: String,
+}
+
+impl From<Result<(bool, String), ServiceError>> for CompareResult {
+    fn from(result: Result<(bool, String), ServiceError>) -> Self {
+        match result {
+            Ok((matches, local_digest)) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                matches,
+                local_digest,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                matches: false,
+                local_digest: "".to_string(),
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct KeyExistenceResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// `exists[i]` is true if `key_ids[i]` is stored
+    pub exists: Vec<bool>,
+}
+
+impl From<Result<Vec<bool>, ServiceError>> for KeyExistenceResult {
+    fn from(result: Result<Vec<bool>, ServiceError>) -> Self {
+        match result {
+            Ok(exists) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                exists,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                exists: vec![],
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct DigestResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    /// base58-encoded digest
+    pub digest: String,
+}
+
+impl From<Result<String, ServiceError>> for DigestResult {
+    fn from(result: Result<String, ServiceError>) -> Self {
+        match result {
+            Ok(digest) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                digest,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                digest: "".to_string(),
+            },
+        }
+    }
+}
+
+#[marine]
+pub struct RepublishKeysResult {
+    pub success: bool,
+    pub error: String,
+    pub error_code: i64,
+    pub updated: u64,
+}
+
+impl From<Result<u64, ServiceError>> for RepublishKeysResult {
+    fn from(result: Result<u64, ServiceError>) -> Self {
+        match result {
+            Ok(updated) => Self {
+                success: true,
+                error: "".to_string(),
+                error_code: 0,
+                updated,
+            },
+            Err(err) => Self {
+                success: false,
+                error: err.to_string(),
+                error_code: err.code(),
+                updated: 0,
+            },
+        }
+    }
+}
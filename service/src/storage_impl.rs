@@ -15,7 +15,10 @@
  */
 
 use crate::config::load_config;
-use crate::defaults::DB_PATH;
+use crate::defaults::{
+    DB_PATH, KEYS_TABLE_NAME, PINNED_KEYS_TABLE_NAME, RECORDS_TABLE_NAME, SCHEMA_VERSION,
+    SCHEMA_VERSION_TABLE_NAME,
+};
 use crate::error::ServiceError;
 use crate::record::Record;
 use crate::results::EvictStaleItem;
@@ -25,6 +28,14 @@ pub struct Storage {
     pub(crate) connection: Connection,
 }
 
+pub struct StorageStats {
+    pub keys_count: u64,
+    pub records_count: u64,
+    pub host_records_count: u64,
+    pub pinned_keys_count: u64,
+    pub oldest_key_timestamp: u64,
+}
+
 #[inline]
 pub(crate) fn get_storage() -> SqliteResult<Storage> {
     marine_sqlite_connector::open(DB_PATH).map(|c| Storage { connection: c })
@@ -62,14 +73,180 @@ impl Storage {
         }
     }
 
+    /// Run `f` inside a transaction, changes are rolled back if `f` fails
+    pub fn transaction<T, F>(&self, f: F) -> Result<T, ServiceError>
+    where
+        F: FnOnce(&Self) -> Result<T, ServiceError>,
+    {
+        self.connection.execute("BEGIN TRANSACTION;")?;
+        match f(self) {
+            Ok(result) => {
+                self.connection.execute("COMMIT;")?;
+                Ok(result)
+            }
+            Err(error) => {
+                self.connection.execute("ROLLBACK;")?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Run `f` inside a savepoint, so only its changes are rolled back if it fails.
+    /// Unlike `transaction` it can be nested into a transaction
+    pub fn savepoint<T, F>(&self, f: F) -> Result<T, ServiceError>
+    where
+        F: FnOnce(&Self) -> Result<T, ServiceError>,
+    {
+        self.connection.execute("SAVEPOINT sp;")?;
+        match f(self) {
+            Ok(result) => {
+                self.connection.execute("RELEASE sp;")?;
+                Ok(result)
+            }
+            Err(error) => {
+                self.connection.execute("ROLLBACK TO sp;")?;
+                self.connection.execute("RELEASE sp;")?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Statements migrating the schema from version `i` to `i + 1`, `SCHEMA_VERSION` is their count
+    fn migrations() -> Vec<String> {
+        vec![
+            // 0 -> 1: records may expire
+            f!("ALTER TABLE {RECORDS_TABLE_NAME} ADD COLUMN expires_at INTEGER DEFAULT 0;"),
+            // 1 -> 2: insertion order of records
+            f!("ALTER TABLE {RECORDS_TABLE_NAME} ADD COLUMN seq INTEGER DEFAULT 0;"),
+        ]
+    }
+
+    fn get_schema_version(&self) -> Result<Option<u64>, ServiceError> {
+        let mut statement = self
+            .connection
+            .prepare(f!("SELECT version FROM {SCHEMA_VERSION_TABLE_NAME};"))?;
+
+        if let State::Row = statement.next()? {
+            Ok(Some(statement.read::<i64>(0)? as u64))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn set_schema_version(&self, version: u64) -> Result<(), ServiceError> {
+        self.connection
+            .execute(f!("DELETE FROM {SCHEMA_VERSION_TABLE_NAME};"))?;
+        let mut statement = self.connection.prepare(f!(
+            "INSERT INTO {SCHEMA_VERSION_TABLE_NAME} (version) VALUES (?);"
+        ))?;
+        statement.bind(1, &Value::Integer(version as i64))?;
+        statement.next()?;
+        Ok(())
+    }
+
+    /// Apply migrations from the stored schema version up to `SCHEMA_VERSION`, must run before tables are created.
+    /// Databases written before the version was tracked are detected by the records table layout.
+    /// Fails if the stored version is newer than this binary supports, nothing is touched then
+    pub fn migrate(&self) -> Result<(), ServiceError> {
+        self.connection.execute(f!("
+            CREATE TABLE IF NOT EXISTS {SCHEMA_VERSION_TABLE_NAME} (
+                version INTEGER NOT NULL
+            );
+        "))?;
+
+        let stored_version = match self.get_schema_version()? {
+            Some(version) => version,
+            None => {
+                let records_schema = self.get_table_schema(RECORDS_TABLE_NAME.to_string())?;
+                // an empty database gets the current schema from `create_*_table`
+                if records_schema.is_empty() {
+                    SCHEMA_VERSION
+                } else if records_schema.contains("expires_at") {
+                    1
+                } else {
+                    0
+                }
+            }
+        };
+
+        if stored_version > SCHEMA_VERSION {
+            return Err(ServiceError::UnsupportedSchemaVersion(
+                stored_version,
+                SCHEMA_VERSION,
+            ));
+        }
+
+        let migrations = Self::migrations();
+        for version in stored_version..SCHEMA_VERSION {
+            self.transaction(|storage| {
+                storage.connection.execute(&migrations[version as usize])?;
+                storage.set_schema_version(version + 1)
+            })?;
+        }
+
+        if self.get_schema_version()?.is_none() {
+            self.set_schema_version(SCHEMA_VERSION)?;
+        }
+
+        Ok(())
+    }
+
     pub fn delete_table(&self, table_name: String) -> Result<(), ServiceError> {
         self.connection
             .execute(f!("DROP TABLE IF EXISTS {table_name};"))?;
         Ok(())
     }
 
-    /// Remove expired records (based on `timestamp_created`), expired tombstones (based on `timestamp_issued`)
-    /// and then expired keys without actual records
+    /// Force a checkpoint so committed changes are written to the database file
+    pub fn flush(&self) -> Result<(), ServiceError> {
+        self.connection.execute("PRAGMA wal_checkpoint(FULL);")?;
+        Ok(())
+    }
+
+    /// Return database size on disk as `page_count * page_size`
+    pub fn get_db_size_bytes(&self) -> Result<u64, ServiceError> {
+        let mut size = 1u64;
+        for pragma in ["page_count", "page_size"] {
+            let mut statement = self.connection.prepare(f!("PRAGMA {pragma};"))?;
+            if let State::Row = statement.next()? {
+                size *= statement.read::<i64>(0)? as u64;
+            } else {
+                return Err(ServiceError::InternalError(f!(
+                    "PRAGMA {pragma} returned nothing"
+                )));
+            }
+        }
+
+        Ok(size)
+    }
+
+    /// Return row counts of the tables with aggregate queries, records stored on `host_id` are counted as host records
+    pub fn get_stats(&self, host_id: String) -> Result<StorageStats, ServiceError> {
+        let mut statement = self.connection.prepare(f!("SELECT \
+                (SELECT COUNT(*) FROM {KEYS_TABLE_NAME}), \
+                (SELECT COUNT(*) FROM {RECORDS_TABLE_NAME} WHERE is_tombstoned = 0), \
+                (SELECT COUNT(*) FROM {RECORDS_TABLE_NAME} WHERE is_tombstoned = 0 AND peer_id = ?), \
+                (SELECT COUNT(*) FROM {PINNED_KEYS_TABLE_NAME}), \
+                (SELECT coalesce(MIN(timestamp_created), 0) FROM {KEYS_TABLE_NAME})"))?;
+        statement.bind(1, &Value::String(host_id))?;
+
+        if let State::Row = statement.next()? {
+            Ok(StorageStats {
+                keys_count: statement.read::<i64>(0)? as u64,
+                records_count: statement.read::<i64>(1)? as u64,
+                host_records_count: statement.read::<i64>(2)? as u64,
+                pinned_keys_count: statement.read::<i64>(3)? as u64,
+                oldest_key_timestamp: statement.read::<i64>(4)? as u64,
+            })
+        } else {
+            Err(ServiceError::InternalError(
+                "get_stats: something went totally wrong".to_string(),
+            ))
+        }
+    }
+
+    /// Remove expired records (based on `timestamp_created` or `expires_at`), expired tombstones (based on `timestamp_issued`)
+    /// and then expired keys without actual records. Access entries of removed records are dropped too
     pub fn clear_expired(
         &self,
         current_timestamp_sec: u64,
@@ -78,8 +255,10 @@ impl Storage {
 
         let expired_timestamp = current_timestamp_sec - config.expired_timeout;
         let deleted_tombstones = self.clear_expired_tombstones(expired_timestamp)?;
-        let deleted_records = self.clear_expired_records(expired_timestamp)?;
+        let deleted_records = self.clear_expired_records(expired_timestamp)?
+            + self.clear_expiring_records(current_timestamp_sec)?;
         let deleted_keys = self.clear_expired_keys(expired_timestamp)?;
+        self.delete_stale_records_access()?;
 
         Ok((deleted_keys, deleted_records, deleted_tombstones))
     }
@@ -15,7 +15,9 @@
  */
 #[cfg(test)]
 mod tests {
-    use fluence_keypair::KeyPair;
+    use fluence_keypair::error::VerificationError;
+    use fluence_keypair::{KeyFormat, KeyPair, Signature};
+    use sha2::{Digest, Sha256};
     use std::fs;
 
     use rusqlite::Connection;
@@ -23,17 +25,33 @@ mod tests {
     use marine_rs_sdk_test::{CallParameters, SecurityTetraplet};
     use marine_test_env::registry::{EvictStaleResult, Record, RegistryResult, ServiceInterface};
 
+    use crate::config::ValueEncoding;
     use crate::defaults::{
-        CONFIG_FILE, DB_PATH, DEFAULT_EXPIRED_AGE, DEFAULT_STALE_AGE, KEYS_TABLE_NAME,
-        RECORDS_TABLE_NAME, TRUSTED_TIMESTAMP_FUNCTION_NAME, TRUSTED_TIMESTAMP_SERVICE_ID,
-        TRUSTED_WEIGHT_FUNCTION_NAME, TRUSTED_WEIGHT_SERVICE_ID,
+        CONFIG_FILE, DB_PATH, DEFAULT_EXPIRED_AGE, DEFAULT_STALE_AGE, EXPORT_SCHEMA_VERSION,
+        IMMUTABLE_KEYS_TABLE_NAME, KEYS_TABLE_NAME, KEY_ACCESS_TABLE_NAME,
+        KEY_ID_COLLISIONS_TABLE_NAME, KEY_LABELS_TABLE_NAME, KEY_ROTATIONS_TABLE_NAME,
+        MAX_CLOCK_SKEW_SEC, MAX_DISPLAY_LABEL_LEN, MAX_METADATA_BYTES, MAX_RELAY_IDS,
+        MAX_SERVICE_IDS, MAX_VALUE_SIZE_BYTES, MAX_WEIGHT, OVERWRITE_LOG_TABLE_NAME,
+        PINNED_KEYS_TABLE_NAME, PUT_RATE_LIMIT, RATE_LIMIT_WINDOW_SEC, RECORDS_LIMIT,
+        RECORDS_SEQ_TABLE_NAME, RECORDS_TABLE_NAME, RECORD_ACCESS_TABLE_NAME, SCHEMA_VERSION,
+        SCHEMA_VERSION_TABLE_NAME, TOMBSTONE_GRACE_SEC, TRUSTED_TIMESTAMP_FUNCTION_NAME,
+        TRUSTED_TIMESTAMP_SERVICE_ID, TRUSTED_WEIGHT_FUNCTION_NAME, TRUSTED_WEIGHT_SERVICE_ID,
     };
     use crate::error::ServiceError::{
-        InvalidKeyTimestamp, InvalidTimestampTetraplet, InvalidWeightPeerId,
-        KeyAlreadyExistsNewerTimestamp,
+        AuthorsArgumentEmpty, CasConflict, DisplayLabelTooLong, EmptyKeyPrefix,
+        HostRecordsLimitExceeded, InsufficientCapacity, InvalidBucketSize, InvalidKeyTimestamp,
+        InvalidPageLimit, InvalidRecordMetadataSignature, InvalidRecordSignature,
+        InvalidSignatureFormat, InvalidTimeWindow, InvalidTimestampFuture,
+        InvalidTimestampTetraplet, InvalidTimestampTooOld, InvalidValueEncoding,
+        InvalidWeightPeerId, KeyAlreadyExists, KeyAlreadyExistsNewerTimestamp, KeyImmutable,
+        KeyNotExists, MetadataTooLarge, MissingRelayId, NotAdmin, NotKeyOwner, RecordChangedSince,
+        RecordIdentityNotFound, RecordNotFound, RecordNotIssuedByCaller, RecordsArgumentEmpty,
+        StorageReadOnly, TooManyAuthors, TooManyIds, UnknownEvictionPolicy, UnknownField,
+        UnknownValueEncoding, ValueTooLarge, WeightTooHigh, WeightTooLow, WeightsCountMismatch,
     };
+    use crate::merkle::{verify_merkle_proof, MerkleProofStep};
     use crate::tests::tests::marine_test_env::registry::{
-        Key, RecordMetadata, RegisterKeyResult, Tombstone, WeightResult,
+        Key, MergeResult, RecordMetadata, RegisterKeyResult, Tombstone, WeightResult,
     };
 
     impl PartialEq for Key {
@@ -48,6 +66,9 @@ mod tests {
 
     impl Eq for Key {}
 
+    /// `HOST_SIGNING_KEY` env of the registry module in Config.toml
+    const TEST_HOST_SIGNING_KEY: &str = "B1Vw2etv5SZG8gzALFs7HkufgiR9tLFgUg7bFh7QHnoc";
+
     fn clear_env() {
         let connection = Connection::open(DB_PATH).unwrap();
 
@@ -57,12 +78,133 @@ mod tests {
         connection
             .execute(f!("DROP TABLE IF EXISTS {RECORDS_TABLE_NAME}").as_str(), [])
             .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {OVERWRITE_LOG_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {KEY_ROTATIONS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {KEY_ACCESS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {RECORD_ACCESS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {PINNED_KEYS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {IMMUTABLE_KEYS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {KEY_LABELS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {SCHEMA_VERSION_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {KEY_ID_COLLISIONS_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                f!("DROP TABLE IF EXISTS {RECORDS_SEQ_TABLE_NAME}").as_str(),
+                [],
+            )
+            .unwrap();
 
         if fs::metadata(CONFIG_FILE).is_ok() {
             fs::remove_file(CONFIG_FILE).unwrap();
         }
     }
 
+    /// Run `sql` with text `params` on the database of `registry` through its own sqlite module, bypassing the service.
+    /// Return the first column of result rows as text and the number of changed rows
+    fn execute_service_db(
+        registry: &mut ServiceInterface,
+        sql: &str,
+        params: &[&str],
+    ) -> (Vec<String>, i32) {
+        const SQLITE_OPEN_READWRITE: i32 = 2;
+        const SQLITE_ROW: i32 = 100;
+        const SQLITE_DONE: i32 = 101;
+
+        let sqlite = &mut registry.modules.sqlite3;
+        let db = sqlite.sqlite3_open_v2(DB_PATH.to_string(), SQLITE_OPEN_READWRITE, "".to_string());
+        assert_eq!(db.ret_code, 0);
+        let statement = sqlite.sqlite3_prepare_v2(db.db_handle, sql.to_string());
+        assert_eq!(
+            statement.ret_code,
+            0,
+            "{}",
+            sqlite.sqlite3_errmsg(db.db_handle)
+        );
+        for (i, param) in params.iter().enumerate() {
+            sqlite.sqlite3_bind_text(statement.stmt_handle, i as i32 + 1, param.to_string(), 0);
+        }
+
+        let mut rows = vec![];
+        loop {
+            match sqlite.sqlite3_step(statement.stmt_handle) {
+                SQLITE_ROW => rows.push(sqlite.sqlite3_column_text(statement.stmt_handle, 0)),
+                SQLITE_DONE => break,
+                _ => panic!("{}", sqlite.sqlite3_errmsg(db.db_handle)),
+            }
+        }
+        let changes = sqlite.sqlite3_changes(db.db_handle);
+        sqlite.sqlite3_finalize(statement.stmt_handle);
+        sqlite.sqlite3_close(db.db_handle);
+
+        (rows, changes)
+    }
+
+    /// Delete the key row from the service database bypassing the service, leaving its records orphaned
+    fn delete_key_row(registry: &mut ServiceInterface, key_id: &str) {
+        let (_, deleted) = execute_service_db(
+            registry,
+            &f!("DELETE FROM {KEYS_TABLE_NAME} WHERE key_id = ?1"),
+            &[key_id],
+        );
+        assert_eq!(deleted, 1);
+    }
+
+    /// Make the service database read-only: sqlite refuses to write to databases
+    /// with file format write version (header byte 18) greater than 2
+    fn make_db_read_only(registry: &mut ServiceInterface) {
+        execute_service_db(
+            registry,
+            "UPDATE sqlite_dbpage SET data = CAST(substr(data, 1, 18) || x'03' || substr(data, 20) AS BLOB) \
+                WHERE pgno = 1",
+            &[],
+        );
+    }
+
     struct CPWrapper {
         pub cp: CallParameters,
     }
@@ -111,6 +253,24 @@ mod tests {
             self
         }
 
+        fn add_batch_weight_tetraplets(mut self, arg_number: usize, count: usize) -> Self {
+            if self.cp.tetraplets.len() <= arg_number {
+                self.cp.tetraplets.resize(arg_number + 1, vec![]);
+            }
+
+            self.cp.tetraplets[arg_number] = vec![
+                SecurityTetraplet {
+                    peer_pk: self.cp.host_id.clone(),
+                    service_id: TRUSTED_WEIGHT_SERVICE_ID.to_string(),
+                    function_name: TRUSTED_WEIGHT_FUNCTION_NAME.to_string(),
+                    json_path: "".to_string(),
+                };
+                count
+            ];
+
+            self
+        }
+
         pub fn get(&self) -> CallParameters {
             self.cp.clone()
         }
@@ -213,9 +373,13 @@ mod tests {
         result.key_id
     }
 
-    fn get_key_metadata(registry: &mut ServiceInterface, key_id: String) -> Key {
+    fn get_key_metadata(
+        registry: &mut ServiceInterface,
+        key_id: String,
+        current_timestamp: u64,
+    ) -> Key {
         let cp = CPWrapper::new("peer_id", "host_if").add_timestamp_tetraplets(1);
-        let result = registry.get_key_metadata_cp(key_id, cp.get());
+        let result = registry.get_key_metadata_cp(key_id, current_timestamp, cp.get());
         assert!(result.success, "{}", result.error);
         result.key
     }
@@ -313,6 +477,36 @@ mod tests {
         host_kp.sign(&bytes).unwrap().to_vec().to_vec()
     }
 
+    fn create_record(
+        registry: &mut ServiceInterface,
+        key_id: String,
+        issuer_kp: &KeyPair,
+        host_kp: &KeyPair,
+        timestamp_created: u64,
+        value: String,
+    ) -> Record {
+        let metadata = create_record_metadata(
+            registry,
+            key_id,
+            issuer_kp,
+            timestamp_created,
+            value,
+            host_kp.get_peer_id().to_base58(),
+            vec![],
+            vec![],
+            vec![],
+        );
+        let signature =
+            get_signed_record_bytes(registry, host_kp, metadata.clone(), timestamp_created);
+
+        Record {
+            metadata,
+            timestamp_created,
+            expires_at: 0,
+            signature,
+        }
+    }
+
     fn put_record(
         registry: &mut ServiceInterface,
         key_id: String,
@@ -724,7 +918,7 @@ mod tests {
             weight,
         );
 
-        let old_key = get_key_metadata(&mut registry, key_id.clone());
+        let old_key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
 
         let timestamp_created_new = timestamp_created_old + 10u64;
         register_key_checked(
@@ -735,7 +929,7 @@ mod tests {
             current_timestamp,
             weight,
         );
-        let new_key = get_key_metadata(&mut registry, key_id.clone());
+        let new_key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
         assert_ne!(old_key, new_key);
 
         let cp = CPWrapper::new(&issuer_peer_id, "host_id")
@@ -746,7 +940,7 @@ mod tests {
             registry.republish_key_cp(old_key.clone(), weight, current_timestamp, cp.get());
         assert!(result.success, "{}", result.error);
 
-        let result_key = get_key_metadata(&mut registry, key_id.clone());
+        let result_key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
         assert_eq!(new_key, result_key);
     }
 
@@ -781,7 +975,7 @@ mod tests {
             weight,
         );
 
-        let result_key = get_key_metadata(&mut registry, key_id.clone());
+        let result_key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
         let expected_key = Key {
             id: key_id,
             label,
@@ -814,7 +1008,7 @@ mod tests {
             weight,
         );
 
-        let result_key = get_key_metadata(&mut registry, key_id.clone());
+        let result_key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
         let cp = CPWrapper::new(&issuer_peer_id, "host_id")
             .add_weight_tetraplets(1)
             .add_timestamp_tetraplets(2);
@@ -869,7 +1063,7 @@ mod tests {
             weight,
         );
 
-        let key = get_key_metadata(&mut registry, key_id);
+        let key = get_key_metadata(&mut registry, key_id, current_timestamp);
 
         current_timestamp += DEFAULT_STALE_AGE;
         let evict_result = evict_stale_checked(&mut registry, current_timestamp);
@@ -1029,4 +1223,6093 @@ mod tests {
         let tombstones = get_tombstones(&mut registry, key_id.clone(), current_timestamp);
         assert_eq!(tombstones.len(), 0);
     }
+
+    #[test]
+    fn purge_tombstones_past_grace_period() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let label = "some_key".to_string();
+        let timestamp_old = 100u64;
+        let timestamp_fresh = timestamp_old + TOMBSTONE_GRACE_SEC;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            label,
+            timestamp_old,
+            timestamp_old,
+            weight,
+        );
+
+        add_tombstone_checked(
+            &mut registry,
+            key_id.clone(),
+            "old_peer_id".to_string(),
+            &issuer_kp,
+            timestamp_old,
+            vec![],
+        );
+        add_tombstone_checked(
+            &mut registry,
+            key_id.clone(),
+            "fresh_peer_id".to_string(),
+            &issuer_kp,
+            timestamp_fresh,
+            vec![],
+        );
+
+        let current_timestamp = timestamp_fresh + 1;
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(0);
+        let result = registry.purge_tombstones_cp(current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count, 1);
+
+        let tombstones = get_tombstones(&mut registry, key_id, current_timestamp);
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(tombstones[0].peer_id, "fresh_peer_id");
+    }
+
+    #[test]
+    fn get_records_by_authors() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers = vec![
+            KeyPair::generate_ed25519(),
+            KeyPair::generate_ed25519(),
+            KeyPair::generate_ed25519(),
+        ];
+        let label = "some_key".to_string();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            label,
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        for issuer_kp in issuers.iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let authors = vec![
+            issuers[0].get_peer_id().to_base58(),
+            issuers[2].get_peer_id().to_base58(),
+        ];
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_records_by_authors_cp(
+            key_id.clone(),
+            authors.clone(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 2);
+        assert!(result
+            .result
+            .iter()
+            .all(|r| authors.contains(&r.metadata.issued_by)));
+
+        let result =
+            registry.get_records_by_authors_cp(key_id, vec![], current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, AuthorsArgumentEmpty.to_string());
+    }
+
+    #[test]
+    fn republish_records_batch_tetraplets() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let label = "some_key".to_string();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            label,
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+
+        let records = vec![
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                timestamp_created,
+                "some_value".to_string(),
+            ),
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &other_issuer_kp,
+                &host_kp,
+                timestamp_created,
+                "other_value".to_string(),
+            ),
+        ];
+        let weights = vec![
+            get_weight(issuer_kp.get_peer_id().to_base58(), 0),
+            get_weight(other_issuer_kp.get_peer_id().to_base58(), 0),
+        ];
+
+        let mut cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 2)
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(
+            records.clone(),
+            weights.clone(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+
+        cp.cp.tetraplets[1][1].service_id = "fake_trust_graph".to_string();
+        let result = registry.republish_records_cp(records, weights, current_timestamp, cp.get());
+        assert!(!result.success);
+        assert!(result.error.starts_with("Invalid weight tetraplet"));
+    }
+
+    #[test]
+    fn get_records_with_freshness_stale_key() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let label = "some_key".to_string();
+        let timestamp_created = 100u64;
+        let mut current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &kp,
+            label,
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        current_timestamp += DEFAULT_STALE_AGE;
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result =
+            registry.get_records_with_freshness_cp(key_id.clone(), current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.stale);
+
+        let key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
+        republish_key_checked(&mut registry, key, current_timestamp);
+        let result = registry.get_records_with_freshness_cp(key_id, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(!result.stale);
+    }
+
+    #[test]
+    fn put_record_value_encoding() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let owner_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        let cases = vec![
+            (ValueEncoding::Utf8, "значение", Some("line\nbreak\u{0}")),
+            (
+                ValueEncoding::Json,
+                r#"{"value": [1, 2]}"#,
+                Some("{value: 1"),
+            ),
+            (
+                ValueEncoding::Base64,
+                "c29tZV92YWx1ZQ==",
+                Some("some_value!"),
+            ),
+        ];
+        for (encoding, valid_value, invalid_value) in cases.into_iter() {
+            let result = registry.set_value_encoding(format!("{:?}", encoding));
+            assert!(result.success, "{}", result.error);
+
+            let result = put_record(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                valid_value.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+            assert!(result.success, "{}", result.error);
+
+            if let Some(invalid_value) = invalid_value {
+                let result = put_record(
+                    &mut registry,
+                    key_id.clone(),
+                    &KeyPair::generate_ed25519(),
+                    &host_kp,
+                    timestamp_created,
+                    timestamp_created,
+                    invalid_value.to_string(),
+                    vec![],
+                    vec![],
+                    vec![],
+                    weight,
+                );
+                assert_eq!(
+                    result.error,
+                    InvalidValueEncoding(key_id.clone(), encoding).to_string()
+                );
+            }
+        }
+
+        let result = registry.set_value_encoding("Xml".to_string());
+        assert_eq!(
+            result.error,
+            UnknownValueEncoding("Xml".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn move_records_to_another_key() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let host_kps = vec![KeyPair::generate_ed25519(), KeyPair::generate_ed25519()];
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let from_key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "old_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+        let to_key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "new_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        let mut resigned_records = vec![];
+        for host_kp in host_kps.iter() {
+            put_record_checked(
+                &mut registry,
+                from_key_id.clone(),
+                &owner_kp,
+                host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+            resigned_records.push(create_record(
+                &mut registry,
+                to_key_id.clone(),
+                &owner_kp,
+                host_kp,
+                timestamp_created,
+                "some_value".to_string(),
+            ));
+        }
+        let weights = vec![get_weight(owner_peer_id.clone(), weight); 2];
+
+        let cp = CPWrapper::new(&owner_peer_id, "host_id")
+            .add_batch_weight_tetraplets(3, 2)
+            .add_timestamp_tetraplets(4);
+        let result = registry.move_records_cp(
+            from_key_id.clone(),
+            to_key_id.clone(),
+            resigned_records,
+            weights.clone(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 2);
+
+        assert!(get_records(&mut registry, from_key_id, current_timestamp).is_empty());
+        let moved_records = get_records(&mut registry, to_key_id, current_timestamp);
+        assert_eq!(moved_records.len(), 2);
+
+        // moved records verify under the new key
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 2)
+            .add_timestamp_tetraplets(2);
+        let result =
+            registry.republish_records_cp(moved_records, weights, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+    }
+
+    #[test]
+    fn move_records_partial_batch_keeps_other_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let moved_host_kp = KeyPair::generate_ed25519();
+        let kept_host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let from_key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "old_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        let to_key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "new_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        for host_kp in [&moved_host_kp, &kept_host_kp] {
+            put_record_checked(
+                &mut registry,
+                from_key_id.clone(),
+                &owner_kp,
+                host_kp,
+                current_timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new(&owner_peer_id, "host_id")
+            .add_batch_weight_tetraplets(3, 0)
+            .add_timestamp_tetraplets(4);
+        let result = registry.move_records_cp(
+            from_key_id.clone(),
+            to_key_id.clone(),
+            vec![],
+            vec![],
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(result.error, RecordsArgumentEmpty.to_string());
+
+        let resigned_record = create_record(
+            &mut registry,
+            to_key_id.clone(),
+            &owner_kp,
+            &moved_host_kp,
+            current_timestamp,
+            "some_value".to_string(),
+        );
+        let cp = CPWrapper::new(&owner_peer_id, "host_id")
+            .add_batch_weight_tetraplets(3, 1)
+            .add_timestamp_tetraplets(4);
+        let result = registry.move_records_cp(
+            from_key_id.clone(),
+            to_key_id.clone(),
+            vec![resigned_record],
+            vec![get_weight(owner_peer_id, 0)],
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 1);
+
+        // the record not in the batch stays under the old key
+        let kept_records = get_records(&mut registry, from_key_id, current_timestamp);
+        assert_eq!(kept_records.len(), 1);
+        assert_eq!(
+            kept_records[0].metadata.peer_id,
+            kept_host_kp.get_peer_id().to_base58()
+        );
+        let moved_records = get_records(&mut registry, to_key_id, current_timestamp);
+        assert_eq!(moved_records.len(), 1);
+        assert_eq!(
+            moved_records[0].metadata.peer_id,
+            moved_host_kp.get_peer_id().to_base58()
+        );
+    }
+
+    #[test]
+    fn get_consensus_value_highest_weight_wins() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let owner_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+
+        // "value_a" has more supporters but "value_b" has higher total weight
+        for (value, weight) in [("value_a", 1), ("value_a", 2), ("value_b", 4)] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                value.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_consensus_value_cp(key_id.clone(), current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.value, "value_b");
+        assert_eq!(result.weight, 4);
+        assert_eq!(result.support, 4.0 / 7.0);
+        assert!(!result.tie);
+
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &KeyPair::generate_ed25519(),
+            &host_kp,
+            timestamp_created,
+            timestamp_created,
+            "value_a".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            1,
+        );
+        let result = registry.get_consensus_value_cp(key_id, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.value, "value_a");
+        assert!(result.tie);
+    }
+
+    #[test]
+    fn clear_records_before_timestamp() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let current_timestamp = 200u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            100,
+            current_timestamp,
+            weight,
+        );
+
+        for (kp, timestamp_created) in [
+            (&issuer_kp, 100u64),
+            (&issuer_kp, 200u64),
+            (&other_issuer_kp, 100u64),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                kp,
+                &KeyPair::generate_ed25519(),
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let cp = CPWrapper::new(&issuer_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result =
+            registry.clear_records_before_cp(key_id.clone(), 150, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count, 1);
+
+        let records = get_records(&mut registry, key_id.clone(), current_timestamp);
+        assert_eq!(records.len(), 2);
+        assert!(records
+            .iter()
+            .all(|r| r.metadata.issued_by != issuer_peer_id || r.timestamp_created == 200));
+
+        // created within the clock skew window
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &KeyPair::generate_ed25519(),
+            current_timestamp + 30,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            weight,
+        );
+
+        // records of pinned keys are kept
+        let result = registry.set_key_pinned_cp(key_id.clone(), true, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let result = registry.clear_records_before_cp(
+            key_id.clone(),
+            current_timestamp + 1000,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count, 0);
+
+        let result = registry.set_key_pinned_cp(key_id.clone(), false, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let result = registry.clear_records_before_cp(
+            key_id.clone(),
+            current_timestamp + 1000,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count, 2);
+
+        let records = get_records(&mut registry, key_id, current_timestamp);
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].metadata.issued_by,
+            other_issuer_kp.get_peer_id().to_base58()
+        );
+    }
+
+    #[test]
+    fn flush_storage_after_writes() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &kp,
+            &KeyPair::generate_ed25519(),
+            timestamp_created,
+            timestamp_created,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            weight,
+        );
+
+        let result = registry.flush_storage();
+        assert!(result.success, "{}", result.error);
+        assert_eq!(
+            get_records(&mut registry, key_id, current_timestamp).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn get_rate_limit_status_decrements() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let status =
+            registry.get_rate_limit_status_cp(issuer_peer_id.clone(), current_timestamp, cp.get());
+        assert!(status.success, "{}", status.error);
+        assert_eq!(status.remaining, PUT_RATE_LIMIT);
+
+        for _ in 0..2 {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &KeyPair::generate_ed25519(),
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let status = registry.get_rate_limit_status_cp(
+            issuer_peer_id.clone(),
+            current_timestamp + 10,
+            cp.get(),
+        );
+        assert!(status.success, "{}", status.error);
+        assert_eq!(status.remaining, PUT_RATE_LIMIT - 2);
+        assert_eq!(status.reset_in_sec, RATE_LIMIT_WINDOW_SEC - 10);
+
+        let status = registry.get_rate_limit_status_cp(
+            issuer_peer_id,
+            current_timestamp + RATE_LIMIT_WINDOW_SEC,
+            cp.get(),
+        );
+        assert_eq!(status.remaining, PUT_RATE_LIMIT);
+    }
+
+    #[test]
+    fn remove_keys_by_prefix_keeps_siblings() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let peer_id = kp.get_peer_id().to_base58();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let mut key_ids = vec![];
+        for label in ["app/users/a", "app/users/b", "app/groups/a"] {
+            key_ids.push(register_key_checked(
+                &mut registry,
+                &kp,
+                label.to_string(),
+                timestamp_created,
+                current_timestamp,
+                weight,
+            ));
+        }
+        put_record_checked(
+            &mut registry,
+            key_ids[0].clone(),
+            &kp,
+            &KeyPair::generate_ed25519(),
+            timestamp_created,
+            timestamp_created,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            weight,
+        );
+
+        let cp = CPWrapper::new(&peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result =
+            registry.remove_keys_by_prefix_cp("".to_string(), false, current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, EmptyKeyPrefix.to_string());
+
+        let result = registry.remove_keys_by_prefix_cp(
+            "app/users/".to_string(),
+            false,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 2);
+        assert_eq!(result.records_count, 1);
+
+        let cp = CPWrapper::new("peer_id", "host_if").add_timestamp_tetraplets(1);
+        assert!(
+            !registry
+                .get_key_metadata_cp(key_ids[0].clone(), current_timestamp, cp.get())
+                .success
+        );
+        assert!(
+            !registry
+                .get_key_metadata_cp(key_ids[1].clone(), current_timestamp, cp.get())
+                .success
+        );
+        get_key_metadata(&mut registry, key_ids[2].clone(), current_timestamp);
+    }
+
+    #[test]
+    fn remove_keys_by_prefix_skips_pinned_unless_forced() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let peer_id = kp.get_peer_id().to_base58();
+        let pinned_key_id =
+            register_key_checked(&mut registry, &kp, "app/a".to_string(), 10, 10, 0);
+        let key_id = register_key_checked(&mut registry, &kp, "app/b".to_string(), 10, 10, 0);
+
+        let cp = CPWrapper::new(&peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.set_key_pinned_cp(pinned_key_id.clone(), true, 10, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let result = registry.remove_keys_by_prefix_cp("app/".to_string(), false, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 1);
+        let cp = CPWrapper::new(&peer_id, "host_id").add_timestamp_tetraplets(1);
+        assert!(!registry.get_key_metadata_cp(key_id, 20, cp.get()).success);
+        get_key_metadata(&mut registry, pinned_key_id.clone(), 20);
+
+        let cp = CPWrapper::new(&peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.remove_keys_by_prefix_cp("app/".to_string(), true, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 1);
+        let cp = CPWrapper::new(&peer_id, "host_id").add_timestamp_tetraplets(1);
+        assert!(
+            !registry
+                .get_key_metadata_cp(pinned_key_id, 20, cp.get())
+                .success
+        );
+    }
+
+    #[test]
+    fn overwrite_log_records_superseded_version() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        registry.set_overwrite_log(true);
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        for (timestamp, value) in [(100u64, "first"), (200u64, "second")] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                timestamp,
+                timestamp,
+                value.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let result = registry.get_overwrite_log(key_id.clone(), 10);
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        let entry = &result.result[0];
+        assert_eq!(entry.key_id, key_id);
+        assert_eq!(entry.issued_by, issuer_kp.get_peer_id().to_base58());
+        assert_eq!(entry.peer_id, host_kp.get_peer_id().to_base58());
+        assert_eq!(entry.timestamp_issued, 100);
+        assert_eq!(entry.timestamp_created, 100);
+    }
+
+    #[test]
+    fn get_top_records_by_weight() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..10).map(|_| KeyPair::generate_ed25519()).collect();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+
+        for (weight, issuer_kp) in (1u32..).zip(issuers.iter()) {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_top_records_cp(key_id.clone(), 3, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let issued_by: Vec<String> = result
+            .result
+            .iter()
+            .map(|r| r.metadata.issued_by.clone())
+            .collect();
+        let expected: Vec<String> = issuers[7..]
+            .iter()
+            .rev()
+            .map(|kp| kp.get_peer_id().to_base58())
+            .collect();
+        assert_eq!(issued_by, expected);
+
+        let result = registry.get_top_records_cp(key_id, 0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+    }
+
+    #[test]
+    fn is_key_owner_check() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let peer_id = kp.get_peer_id().to_base58();
+        let other_peer_id = KeyPair::generate_ed25519().get_peer_id().to_base58();
+
+        let key_id = register_key_checked(&mut registry, &kp, "some_key".to_string(), 100, 100, 0);
+
+        let result = registry.is_key_owner(key_id.clone(), peer_id.clone());
+        assert!(result.success, "{}", result.error);
+        assert!(result.exists);
+        assert!(result.is_owner);
+
+        let result = registry.is_key_owner(key_id, other_peer_id.clone());
+        assert!(result.success, "{}", result.error);
+        assert!(result.exists);
+        assert!(!result.is_owner);
+
+        let missing_key_id = registry.get_key_id("missing_key".to_string(), other_peer_id);
+        let result = registry.is_key_owner(missing_key_id, peer_id);
+        assert!(result.success, "{}", result.error);
+        assert!(!result.exists);
+        assert!(!result.is_owner);
+    }
+
+    #[test]
+    fn purge_invalid_records_removes_failing_verification() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let current_timestamp = 150u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            100,
+            100,
+            weight,
+        );
+        // the last record is created within the clock skew window and must survive the purge
+        for (issuer_kp, timestamp) in issuers.iter().zip([100u64, 100, current_timestamp + 30]) {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        // the second record fails verification
+        let broken_issuer = issuers[1].get_peer_id().to_base58();
+        let (_, updated) = execute_service_db(
+            &mut registry,
+            &f!("UPDATE {RECORDS_TABLE_NAME} SET value = 'other_value' WHERE issued_by = ?1"),
+            &[&broken_issuer],
+        );
+        assert_eq!(updated, 1);
+
+        let cp = CPWrapper::new("some_peer_id", &host_id).add_timestamp_tetraplets(0);
+        let result = registry.purge_invalid_records_cp(current_timestamp, 0, 0, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotAdmin("some_peer_id".to_string()).to_string()
+        );
+
+        // one record per call, the cursor reaches the records after the first one
+        let cp = CPWrapper::new(&host_id, &host_id).add_timestamp_tetraplets(0);
+        let mut purged = vec![];
+        let mut after_rowid = 0;
+        loop {
+            let result =
+                registry.purge_invalid_records_cp(current_timestamp, after_rowid, 1, cp.get());
+            assert!(result.success, "{}", result.error);
+            purged.push(result.count);
+            if result.next_rowid == 0 {
+                break;
+            }
+            after_rowid = result.next_rowid;
+        }
+        assert_eq!(purged, vec![0, 1, 0, 0]);
+
+        let mut remaining: Vec<String> = get_records(&mut registry, key_id, current_timestamp)
+            .into_iter()
+            .map(|r| r.metadata.issued_by)
+            .collect();
+        remaining.sort();
+        let mut expected = vec![
+            issuers[0].get_peer_id().to_base58(),
+            issuers[2].get_peer_id().to_base58(),
+        ];
+        expected.sort();
+        assert_eq!(remaining, expected);
+    }
+
+    #[test]
+    fn merge_equal_timestamp_host_records_deterministic() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &host_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            timestamp_created,
+            0,
+        );
+
+        let mut records: Vec<Record> = (0..2)
+            .map(|_| {
+                create_record(
+                    &mut registry,
+                    key_id.clone(),
+                    &KeyPair::generate_ed25519(),
+                    &host_kp,
+                    timestamp_created,
+                    "some_value".to_string(),
+                )
+            })
+            .collect();
+        records.sort_by(|l, r| l.metadata.issued_by.cmp(&r.metadata.issued_by));
+
+        let straight = registry.merge_two(vec![records[0].clone()], vec![records[1].clone()]);
+        let reversed = registry.merge_two(vec![records[1].clone()], vec![records[0].clone()]);
+        assert!(straight.success, "{}", straight.error);
+        assert!(reversed.success, "{}", reversed.error);
+
+        let issued_by = |result: &MergeResult| -> Vec<String> {
+            result
+                .result
+                .iter()
+                .map(|r| r.metadata.issued_by.clone())
+                .collect()
+        };
+        assert_eq!(issued_by(&straight), issued_by(&reversed));
+        assert_eq!(
+            straight.result[0].metadata.issued_by,
+            records[0].metadata.issued_by
+        );
+    }
+
+    #[test]
+    fn get_db_size_bytes_grows() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &host_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            timestamp_created,
+            0,
+        );
+
+        let before = registry.get_db_size_bytes();
+        assert!(before.success, "{}", before.error);
+        assert!(before.size > 0);
+
+        for _ in 0..30 {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "x".repeat(250),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let after = registry.get_db_size_bytes();
+        assert!(after.success, "{}", after.error);
+        assert!(after.size > before.size);
+    }
+
+    #[test]
+    fn clear_host_records_batch() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_ids: Vec<String> = ["key_a", "key_b", "key_c"]
+            .iter()
+            .map(|label| {
+                register_key_checked(
+                    &mut registry,
+                    &issuer_kp,
+                    label.to_string(),
+                    timestamp_created,
+                    current_timestamp,
+                    weight,
+                )
+            })
+            .collect();
+
+        for (key_id, issuer) in [
+            (&key_ids[0], &issuer_kp),
+            (&key_ids[0], &other_issuer_kp),
+            (&key_ids[1], &issuer_kp),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let cp = CPWrapper::new(&issuer_kp.get_peer_id().to_base58(), &host_id)
+            .add_timestamp_tetraplets(1);
+        let result = registry.clear_host_records_cp(key_ids.clone(), current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let counts: Vec<(String, bool, u64)> = result
+            .results
+            .into_iter()
+            .map(|item| (item.key_id, item.success, item.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                (key_ids[0].clone(), true, 1),
+                (key_ids[1].clone(), true, 1),
+                (key_ids[2].clone(), true, 0),
+            ]
+        );
+
+        let records = get_records(&mut registry, key_ids[0].clone(), current_timestamp);
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].metadata.issued_by,
+            other_issuer_kp.get_peer_id().to_base58()
+        );
+        assert!(get_records(&mut registry, key_ids[1].clone(), current_timestamp).is_empty());
+    }
+
+    #[test]
+    fn get_records_by_value_from_several_authors() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers = vec![
+            KeyPair::generate_ed25519(),
+            KeyPair::generate_ed25519(),
+            KeyPair::generate_ed25519(),
+        ];
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let weight = 0;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            weight,
+        );
+
+        for (issuer_kp, value) in issuers.iter().zip(["shared", "shared", "other"]) {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                value.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_records_by_value_cp(
+            key_id,
+            "shared".to_string(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        let mut issued_by: Vec<String> = result
+            .result
+            .into_iter()
+            .map(|r| r.metadata.issued_by)
+            .collect();
+        issued_by.sort();
+        let mut expected: Vec<String> = issuers[..2]
+            .iter()
+            .map(|kp| kp.get_peer_id().to_base58())
+            .collect();
+        expected.sort();
+        assert_eq!(issued_by, expected);
+    }
+
+    #[test]
+    fn key_rotation_store_and_resolve() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let old_kp = KeyPair::generate_ed25519();
+        let new_kp = KeyPair::generate_ed25519();
+        let old_peer_id = old_kp.get_peer_id().to_base58();
+        let new_peer_id = new_kp.get_peer_id().to_base58();
+        let timestamp_issued = 100u64;
+        let current_timestamp = 100u64;
+
+        let bytes = registry.get_key_rotation_bytes(
+            old_peer_id.clone(),
+            new_peer_id.clone(),
+            timestamp_issued,
+        );
+        let old_signature = old_kp.sign(&bytes).unwrap().to_vec().to_vec();
+        let new_signature = new_kp.sign(&bytes).unwrap().to_vec().to_vec();
+
+        let cp = CPWrapper::new(&old_peer_id, "host_id").add_timestamp_tetraplets(5);
+        // signed only by the old key
+        let result = registry.add_key_rotation_cp(
+            old_peer_id.clone(),
+            new_peer_id.clone(),
+            timestamp_issued,
+            old_signature.clone(),
+            old_signature.clone(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+
+        let result = registry.add_key_rotation_cp(
+            old_peer_id.clone(),
+            new_peer_id.clone(),
+            timestamp_issued,
+            old_signature,
+            new_signature,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+
+        let result = registry.get_key_rotation(old_peer_id);
+        assert!(result.success, "{}", result.error);
+        assert!(result.rotated);
+        assert_eq!(result.peer_id, new_peer_id);
+
+        let result = registry.get_key_rotation(new_peer_id.clone());
+        assert!(result.success, "{}", result.error);
+        assert!(!result.rotated);
+        assert_eq!(result.peer_id, new_peer_id);
+    }
+
+    #[test]
+    fn get_records_weight_budget_minimal_set() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..5).map(|_| KeyPair::generate_ed25519()).collect();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+
+        for (weight, issuer_kp) in (1u32..).zip(issuers.iter()) {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        // 5 + 4 reaches the budget
+        let result =
+            registry.get_records_weight_budget_cp(key_id.clone(), 8, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let issued_by: Vec<String> = result
+            .result
+            .into_iter()
+            .map(|r| r.metadata.issued_by)
+            .collect();
+        assert_eq!(
+            issued_by,
+            vec![
+                issuers[4].get_peer_id().to_base58(),
+                issuers[3].get_peer_id().to_base58()
+            ]
+        );
+
+        let result = registry.get_records_weight_budget_cp(key_id, 0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+    }
+
+    #[test]
+    fn compact_key_drops_expired_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers = vec![KeyPair::generate_ed25519(), KeyPair::generate_ed25519()];
+        let old_timestamp = 100u64;
+        let current_timestamp = old_timestamp + DEFAULT_EXPIRED_AGE;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "some_key".to_string(),
+            old_timestamp,
+            old_timestamp,
+            0,
+        );
+
+        for (issuer_kp, timestamp) in issuers.iter().zip([old_timestamp, current_timestamp]) {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp,
+                timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.compact_key_cp(key_id.clone(), current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotKeyOwner(key_id.clone(), "some_peer_id".to_string()).to_string()
+        );
+
+        let seq_sql = f!("SELECT seq FROM {RECORDS_TABLE_NAME} ORDER BY seq");
+        let (seqs, _) = execute_service_db(&mut registry, &seq_sql, &[]);
+        assert_eq!(seqs.len(), 2);
+
+        let cp = CPWrapper::new(&owner_peer_id, "host_id").add_timestamp_tetraplets(1);
+        let result = registry.compact_key_cp(key_id.clone(), current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        // the kept record isn't rewritten
+        let (compacted_seqs, _) = execute_service_db(&mut registry, &seq_sql, &[]);
+        assert_eq!(compacted_seqs, vec![seqs[1].clone()]);
+
+        // nothing is left for clear_expired
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(0);
+        let result = registry.clear_expired_cp(current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count_records, 0);
+
+        let records = get_records(&mut registry, key_id, current_timestamp);
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].metadata.issued_by,
+            issuers[1].get_peer_id().to_base58()
+        );
+    }
+
+    #[test]
+    fn get_records_returns_identities() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers = vec![KeyPair::generate_ed25519(), KeyPair::generate_ed25519()];
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+        for issuer_kp in issuers.iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(key_id, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 2);
+        assert_eq!(result.identities.len(), 2);
+        for (record, identity) in result.result.iter().zip(result.identities.iter()) {
+            let expected = registry.get_record_identity(
+                record.metadata.key_id.clone(),
+                record.metadata.issued_by.clone(),
+                record.metadata.peer_id.clone(),
+            );
+            assert_eq!(identity, &expected);
+        }
+        assert_ne!(result.identities[0], result.identities[1]);
+    }
+
+    #[test]
+    fn republish_records_clock_skew_tolerance() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        let records = vec![
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                current_timestamp + MAX_CLOCK_SKEW_SEC / 2,
+                "slightly_ahead".to_string(),
+            ),
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &other_issuer_kp,
+                &host_kp,
+                current_timestamp + MAX_CLOCK_SKEW_SEC + 1,
+                "too_far_ahead".to_string(),
+            ),
+        ];
+        let weights = vec![
+            get_weight(issuer_kp.get_peer_id().to_base58(), 0),
+            get_weight(other_issuer_kp.get_peer_id().to_base58(), 0),
+        ];
+
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 2)
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(records, weights, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 1);
+        assert_eq!(result.skipped, 1);
+
+        let records = get_records(&mut registry, key_id, current_timestamp);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.value, "slightly_ahead");
+    }
+
+    #[test]
+    fn get_all_service_ids_distinct() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+        let owner_kp = KeyPair::generate_ed25519();
+
+        let key_ids: Vec<String> = ["key_a", "key_b"]
+            .iter()
+            .map(|label| {
+                register_key_checked(
+                    &mut registry,
+                    &owner_kp,
+                    label.to_string(),
+                    timestamp_created,
+                    current_timestamp,
+                    0,
+                )
+            })
+            .collect();
+
+        for (key_id, service_id) in [
+            (&key_ids[0], vec!["service_b".to_string()]),
+            (&key_ids[0], vec!["service_a".to_string()]),
+            (&key_ids[1], vec!["service_b".to_string()]),
+            (&key_ids[1], vec![]),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                service_id,
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_all_service_ids_cp(0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(
+            result.service_ids,
+            vec!["service_a".to_string(), "service_b".to_string()]
+        );
+
+        let result = registry.get_all_service_ids_cp(1, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.service_ids, vec!["service_a".to_string()]);
+    }
+
+    #[test]
+    fn strict_signatures_reject_wrong_format() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        registry.set_strict_signatures(true);
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        // well-formed signatures pass the strict check
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            timestamp_created,
+            timestamp_created,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new(&issuer_peer_id, "host_id")
+            .add_weight_tetraplets(6)
+            .add_timestamp_tetraplets(7);
+        let result = registry.register_key_cp(
+            "other_key".to_string(),
+            vec![issuer_peer_id.clone()],
+            timestamp_created,
+            vec![],
+            "".to_string(),
+            vec![0u8; 10],
+            get_weight(issuer_peer_id.clone(), 0),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            InvalidSignatureFormat(issuer_peer_id.clone(), "ed25519".to_string()).to_string()
+        );
+
+        let metadata = create_record_metadata(
+            &mut registry,
+            key_id,
+            &issuer_kp,
+            timestamp_created,
+            "other_value".to_string(),
+            host_id.clone(),
+            vec![],
+            vec![],
+            vec![],
+        );
+        let mut signature =
+            get_signed_record_bytes(&mut registry, &host_kp, metadata.clone(), timestamp_created);
+        signature.pop();
+        let cp = CPWrapper::new(&issuer_peer_id, &host_id)
+            .add_weight_tetraplets(3)
+            .add_timestamp_tetraplets(4);
+        let result = registry.put_record_cp(
+            metadata,
+            timestamp_created,
+            signature,
+            get_weight(issuer_peer_id, 0),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            InvalidSignatureFormat(host_id, "ed25519".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn reserve_key_capacity_within_and_beyond() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+        for _ in 0..2 {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &KeyPair::generate_ed25519(),
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let available = RECORDS_LIMIT - 2;
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.reserve_key_capacity_cp(
+            key_id.clone(),
+            available as u32,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+
+        let result = registry.reserve_key_capacity_cp(
+            key_id.clone(),
+            available as u32 + 1,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            InsufficientCapacity(key_id, available as u32 + 1, available).to_string()
+        );
+    }
+
+    #[test]
+    fn get_records_via_relay_since_filters() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let current_timestamp = 300u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            100,
+            current_timestamp,
+            0,
+        );
+
+        // recent via the relay, old via the relay, recent via another relay
+        for (issuer_kp, timestamp, relay_id) in [
+            (&issuers[0], 250u64, "relay_a"),
+            (&issuers[1], 150u64, "relay_a"),
+            (&issuers[2], 250u64, "relay_b"),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp,
+                timestamp,
+                "some_value".to_string(),
+                vec![relay_id.to_string()],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(3);
+        let result = registry.get_records_via_relay_since_cp(
+            key_id,
+            "relay_a".to_string(),
+            200,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(
+            result.result[0].metadata.issued_by,
+            issuers[0].get_peer_id().to_base58()
+        );
+    }
+
+    #[test]
+    fn republish_records_diff_reports_identities() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            current_timestamp - 10,
+            current_timestamp - 10,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        // newer version of the stored record and a record of a new author
+        let records = vec![
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                current_timestamp,
+                "new_value".to_string(),
+            ),
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &other_issuer_kp,
+                &host_kp,
+                current_timestamp,
+                "other_value".to_string(),
+            ),
+        ];
+        let weights = vec![
+            get_weight(issuer_kp.get_peer_id().to_base58(), 0),
+            get_weight(other_issuer_kp.get_peer_id().to_base58(), 0),
+        ];
+        let identities: Vec<String> = records
+            .iter()
+            .map(|r| {
+                registry.get_record_identity(
+                    key_id.clone(),
+                    r.metadata.issued_by.clone(),
+                    r.metadata.peer_id.clone(),
+                )
+            })
+            .collect();
+        let (record_identity, new_record_identity) = (identities[0].clone(), identities[1].clone());
+
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 2)
+            .add_timestamp_tetraplets(2);
+        let result =
+            registry.republish_records_diff_cp(records, weights, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.before, vec![record_identity.clone()]);
+
+        let added: Vec<&String> = result
+            .after
+            .iter()
+            .filter(|id| !result.before.contains(id))
+            .collect();
+        let removed: Vec<&String> = result
+            .before
+            .iter()
+            .filter(|id| !result.after.contains(id))
+            .collect();
+        let unchanged: Vec<&String> = result
+            .after
+            .iter()
+            .filter(|id| result.before.contains(id))
+            .collect();
+        assert_eq!(added, vec![&new_record_identity]);
+        assert!(removed.is_empty());
+        assert_eq!(unchanged, vec![&record_identity]);
+    }
+
+    #[test]
+    fn set_key_weight_checks() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let other_kp = KeyPair::generate_ed25519();
+        let other_peer_id = other_kp.get_peer_id().to_base58();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        let set_key_weight = |registry: &mut ServiceInterface, caller: &str, weight: u32| {
+            let cp = CPWrapper::new(caller, "host_id")
+                .add_weight_tetraplets(1)
+                .add_timestamp_tetraplets(2);
+            registry.set_key_weight_cp(
+                key_id.clone(),
+                get_weight(caller.to_string(), weight),
+                current_timestamp,
+                cp.get(),
+            )
+        };
+
+        let result = set_key_weight(&mut registry, &owner_peer_id, MAX_WEIGHT);
+        assert!(result.success, "{}", result.error);
+
+        let result = set_key_weight(&mut registry, &other_peer_id, 1);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotKeyOwner(key_id.clone(), other_peer_id).to_string()
+        );
+
+        let result = set_key_weight(&mut registry, &owner_peer_id, MAX_WEIGHT + 1);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            WeightTooHigh(MAX_WEIGHT + 1, MAX_WEIGHT).to_string()
+        );
+    }
+
+    #[test]
+    fn get_records_json_projects_fields() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_records_json_cp(
+            key_id.clone(),
+            vec!["value".to_string(), "issued_by".to_string()],
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        let records: serde_json::Value = serde_json::from_str(&result.result).unwrap();
+        assert_eq!(
+            records,
+            serde_json::json!([{
+                "value": "some_value",
+                "issued_by": issuer_kp.get_peer_id().to_base58(),
+            }])
+        );
+
+        let result = registry.get_records_json_cp(
+            key_id,
+            vec!["value".to_string(), "signature".to_string()],
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            UnknownField("signature".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn find_and_purge_orphaned_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = "host_id";
+        let current_timestamp = 100u64;
+
+        let mut key_ids = vec![];
+        for label in ["orphaned_key", "some_key"] {
+            let key_id = register_key_checked(
+                &mut registry,
+                &issuer_kp,
+                label.to_string(),
+                current_timestamp,
+                current_timestamp,
+                0,
+            );
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                current_timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+            key_ids.push(key_id);
+        }
+        delete_key_row(&mut registry, &key_ids[0]);
+
+        let cp = CPWrapper::new("some_peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.find_orphaned_records_cp(0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.key_id, key_ids[0]);
+
+        let cp = CPWrapper::new("some_peer_id", host_id);
+        let result = registry.purge_orphaned_records_cp(cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotAdmin("some_peer_id".to_string()).to_string()
+        );
+
+        let cp = CPWrapper::new(host_id, host_id);
+        let result = registry.purge_orphaned_records_cp(cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count, 1);
+
+        let cp = CPWrapper::new("some_peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.find_orphaned_records_cp(0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+        assert_eq!(
+            get_records(&mut registry, key_ids[1].clone(), current_timestamp).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn put_record_metadata_size_limit() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        // key_id, issued_by, peer_id, timestamp_issued and ed25519 issuer_signature
+        let overhead = key_id.len()
+            + issuer_kp.get_peer_id().to_base58().len()
+            + host_kp.get_peer_id().to_base58().len()
+            + 8
+            + 64;
+        let put_value = |registry: &mut ServiceInterface, value_len: usize| {
+            put_record(
+                registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                current_timestamp,
+                current_timestamp,
+                "a".repeat(value_len),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            )
+        };
+
+        let result = put_value(&mut registry, MAX_METADATA_BYTES - overhead);
+        assert!(result.success, "{}", result.error);
+
+        let result = put_value(&mut registry, MAX_METADATA_BYTES - overhead + 1);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            MetadataTooLarge(key_id.clone(), MAX_METADATA_BYTES + 1, MAX_METADATA_BYTES)
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn validate_import_reports_invalid_items() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = "host_id";
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id,
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(0);
+        let result = registry.export_all_cp(current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let blob = result.blob;
+
+        let cp = CPWrapper::new("some_peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.validate_import_cp(blob.clone(), current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.schema_compatible);
+        assert_eq!(result.valid_count, 2);
+        assert_eq!(result.invalid_count, 0);
+
+        let mut tampered: serde_json::Value = serde_json::from_slice(&blob).unwrap();
+        tampered["records"][0]["record"]["metadata"]["value"] = "other_value".into();
+        let tampered = serde_json::to_vec(&tampered).unwrap();
+        let result = registry.validate_import_cp(tampered, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.valid_count, 1);
+        assert_eq!(result.invalid_count, 1);
+
+        let mut incompatible: serde_json::Value = serde_json::from_slice(&blob).unwrap();
+        incompatible["schema_version"] = (EXPORT_SCHEMA_VERSION + 1).into();
+        let incompatible = serde_json::to_vec(&incompatible).unwrap();
+        let result = registry.validate_import_cp(incompatible, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(!result.schema_compatible);
+    }
+
+    #[test]
+    fn get_records_ranked_one_per_author_first() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let trusted_kp = KeyPair::generate_ed25519();
+        let other_kp = KeyPair::generate_ed25519();
+        let host_kps = [KeyPair::generate_ed25519(), KeyPair::generate_ed25519()];
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &trusted_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        // (issuer, host, timestamp_created, weight)
+        let records = [
+            (&trusted_kp, &host_kps[0], 100u64, 5u32),
+            (&trusted_kp, &host_kps[1], 90u64, 5u32),
+            (&other_kp, &host_kps[0], 95u64, 3u32),
+            (&other_kp, &host_kps[1], 80u64, 3u32),
+        ];
+        for (issuer_kp, host_kp, timestamp, weight) in records.iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                host_kp,
+                *timestamp,
+                *timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                *weight,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_ranked_cp(key_id, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let ranked: Vec<(String, u64)> = result
+            .result
+            .iter()
+            .map(|r| (r.metadata.issued_by.clone(), r.timestamp_created))
+            .collect();
+        let trusted = trusted_kp.get_peer_id().to_base58();
+        let other = other_kp.get_peer_id().to_base58();
+        assert_eq!(
+            ranked,
+            vec![
+                (trusted.clone(), 100),
+                (other.clone(), 95),
+                (trusted, 90),
+                (other, 80),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_hot_keys_ordered_by_access() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_ids: Vec<String> = ["key_a", "key_b", "key_c"]
+            .iter()
+            .map(|label| {
+                register_key_checked(
+                    &mut registry,
+                    &owner_kp,
+                    label.to_string(),
+                    current_timestamp,
+                    current_timestamp,
+                    0,
+                )
+            })
+            .collect();
+
+        // key_c is never read, key_b is read last
+        get_records(&mut registry, key_ids[1].clone(), current_timestamp);
+        get_records(&mut registry, key_ids[0].clone(), current_timestamp + 1);
+        get_records(&mut registry, key_ids[1].clone(), current_timestamp + 2);
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_hot_keys_cp(0, current_timestamp + 2, cp.get());
+        assert!(result.success, "{}", result.error);
+        let hot_key_ids: Vec<String> = result.result.into_iter().map(|k| k.id).collect();
+        assert_eq!(hot_key_ids, vec![key_ids[1].clone(), key_ids[0].clone()]);
+
+        let result = registry.get_hot_keys_cp(1, current_timestamp + 2, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].id, key_ids[1]);
+    }
+
+    #[test]
+    fn clear_record_if_checks_timestamp() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new(&issuer_peer_id, "host_id").add_timestamp_tetraplets(3);
+        let result = registry.clear_record_if_cp(
+            key_id.clone(),
+            "some_value".to_string(),
+            current_timestamp - 1,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            RecordChangedSince(key_id.clone(), issuer_peer_id.clone()).to_string()
+        );
+        assert_eq!(
+            get_records(&mut registry, key_id.clone(), current_timestamp).len(),
+            1
+        );
+
+        let result = registry.clear_record_if_cp(
+            key_id.clone(),
+            "some_value".to_string(),
+            current_timestamp,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert!(get_records(&mut registry, key_id, current_timestamp).is_empty());
+    }
+
+    #[test]
+    fn record_merkle_proof_verifies_against_root() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        for issuer_kp in issuers.iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                current_timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_key_merkle_root_cp(key_id.clone(), current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.leaves_count, 3);
+        let root = result.root;
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        for issuer_kp in issuers.iter() {
+            let identity = registry.get_record_identity(
+                key_id.clone(),
+                issuer_kp.get_peer_id().to_base58(),
+                host_kp.get_peer_id().to_base58(),
+            );
+            let result = registry.get_record_merkle_proof_cp(
+                key_id.clone(),
+                identity.clone(),
+                current_timestamp,
+                cp.get(),
+            );
+            assert!(result.success, "{}", result.error);
+            assert_eq!(result.root, root);
+
+            let proof: Vec<MerkleProofStep> = result
+                .proof
+                .into_iter()
+                .map(|step| MerkleProofStep {
+                    hash: step.hash,
+                    is_left: step.is_left,
+                })
+                .collect();
+            assert!(verify_merkle_proof(&root, &identity, &proof));
+            assert!(!verify_merkle_proof(&root, "other_identity", &proof));
+        }
+
+        let result = registry.get_record_merkle_proof_cp(
+            key_id.clone(),
+            "other_identity".to_string(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            RecordIdentityNotFound(key_id, "other_identity".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn read_only_storage_serves_reads() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        make_db_read_only(&mut registry);
+
+        let records = get_records(&mut registry, key_id.clone(), current_timestamp);
+        assert_eq!(records.len(), 1);
+
+        let result = put_record(
+            &mut registry,
+            key_id,
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp + 1,
+            "other_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        assert!(!result.success);
+        assert_eq!(result.error, StorageReadOnly.to_string());
+    }
+
+    #[test]
+    fn get_keys_metadata_aligned() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_ids: Vec<String> = ["key_a", "key_b"]
+            .iter()
+            .map(|label| {
+                register_key_checked(
+                    &mut registry,
+                    &owner_kp,
+                    label.to_string(),
+                    current_timestamp,
+                    current_timestamp,
+                    0,
+                )
+            })
+            .collect();
+
+        let requested = vec![
+            key_ids[1].clone(),
+            "missing_key_id".to_string(),
+            key_ids[0].clone(),
+        ];
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_keys_metadata_cp(requested, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.found, vec![true, false, true]);
+        assert_eq!(result.keys.len(), 3);
+        assert_eq!(result.keys[0].id, key_ids[1]);
+        assert_eq!(result.keys[0].label, "key_b");
+        assert!(result.keys[1].id.is_empty());
+        assert_eq!(result.keys[2].id, key_ids[0]);
+        assert_eq!(result.keys[2].label, "key_a");
+    }
+
+    #[test]
+    fn default_weight_applies_only_when_enabled() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        let metadata = create_record_metadata(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            current_timestamp,
+            "some_value".to_string(),
+            host_id.clone(),
+            vec![],
+            vec![],
+            vec![],
+        );
+        let signature =
+            get_signed_record_bytes(&mut registry, &host_kp, metadata.clone(), current_timestamp);
+        let no_weight = WeightResult {
+            success: false,
+            weight: 0,
+            peer_id: "".to_string(),
+            error: "".to_string(),
+        };
+
+        // no weight tetraplets, the weight doesn't come from trust-graph
+        let cp = CPWrapper::new(&issuer_peer_id, &host_id).add_timestamp_tetraplets(4);
+        let result = registry.put_record_cp(
+            metadata.clone(),
+            current_timestamp,
+            signature.clone(),
+            no_weight.clone(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(!result.success);
+
+        let result = registry.set_default_weight(true, MAX_WEIGHT + 1);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            WeightTooHigh(MAX_WEIGHT + 1, MAX_WEIGHT).to_string()
+        );
+
+        let result = registry.set_default_weight(true, 7);
+        assert!(result.success, "{}", result.error);
+        let result = registry.put_record_cp(
+            metadata,
+            current_timestamp,
+            signature,
+            no_weight,
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+
+        let cp = CPWrapper::new("some_peer_id", &host_id).add_timestamp_tetraplets(2);
+        let result = registry.get_records_json_cp(
+            key_id,
+            vec!["weight".to_string()],
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result, r#"[{"weight":7}]"#);
+    }
+
+    #[test]
+    fn get_republish_queue_returns_due_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = DEFAULT_STALE_AGE * 3;
+
+        // never republished, republished a stale age ago, republished recently
+        let mut key_ids = vec![];
+        for (label, published) in [
+            ("never", None),
+            ("stale", Some(current_timestamp - DEFAULT_STALE_AGE)),
+            ("fresh", Some(current_timestamp - 1)),
+        ] {
+            let key_id = register_key_checked(
+                &mut registry,
+                &owner_kp,
+                label.to_string(),
+                current_timestamp - DEFAULT_STALE_AGE * 2,
+                current_timestamp,
+                0,
+            );
+            if let Some(published) = published {
+                let key = get_key_metadata(&mut registry, key_id.clone(), current_timestamp);
+                republish_key_checked(&mut registry, key, published);
+            }
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &owner_kp,
+                &host_kp,
+                current_timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+            key_ids.push(key_id);
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_republish_queue_cp(0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let mut queued: Vec<String> = result
+            .result
+            .into_iter()
+            .map(|r| r.metadata.key_id)
+            .collect();
+        queued.sort();
+        let mut expected = vec![key_ids[0].clone(), key_ids[1].clone()];
+        expected.sort();
+        assert_eq!(queued, expected);
+
+        let result = registry.get_republish_queue_cp(1, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+    }
+
+    #[test]
+    fn rename_key_moves_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let old_key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "old_label".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        let other_key_id = register_key_checked(
+            &mut registry,
+            &owner_kp,
+            "other_label".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            old_key_id.clone(),
+            &owner_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new(&owner_peer_id, "host_id").add_timestamp_tetraplets(4);
+        let rename = |registry: &mut ServiceInterface, new_label: &str| {
+            let signature = get_signed_key_bytes(
+                registry,
+                &owner_kp,
+                new_label.to_string(),
+                current_timestamp,
+                vec![],
+                "".to_string(),
+            );
+            registry.rename_key_cp(
+                "old_label".to_string(),
+                new_label.to_string(),
+                signature,
+                current_timestamp,
+                current_timestamp,
+                cp.get(),
+            )
+        };
+
+        let result = rename(&mut registry, "other_label");
+        assert!(!result.success);
+        assert_eq!(result.error, KeyAlreadyExists(other_key_id).to_string());
+
+        let owner_cp = CPWrapper::new(&owner_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result =
+            registry.set_key_pinned_cp(old_key_id.clone(), true, current_timestamp, owner_cp.get());
+        assert!(result.success, "{}", result.error);
+        let result = registry.set_key_display_label_cp(
+            old_key_id.clone(),
+            "Old key".to_string(),
+            owner_cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+
+        let result = rename(&mut registry, "new_label");
+        assert!(result.success, "{}", result.error);
+
+        let new_key_id = registry.get_key_id("new_label".to_string(), owner_peer_id.clone());
+        let metadata_cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result =
+            registry.get_key_metadata_cp(new_key_id.clone(), current_timestamp, metadata_cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.key.label, "new_label");
+        assert_eq!(result.display_label, "Old key");
+
+        // the pinned key survives removal without force
+        let result = registry.remove_keys_by_prefix_cp(
+            "new_label".to_string(),
+            false,
+            current_timestamp,
+            owner_cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 0);
+        let records = get_records(&mut registry, new_key_id.clone(), current_timestamp);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.key_id, new_key_id);
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(old_key_id.clone(), current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, KeyNotExists(old_key_id).to_string());
+    }
+
+    #[test]
+    fn get_records_verified_flags_broken_signatures() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let skewed_issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        // the last record is created within the clock skew window
+        for (issuer, timestamp_created) in [
+            (&issuer_kp, current_timestamp),
+            (&other_issuer_kp, current_timestamp),
+            (&skewed_issuer_kp, current_timestamp + 30),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer,
+                &host_kp,
+                timestamp_created,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        // tamper with the stored value of one record
+        let broken_issuer = other_issuer_kp.get_peer_id().to_base58();
+        let (_, updated) = execute_service_db(
+            &mut registry,
+            &f!("UPDATE {RECORDS_TABLE_NAME} SET value = 'other_value' WHERE issued_by = ?1"),
+            &[&broken_issuer],
+        );
+        assert_eq!(updated, 1);
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_verified_cp(key_id, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 3);
+        for (record, verified) in result.result.iter().zip(result.verified) {
+            assert_eq!(verified, record.metadata.issued_by != broken_issuer);
+        }
+    }
+
+    #[test]
+    fn eviction_policy_selects_evicted_record() {
+        fn put_at(
+            registry: &mut ServiceInterface,
+            key_id: &str,
+            issuer_kp: &KeyPair,
+            host_kp: &KeyPair,
+            timestamp_created: u64,
+            current_timestamp: u64,
+            weight: u32,
+        ) {
+            let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+            let peer_id = host_kp.get_peer_id().to_base58();
+            let record_metadata = create_record_metadata(
+                registry,
+                key_id.to_string(),
+                issuer_kp,
+                timestamp_created,
+                "value".to_string(),
+                peer_id.clone(),
+                vec![],
+                vec![],
+                vec![],
+            );
+            let signature = get_signed_record_bytes(
+                registry,
+                host_kp,
+                record_metadata.clone(),
+                timestamp_created,
+            );
+            // records of other peers are subject to the limit
+            let cp = CPWrapper::new(&issuer_peer_id, "service_host")
+                .add_weight_tetraplets(3)
+                .add_timestamp_tetraplets(4);
+            let result = registry.put_record_cp(
+                record_metadata,
+                timestamp_created,
+                signature,
+                get_weight(issuer_peer_id, weight),
+                current_timestamp,
+                cp.get(),
+            );
+            assert!(result.success, "{}", result.error);
+        }
+
+        let policies = [
+            ("LowestWeight", 0usize),
+            ("Oldest", 1usize),
+            ("LeastRecentlyAccessed", 2usize),
+        ];
+        for (policy, expected_evicted) in policies.iter() {
+            clear_env();
+            let mut registry = ServiceInterface::new();
+            let result = registry.set_eviction_policy(policy.to_string());
+            assert!(result.success, "{}", result.error);
+
+            let key_kp = KeyPair::generate_ed25519();
+            let host_kp = KeyPair::generate_ed25519();
+            let key_id =
+                register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+            // lightest, oldest and least recently accessed records
+            let special: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+            put_at(&mut registry, &key_id, &special[0], &host_kp, 50, 50, 1);
+            put_at(&mut registry, &key_id, &special[1], &host_kp, 20, 300, 10);
+            put_at(&mut registry, &key_id, &special[2], &host_kp, 30, 40, 10);
+            for i in 0..(RECORDS_LIMIT - 3) as u64 {
+                let kp = KeyPair::generate_ed25519();
+                put_at(&mut registry, &key_id, &kp, &host_kp, 100 + i, 100 + i, 10);
+            }
+
+            let newcomer = KeyPair::generate_ed25519();
+            put_at(&mut registry, &key_id, &newcomer, &host_kp, 400, 400, 10);
+
+            let records = get_records(&mut registry, key_id.clone(), 400);
+            assert_eq!(records.len(), RECORDS_LIMIT, "policy {}", policy);
+            let issuers: Vec<String> = records
+                .iter()
+                .map(|r| r.metadata.issued_by.clone())
+                .collect();
+            assert!(issuers.contains(&newcomer.get_peer_id().to_base58()));
+            for (i, kp) in special.iter().enumerate() {
+                assert_eq!(
+                    issuers.contains(&kp.get_peer_id().to_base58()),
+                    i != *expected_evicted,
+                    "policy {}, record {}",
+                    policy,
+                    i
+                );
+            }
+        }
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let result = registry.set_eviction_policy("Random".to_string());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            UnknownEvictionPolicy("Random".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn get_weight_histogram_counts_buckets() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        for weight in [1u32, 5, 12, 15, 27].iter() {
+            let issuer_kp = KeyPair::generate_ed25519();
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                20,
+                20,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                *weight,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_weight_histogram_cp(key_id.clone(), 10, 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        let buckets: Vec<(u32, u64)> = result
+            .buckets
+            .iter()
+            .map(|b| (b.lower_bound, b.count))
+            .collect();
+        assert_eq!(buckets, vec![(0, 2), (10, 2), (20, 1)]);
+
+        let result = registry.get_weight_histogram_cp(key_id, 0, 30, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, InvalidBucketSize.to_string());
+    }
+
+    #[test]
+    fn import_all_only_existing_keys_skips_absent_keys() {
+        clear_env();
+        let mut source = ServiceInterface::new();
+        let mut target = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = "host_id";
+        let current_timestamp = 100u64;
+
+        let mut key_ids = vec![];
+        for label in ["present_key", "absent_key"].iter() {
+            let key_id = register_key_checked(
+                &mut source,
+                &owner_kp,
+                label.to_string(),
+                current_timestamp,
+                current_timestamp,
+                0,
+            );
+            put_record_checked(
+                &mut source,
+                key_id.clone(),
+                &owner_kp,
+                &host_kp,
+                current_timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+            key_ids.push(key_id);
+        }
+        register_key_checked(
+            &mut target,
+            &owner_kp,
+            "present_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(0);
+        let result = source.export_all_cp(current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        let blob = result.blob;
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(2);
+        let result = target.import_all_cp(blob.clone(), true, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.imported_keys, 1);
+        assert_eq!(result.imported_records, 1);
+        assert_eq!(result.skipped_records, 1);
+        assert_eq!(result.invalid_count, 0);
+
+        assert_eq!(
+            get_records(&mut target, key_ids[0].clone(), current_timestamp).len(),
+            1
+        );
+        let cp = CPWrapper::new("peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = target.get_records_cp(key_ids[1].clone(), current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, KeyNotExists(key_ids[1].clone()).to_string());
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(2);
+        let result = target.import_all_cp(blob, false, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.imported_keys, 2);
+        assert_eq!(result.imported_records, 2);
+        assert_eq!(result.skipped_records, 0);
+        assert_eq!(
+            get_records(&mut target, key_ids[1].clone(), current_timestamp).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn cas_record_swaps_only_own_expected_value() {
+        fn cas(
+            registry: &mut ServiceInterface,
+            key_id: &str,
+            issuer_kp: &KeyPair,
+            host_kp: &KeyPair,
+            timestamp: u64,
+            value: &str,
+            expected_value: &str,
+        ) -> RegistryResult {
+            let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+            let peer_id = host_kp.get_peer_id().to_base58();
+            let record_metadata = create_record_metadata(
+                registry,
+                key_id.to_string(),
+                issuer_kp,
+                timestamp,
+                value.to_string(),
+                peer_id.clone(),
+                vec![],
+                vec![],
+                vec![],
+            );
+            let signature =
+                get_signed_record_bytes(registry, host_kp, record_metadata.clone(), timestamp);
+            let cp = CPWrapper::new(&issuer_peer_id, &peer_id)
+                .add_weight_tetraplets(3)
+                .add_timestamp_tetraplets(5);
+            registry.cas_record_cp(
+                record_metadata,
+                timestamp,
+                signature,
+                get_weight(issuer_peer_id, 0),
+                expected_value.to_string(),
+                timestamp,
+                cp.get(),
+            )
+        }
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let author_kp = KeyPair::generate_ed25519();
+        let other_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &author_kp, "some_key".to_string(), 10, 10, 0);
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &author_kp,
+            &host_kp,
+            20,
+            20,
+            "v1".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let result = cas(&mut registry, &key_id, &author_kp, &host_kp, 30, "v2", "v1");
+        assert!(result.success, "{}", result.error);
+        let records = get_records(&mut registry, key_id.clone(), 30);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.value, "v2");
+
+        // the value has changed since
+        let result = cas(&mut registry, &key_id, &author_kp, &host_kp, 40, "v3", "v1");
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            RecordChangedSince(key_id.clone(), author_kp.get_peer_id().to_base58()).to_string()
+        );
+
+        // the expected value belongs to another author
+        let result = cas(&mut registry, &key_id, &other_kp, &host_kp, 40, "v3", "v2");
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            RecordNotIssuedByCaller(key_id.clone(), author_kp.get_peer_id().to_base58())
+                .to_string()
+        );
+        let records = get_records(&mut registry, key_id.clone(), 40);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.value, "v2");
+
+        let other_peer_id = other_kp.get_peer_id().to_base58();
+        let cp = CPWrapper::new(&other_peer_id, "host_id").add_timestamp_tetraplets(1);
+        let result = registry.set_key_immutable_cp(key_id.clone(), 40, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotKeyOwner(key_id.clone(), other_peer_id).to_string()
+        );
+
+        let cp = CPWrapper::new(&author_kp.get_peer_id().to_base58(), "host_id")
+            .add_timestamp_tetraplets(1);
+        let result = registry.set_key_immutable_cp(key_id.clone(), 40, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        // the expected value matches but the key is immutable
+        let result = cas(&mut registry, &key_id, &author_kp, &host_kp, 50, "v3", "v2");
+        assert!(!result.success);
+        assert_eq!(result.error, KeyImmutable(key_id.clone()).to_string());
+        let records = get_records(&mut registry, key_id, 50);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.value, "v2");
+    }
+
+    #[test]
+    fn get_author_trust_aggregates_across_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let author_kp = KeyPair::generate_ed25519();
+        let other_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+
+        for (label, author_weight) in [("key_a", 10u32), ("key_b", 30u32)].iter() {
+            let key_id =
+                register_key_checked(&mut registry, &owner_kp, label.to_string(), 10, 10, 0);
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &author_kp,
+                &host_kp,
+                20,
+                20,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                *author_weight,
+            );
+            put_record_checked(
+                &mut registry,
+                key_id,
+                &other_kp,
+                &host_kp,
+                20,
+                20,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                100,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result =
+            registry.get_author_trust_cp(author_kp.get_peer_id().to_base58(), 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.record_count, 2);
+        assert_eq!(result.total_weight, 40);
+        assert_eq!(result.average_weight, 20.0);
+
+        let result = registry.get_author_trust_cp("unknown_peer".to_string(), 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.record_count, 0);
+        assert_eq!(result.average_weight, 0.0);
+    }
+
+    #[test]
+    fn find_key_id_collisions_reports_concatenation_collision() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let colliding_owner = f!("b{owner_peer_id}");
+        register_key_checked(&mut registry, &owner_kp, "other".to_string(), 10, 10, 0);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(0);
+        let result = registry.find_key_id_collisions_cp(20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.collisions.is_empty());
+
+        // ("a", "b" + owner) hashes to the same id as ("ab", owner). Peer ids of one key format have
+        // the same length, so such a key can't be registered, it's stored bypassing the service
+        let key_id = registry.get_key_id("ab".to_string(), owner_peer_id.clone());
+        assert_eq!(
+            registry.get_key_id("a".to_string(), colliding_owner.clone()),
+            key_id
+        );
+        execute_service_db(
+            &mut registry,
+            &f!("INSERT INTO {KEYS_TABLE_NAME} VALUES (?1, 'a', ?2, 10, x'', '', x'00', 0, 0)"),
+            &[&key_id, &colliding_owner],
+        );
+
+        // registering the key replaces the stored one with the same id
+        register_key_checked(&mut registry, &owner_kp, "ab".to_string(), 10, 10, 0);
+        // updates of the same key aren't collisions
+        register_key_checked(&mut registry, &owner_kp, "ab".to_string(), 15, 15, 0);
+
+        let result = registry.find_key_id_collisions_cp(20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.collisions.len(), 1);
+        let collision = &result.collisions[0];
+        assert_eq!(collision.key_id, key_id);
+        let mut pairs: Vec<(String, String)> = collision
+            .keys
+            .iter()
+            .map(|k| (k.label.clone(), k.owner_peer_id.clone()))
+            .collect();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                ("a".to_string(), colliding_owner),
+                ("ab".to_string(), owner_peer_id.clone()),
+            ]
+        );
+
+        // keys created later are not considered
+        let result = registry.find_key_id_collisions_cp(12, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.collisions.is_empty());
+    }
+
+    #[test]
+    fn get_records_page_slices_stable_order() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        // (weight, timestamp_created)
+        let specs = [(5u32, 20u64), (9, 21), (5, 25), (1, 30), (9, 22)];
+        let mut issuers = vec![];
+        for (weight, timestamp_created) in specs.iter() {
+            let issuer_kp = KeyPair::generate_ed25519();
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                *timestamp_created,
+                *timestamp_created,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                *weight,
+            );
+            issuers.push(issuer_kp.get_peer_id().to_base58());
+        }
+        let expected_order: Vec<String> = [4usize, 1, 2, 0, 3]
+            .iter()
+            .map(|i| issuers[*i].clone())
+            .collect();
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let mut paged = vec![];
+        for offset in [0u32, 2, 4].iter() {
+            let result = registry.get_records_page_cp(key_id.clone(), 40, *offset, 2, cp.get());
+            assert!(result.success, "{}", result.error);
+            assert_eq!(result.total, 5);
+            assert_eq!(result.result.len(), result.identities.len());
+            paged.extend(result.result.into_iter().map(|r| r.metadata.issued_by));
+        }
+        assert_eq!(paged, expected_order);
+
+        let result = registry.get_records_page_cp(key_id.clone(), 40, 10, 2, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+        assert_eq!(result.total, 5);
+
+        let result = registry.get_records_page_cp(key_id, 40, 0, 0, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, InvalidPageLimit.to_string());
+    }
+
+    #[test]
+    fn get_records_max_age_returns_fresh_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        let mut issuers = vec![];
+        for timestamp_created in [40u64, 70, 90].iter() {
+            let issuer_kp = KeyPair::generate_ed25519();
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                *timestamp_created,
+                *timestamp_created,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+            issuers.push(issuer_kp.get_peer_id().to_base58());
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(2);
+        let max_age_issuers = |registry: &mut ServiceInterface, max_age_sec: u64| {
+            let result =
+                registry.get_records_max_age_cp(key_id.clone(), max_age_sec, 100, cp.get());
+            assert!(result.success, "{}", result.error);
+            let mut issuers: Vec<String> = result
+                .result
+                .into_iter()
+                .map(|r| r.metadata.issued_by)
+                .collect();
+            issuers.sort();
+            issuers
+        };
+
+        let mut expected = vec![issuers[1].clone(), issuers[2].clone()];
+        expected.sort();
+        // the boundary is inclusive
+        assert_eq!(max_age_issuers(&mut registry, 30), expected);
+        assert_eq!(max_age_issuers(&mut registry, 5), Vec::<String>::new());
+        // doesn't underflow
+        let mut all = issuers.clone();
+        all.sort();
+        assert_eq!(max_age_issuers(&mut registry, 1000), all);
+    }
+
+    #[test]
+    fn get_key_metadata_checks_existence_and_counts_access() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        let key = get_key_metadata(&mut registry, key_id.clone(), 20);
+        assert_eq!(key.id, key_id);
+        assert_eq!(key.label, "some_key");
+        assert_eq!(key.owner_peer_id, key_kp.get_peer_id().to_base58());
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_hot_keys_cp(0, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].id, key_id);
+
+        let result = registry.get_key_metadata_cp("missing_key".to_string(), 20, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            KeyNotExists("missing_key".to_string()).to_string()
+        );
+
+        let cp = CPWrapper::new("peer_id", "host_id");
+        let result = registry.get_key_metadata_cp(key_id, 20, cp.get());
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn get_owner_records_filters_third_party_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let other_kp = KeyPair::generate_ed25519();
+        let host_kps = [KeyPair::generate_ed25519(), KeyPair::generate_ed25519()];
+        let key_id =
+            register_key_checked(&mut registry, &owner_kp, "some_key".to_string(), 10, 10, 0);
+
+        for host_kp in host_kps.iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &owner_kp,
+                host_kp,
+                20,
+                20,
+                "owner_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &other_kp,
+            &host_kps[0],
+            20,
+            20,
+            "other_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_owner_records_cp(key_id, 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 2);
+        assert!(result.result.iter().all(|r| r.metadata.issued_by
+            == owner_kp.get_peer_id().to_base58()
+            && r.metadata.value == "owner_value"));
+    }
+
+    #[test]
+    fn clear_stale_keys_removes_inactive_keys_with_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = "host_id";
+
+        let stale_key_id =
+            register_key_checked(&mut registry, &owner_kp, "stale".to_string(), 10, 10, 0);
+        let read_key_id =
+            register_key_checked(&mut registry, &owner_kp, "read".to_string(), 10, 10, 0);
+        let fresh_key_id =
+            register_key_checked(&mut registry, &owner_kp, "fresh".to_string(), 95, 95, 0);
+        for key_id in [&stale_key_id, &read_key_id].iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.to_string(),
+                &owner_kp,
+                &host_kp,
+                10,
+                10,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+        get_records(&mut registry, read_key_id.clone(), 90);
+
+        let cp = CPWrapper::new("some_peer_id", host_id).add_timestamp_tetraplets(0);
+        let result = registry.clear_stale_keys_cp(100, 20, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotAdmin("some_peer_id".to_string()).to_string()
+        );
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(0);
+        let result = registry.clear_stale_keys_cp(100, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 1);
+        assert_eq!(result.records_count, 1);
+
+        let cp = CPWrapper::new("peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(stale_key_id.clone(), 100, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, KeyNotExists(stale_key_id).to_string());
+        assert_eq!(get_records(&mut registry, read_key_id, 100).len(), 1);
+        get_key_metadata(&mut registry, fresh_key_id, 100);
+    }
+
+    #[test]
+    fn get_recent_records_both_directions() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        for timestamp_created in [30u64, 10, 40, 20].iter() {
+            let issuer_kp = KeyPair::generate_ed25519();
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                *timestamp_created,
+                *timestamp_created,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(3);
+        let mut recent = |n: u32, ascending: bool| -> Vec<u64> {
+            let result = registry.get_recent_records_cp(key_id.clone(), n, ascending, 50, cp.get());
+            assert!(result.success, "{}", result.error);
+            result.result.iter().map(|r| r.timestamp_created).collect()
+        };
+
+        assert_eq!(recent(0, false), vec![40, 30, 20, 10]);
+        assert_eq!(recent(0, true), vec![10, 20, 30, 40]);
+        assert_eq!(recent(2, false), vec![40, 30]);
+        assert_eq!(recent(2, true), vec![30, 40]);
+    }
+
+    #[test]
+    fn get_records_self_heal_deletes_orphaned_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let mut key_ids = vec![];
+        for label in ["healed_key", "kept_key"].iter() {
+            let key_id = register_key_checked(
+                &mut registry,
+                &issuer_kp,
+                label.to_string(),
+                current_timestamp,
+                current_timestamp,
+                0,
+            );
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                current_timestamp,
+                current_timestamp,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+            delete_key_row(&mut registry, &key_id);
+            key_ids.push(key_id);
+        }
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        // orphans are kept without self_heal
+        let result = registry.get_records_cp(key_ids[1].clone(), current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, KeyNotExists(key_ids[1].clone()).to_string());
+
+        registry.set_self_heal(true);
+        let result = registry.get_records_cp(key_ids[0].clone(), current_timestamp, cp.get());
+        assert!(!result.success);
+        assert!(result.result.is_empty());
+        assert_eq!(result.error, KeyNotExists(key_ids[0].clone()).to_string());
+
+        let result = registry.find_orphaned_records_cp(0, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.key_id, key_ids[1]);
+    }
+
+    #[test]
+    fn merge_is_deterministic_on_ties() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let other_kp = KeyPair::generate_ed25519();
+
+        // same (peer_id, issued_by), timestamp_created and weight
+        let mut records: Vec<Record> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|value| {
+                create_record(
+                    &mut registry,
+                    "key_id".to_string(),
+                    &issuer_kp,
+                    &host_kp,
+                    10,
+                    value.to_string(),
+                )
+            })
+            .collect();
+        records.push(create_record(
+            &mut registry,
+            "key_id".to_string(),
+            &other_kp,
+            &host_kp,
+            10,
+            "e".to_string(),
+        ));
+        let expected_survivor = records[..4]
+            .iter()
+            .max_by(|l, r| l.signature.cmp(&r.signature))
+            .unwrap()
+            .metadata
+            .value
+            .clone();
+
+        let mut outputs = vec![];
+        for shift in 0..records.len() {
+            for reverse in [false, true].iter() {
+                let mut shuffled = records.clone();
+                shuffled.rotate_left(shift);
+                if *reverse {
+                    shuffled.reverse();
+                }
+                let result = registry.merge(vec![shuffled]);
+                assert!(result.success, "{}", result.error);
+                let output: Vec<(String, String, Vec<u8>)> = result
+                    .result
+                    .into_iter()
+                    .map(|r| (r.metadata.issued_by, r.metadata.value, r.signature))
+                    .collect();
+                outputs.push(output);
+            }
+        }
+
+        assert_eq!(outputs[0].len(), 2);
+        assert!(outputs.iter().all(|o| *o == outputs[0]));
+        assert!(outputs[0].iter().any(|(issued_by, value, _)| *issued_by
+            == issuer_kp.get_peer_id().to_base58()
+            && *value == expected_survivor));
+    }
+
+    #[test]
+    fn put_records_stores_valid_and_reports_rejected() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let mut records: Vec<Record> = issuers
+            .iter()
+            .map(|kp| {
+                create_record(
+                    &mut registry,
+                    key_id.clone(),
+                    kp,
+                    &host_kp,
+                    20,
+                    "value".to_string(),
+                )
+            })
+            .collect();
+        records[1].metadata.value = "tampered".to_string();
+        let weights: Vec<WeightResult> = issuers
+            .iter()
+            .map(|kp| get_weight(kp.get_peer_id().to_base58(), 5))
+            .collect();
+
+        let cp = CPWrapper::new("peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, records.len())
+            .add_timestamp_tetraplets(2);
+        let result = registry.put_records_cp(records.clone(), weights[..2].to_vec(), 30, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, WeightsCountMismatch(3, 2).to_string());
+
+        let result = registry.put_records_cp(records, weights, 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.stored_count, 2);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].index, 1);
+
+        let mut stored: Vec<String> = get_records(&mut registry, key_id, 30)
+            .into_iter()
+            .map(|r| r.metadata.issued_by)
+            .collect();
+        stored.sort();
+        let mut expected = vec![
+            issuers[0].get_peer_id().to_base58(),
+            issuers[2].get_peer_id().to_base58(),
+        ];
+        expected.sort();
+        assert_eq!(stored, expected);
+    }
+
+    #[test]
+    fn count_keys_follows_registrations_and_removal() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+
+        let result = registry.count_keys();
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count, 0);
+
+        for label in ["app/a", "app/b", "other"].iter() {
+            register_key_checked(&mut registry, &owner_kp, label.to_string(), 10, 10, 0);
+        }
+        assert_eq!(registry.count_keys().count, 3);
+
+        let cp = CPWrapper::new(&owner_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.remove_keys_by_prefix_cp("app/b".to_string(), false, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 1);
+        assert_eq!(registry.count_keys().count, 2);
+    }
+
+    #[test]
+    fn oversized_values_and_ids_are_rejected() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &issuer_kp, "some_key".to_string(), 10, 10, 0);
+
+        let put = |registry: &mut ServiceInterface,
+                   value: String,
+                   relay_id: Vec<String>,
+                   service_id: Vec<String>| {
+            put_record(
+                registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                20,
+                20,
+                value,
+                relay_id,
+                service_id,
+                vec![],
+                0,
+            )
+        };
+
+        let result = put(
+            &mut registry,
+            "v".repeat(MAX_VALUE_SIZE_BYTES + 1),
+            vec![],
+            vec![],
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            ValueTooLarge(MAX_VALUE_SIZE_BYTES + 1, MAX_VALUE_SIZE_BYTES).to_string()
+        );
+
+        let ids = |n: usize| (0..n).map(|i| i.to_string()).collect::<Vec<String>>();
+        let result = put(
+            &mut registry,
+            "value".to_string(),
+            ids(MAX_RELAY_IDS + 1),
+            vec![],
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            TooManyIds("relay_id".to_string(), MAX_RELAY_IDS + 1, MAX_RELAY_IDS).to_string()
+        );
+
+        let result = put(
+            &mut registry,
+            "value".to_string(),
+            vec![],
+            ids(MAX_SERVICE_IDS + 1),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            TooManyIds(
+                "service_id".to_string(),
+                MAX_SERVICE_IDS + 1,
+                MAX_SERVICE_IDS
+            )
+            .to_string()
+        );
+
+        let result = put(
+            &mut registry,
+            "value".to_string(),
+            ids(MAX_RELAY_IDS),
+            ids(MAX_SERVICE_IDS),
+        );
+        assert!(result.success, "{}", result.error);
+        let records = get_records(&mut registry, key_id.clone(), 20);
+        assert_eq!(records[0].metadata.relay_id, ids(MAX_RELAY_IDS));
+        assert_eq!(records[0].metadata.service_id, ids(MAX_SERVICE_IDS));
+
+        // republished records are checked too
+        let record = create_record(
+            &mut registry,
+            key_id,
+            &issuer_kp,
+            &host_kp,
+            30,
+            "v".repeat(MAX_VALUE_SIZE_BYTES + 1),
+        );
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 1)
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(
+            vec![record],
+            vec![get_weight(issuer_kp.get_peer_id().to_base58(), 0)],
+            30,
+            cp.get(),
+        );
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            ValueTooLarge(MAX_VALUE_SIZE_BYTES + 1, MAX_VALUE_SIZE_BYTES).to_string()
+        );
+    }
+
+    #[test]
+    fn merge_by_weight_prefers_heavier_older_record() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+
+        let older = create_record(
+            &mut registry,
+            "key_id".to_string(),
+            &issuer_kp,
+            &host_kp,
+            10,
+            "trusted".to_string(),
+        );
+        let newer = create_record(
+            &mut registry,
+            "key_id".to_string(),
+            &issuer_kp,
+            &host_kp,
+            20,
+            "recent".to_string(),
+        );
+
+        // last write wins by default
+        let result = registry.merge(vec![vec![older.clone(), newer.clone()]]);
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.value, "recent");
+
+        let result = registry.merge_by_weight(vec![older.clone(), newer.clone()], vec![10, 1]);
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.value, "trusted");
+
+        // timestamp breaks weight ties
+        let result = registry.merge_by_weight(vec![older.clone(), newer.clone()], vec![5, 5]);
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result[0].metadata.value, "recent");
+
+        let result = registry.merge_by_weight(vec![older, newer], vec![5]);
+        assert!(!result.success);
+        assert_eq!(result.error, WeightsCountMismatch(2, 1).to_string());
+    }
+
+    #[test]
+    fn export_key_signed_is_verifiable_with_host_key() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        // the module env of Config.toml carries the key
+        let signing_kp = KeyPair::from_secret_key(
+            bs58::decode(TEST_HOST_SIGNING_KEY).into_vec().unwrap(),
+            KeyFormat::Ed25519,
+        )
+        .unwrap();
+        let host_id = "host_id";
+        let key_id =
+            register_key_checked(&mut registry, &owner_kp, "some_key".to_string(), 10, 10, 0);
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &owner_kp,
+            &host_kp,
+            20,
+            20,
+            "value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new("peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.export_key_signed_cp(key_id.clone(), 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.signer_peer_id, signing_kp.get_peer_id().to_base58());
+        assert_eq!(result.timestamp_sec, 30);
+
+        let mut hasher = Sha256::new();
+        hasher.update(30u64.to_be_bytes());
+        hasher.update(&result.blob);
+        let digest = hasher.finalize().to_vec();
+        assert_eq!(digest, result.digest);
+        let signature = Signature::from_bytes(KeyFormat::Ed25519, result.signature.clone());
+        assert!(signing_kp.public().verify(&digest, &signature).is_ok());
+        assert!(owner_kp.public().verify(&digest, &signature).is_err());
+
+        let blob: serde_json::Value = serde_json::from_slice(&result.blob).unwrap();
+        assert_eq!(blob["keys"].as_array().unwrap().len(), 1);
+        assert_eq!(blob["keys"][0]["key"]["id"], key_id.as_str());
+        assert_eq!(blob["records"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn republish_records_evicts_over_limit() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+        let key_id = register_key_checked(
+            &mut registry,
+            &key_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        let issuers: Vec<KeyPair> = (0..RECORDS_LIMIT + 2)
+            .map(|_| KeyPair::generate_ed25519())
+            .collect();
+        let records: Vec<Record> = issuers
+            .iter()
+            .map(|kp| {
+                create_record(
+                    &mut registry,
+                    key_id.clone(),
+                    kp,
+                    &host_kp,
+                    current_timestamp,
+                    "value".to_string(),
+                )
+            })
+            .collect();
+        // the first two records are the lightest ones
+        let weights: Vec<WeightResult> = issuers
+            .iter()
+            .enumerate()
+            .map(|(i, kp)| get_weight(kp.get_peer_id().to_base58(), if i < 2 { 1 } else { 10 }))
+            .collect();
+
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, issuers.len())
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(records, weights, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.evicted, 2);
+
+        let records = get_records(&mut registry, key_id, current_timestamp);
+        assert_eq!(records.len(), RECORDS_LIMIT);
+        for kp in issuers.iter().take(2) {
+            let issuer = kp.get_peer_id().to_base58();
+            assert!(records.iter().all(|r| r.metadata.issued_by != issuer));
+        }
+
+        // host records are never evicted, exceeding the limit on their own is an error
+        let host_key_id = register_key_checked(
+            &mut registry,
+            &key_kp,
+            "host_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        let records: Vec<Record> = issuers
+            .iter()
+            .take(RECORDS_LIMIT + 1)
+            .map(|kp| {
+                create_record(
+                    &mut registry,
+                    host_key_id.clone(),
+                    kp,
+                    &host_kp,
+                    current_timestamp,
+                    "value".to_string(),
+                )
+            })
+            .collect();
+        let weights: Vec<WeightResult> = issuers
+            .iter()
+            .take(RECORDS_LIMIT + 1)
+            .map(|kp| get_weight(kp.get_peer_id().to_base58(), 10))
+            .collect();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let cp = CPWrapper::new("some_peer_id", &host_id)
+            .add_batch_weight_tetraplets(1, RECORDS_LIMIT + 1)
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(records, weights, current_timestamp, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            HostRecordsLimitExceeded(host_key_id.clone(), RECORDS_LIMIT as u64 + 1).to_string()
+        );
+        assert!(get_records(&mut registry, host_key_id, current_timestamp).is_empty());
+    }
+
+    #[test]
+    fn domain_separated_signature_bytes() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let peer_id = host_kp.get_peer_id().to_base58();
+        let current_timestamp = 100u64;
+        let key_id = register_key_checked(
+            &mut registry,
+            &key_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        let put_legacy = |registry: &mut ServiceInterface, issuer_kp: &KeyPair| {
+            let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+            let metadata = create_record_metadata(
+                registry,
+                key_id.clone(),
+                issuer_kp,
+                current_timestamp,
+                "value".to_string(),
+                peer_id.clone(),
+                vec![],
+                vec![],
+                vec![],
+            );
+            // the legacy record layout hashes an empty buffer
+            let legacy_bytes = Sha256::digest(b"").to_vec();
+            let signature = host_kp.sign(&legacy_bytes).unwrap().to_vec().to_vec();
+            let cp = CPWrapper::new(&issuer_peer_id, &peer_id)
+                .add_weight_tetraplets(3)
+                .add_timestamp_tetraplets(4);
+            registry.put_record_cp(
+                metadata,
+                current_timestamp,
+                signature,
+                get_weight(issuer_peer_id, 0),
+                current_timestamp,
+                cp.get(),
+            )
+        };
+
+        // records differing only in timestamp_created had the same legacy bytes
+        let metadata = create_record_metadata(
+            &mut registry,
+            key_id.clone(),
+            &key_kp,
+            current_timestamp,
+            "value".to_string(),
+            peer_id.clone(),
+            vec![],
+            vec![],
+            vec![],
+        );
+        let bytes = registry.get_record_bytes(metadata.clone(), current_timestamp);
+        let other_bytes = registry.get_record_bytes(metadata, current_timestamp + 1);
+        assert_ne!(bytes, other_bytes);
+        assert_ne!(bytes, Sha256::digest(b"").to_vec());
+
+        // values shifted between fields no longer hash the same
+        let ab_bytes = registry.get_key_bytes(
+            "ab".to_string(),
+            vec![],
+            current_timestamp,
+            vec![],
+            "c".to_string(),
+        );
+        let bc_bytes = registry.get_key_bytes(
+            "a".to_string(),
+            vec![],
+            current_timestamp,
+            vec![],
+            "bc".to_string(),
+        );
+        assert_ne!(ab_bytes, bc_bytes);
+
+        // the legacy record layout committed to no field, so it's rejected even with legacy signatures
+        for legacy_signatures in [false, true] {
+            registry.set_legacy_signatures(legacy_signatures);
+            let result = put_legacy(&mut registry, &KeyPair::generate_ed25519());
+            assert!(!result.success);
+            assert!(
+                result.error.starts_with("Invalid record signature"),
+                "{}",
+                result.error
+            );
+        }
+    }
+
+    #[test]
+    fn get_records_excluding_author() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers = vec![
+            KeyPair::generate_ed25519(),
+            KeyPair::generate_ed25519(),
+            KeyPair::generate_ed25519(),
+        ];
+        let timestamp_created = 100u64;
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            timestamp_created,
+            current_timestamp,
+            0,
+        );
+
+        for issuer_kp in issuers.iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let blocked = issuers[1].get_peer_id().to_base58();
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_records_excluding_author_cp(
+            key_id,
+            blocked.clone(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        let mut authors: Vec<String> = result
+            .result
+            .into_iter()
+            .map(|r| r.metadata.issued_by)
+            .collect();
+        authors.sort();
+        let mut expected = vec![
+            issuers[0].get_peer_id().to_base58(),
+            issuers[2].get_peer_id().to_base58(),
+        ];
+        expected.sort();
+        assert_eq!(authors, expected);
+    }
+
+    #[test]
+    fn set_key_pinned_keeps_key_from_gc() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let host_id = "host_id";
+
+        let pinned_key_id =
+            register_key_checked(&mut registry, &owner_kp, "pinned".to_string(), 10, 10, 0);
+        let unpinned_key_id =
+            register_key_checked(&mut registry, &owner_kp, "unpinned".to_string(), 10, 10, 0);
+
+        let cp = CPWrapper::new("other_peer_id", host_id).add_timestamp_tetraplets(2);
+        let result = registry.set_key_pinned_cp(pinned_key_id.clone(), true, 10, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotKeyOwner(pinned_key_id.clone(), "other_peer_id".to_string()).to_string()
+        );
+
+        let cp = CPWrapper::new(&owner_peer_id, host_id).add_timestamp_tetraplets(2);
+        for (key_id, pinned) in [
+            (&pinned_key_id, true),
+            (&unpinned_key_id, true),
+            (&unpinned_key_id, false),
+        ]
+        .iter()
+        {
+            let result = registry.set_key_pinned_cp(key_id.to_string(), *pinned, 10, cp.get());
+            assert!(result.success, "{}", result.error);
+        }
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(0);
+        let result = registry.clear_stale_keys_cp(100, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 1);
+
+        get_key_metadata(&mut registry, pinned_key_id, 100);
+        let cp = CPWrapper::new("peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.get_key_metadata_cp(unpinned_key_id.clone(), 100, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, KeyNotExists(unpinned_key_id).to_string());
+    }
+
+    #[test]
+    fn get_age_percentiles_nearest_rank() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        // ages 10, 20, ..., 100 at timestamp 200
+        for i in 0..10u64 {
+            let issuer_kp = KeyPair::generate_ed25519();
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                100 + i * 10,
+                100 + i * 10,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_age_percentiles_cp(key_id, 200, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.record_count, 10);
+        assert_eq!(result.p50, 50);
+        assert_eq!(result.p90, 90);
+        assert_eq!(result.p99, 100);
+    }
+
+    #[test]
+    fn get_records_filtered_by_service_id_and_issuer() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            10,
+            10,
+            0,
+        );
+
+        let service_ids = ["first", "second", "first"];
+        for (issuer_kp, service_id) in issuers.iter().zip(service_ids.iter()) {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                10,
+                10,
+                "value".to_string(),
+                vec![],
+                vec![service_id.to_string()],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let mut filtered = |service_id: Vec<String>, issued_by: Vec<String>| -> Vec<String> {
+            let result = registry.get_records_filtered_cp(
+                key_id.clone(),
+                10,
+                service_id,
+                issued_by,
+                cp.get(),
+            );
+            assert!(result.success, "{}", result.error);
+            let mut issuers: Vec<String> = result
+                .result
+                .into_iter()
+                .map(|r| r.metadata.issued_by)
+                .collect();
+            issuers.sort();
+            issuers
+        };
+        let peer_id = |i: usize| issuers[i].get_peer_id().to_base58();
+        let sorted = |mut ids: Vec<String>| {
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(filtered(vec![], vec![]).len(), 3);
+        assert_eq!(
+            filtered(vec!["first".to_string()], vec![]),
+            sorted(vec![peer_id(0), peer_id(2)])
+        );
+        assert_eq!(
+            filtered(vec![], vec![peer_id(1), peer_id(2)]),
+            sorted(vec![peer_id(1), peer_id(2)])
+        );
+        assert_eq!(
+            filtered(vec!["first".to_string()], vec![peer_id(1), peer_id(2)]),
+            vec![peer_id(2)]
+        );
+        assert!(filtered(vec!["missing".to_string()], vec![]).is_empty());
+    }
+
+    #[test]
+    fn republish_records_counts_only_changed() {
+        fn republish(registry: &mut ServiceInterface, records: Vec<Record>) -> u64 {
+            let weights: Vec<WeightResult> = records
+                .iter()
+                .map(|r| get_weight(r.metadata.issued_by.clone(), 0))
+                .collect();
+            let cp = CPWrapper::new("some_peer_id", "host_id")
+                .add_batch_weight_tetraplets(1, records.len())
+                .add_timestamp_tetraplets(2);
+            let result = registry.republish_records_cp(records, weights, 110, cp.get());
+            assert!(result.success, "{}", result.error);
+            result.updated
+        }
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuers[0],
+            "some_key".to_string(),
+            10,
+            10,
+            0,
+        );
+
+        let record = |registry: &mut ServiceInterface, i: usize, timestamp_created: u64| {
+            create_record(
+                registry,
+                key_id.clone(),
+                &issuers[i],
+                &host_kp,
+                timestamp_created,
+                f!("value_{timestamp_created}"),
+            )
+        };
+        let updated_record = record(&mut registry, 0, 100);
+        let unchanged_record = record(&mut registry, 1, 100);
+        let newer_record = record(&mut registry, 0, 110);
+        let new_record = record(&mut registry, 2, 100);
+
+        assert_eq!(
+            republish(
+                &mut registry,
+                vec![updated_record.clone(), unchanged_record.clone()]
+            ),
+            2
+        );
+        // only the replaced version and the new record change the stored state
+        assert_eq!(
+            republish(
+                &mut registry,
+                vec![newer_record, unchanged_record, new_record, updated_record]
+            ),
+            2
+        );
+        assert_eq!(get_records(&mut registry, key_id, 110).len(), 3);
+    }
+
+    #[test]
+    fn set_key_display_label_by_owner() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let key_id =
+            register_key_checked(&mut registry, &owner_kp, "some_key".to_string(), 10, 10, 0);
+
+        let cp = CPWrapper::new("other_peer_id", "host_id");
+        let result =
+            registry.set_key_display_label_cp(key_id.clone(), "Spam".to_string(), cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            NotKeyOwner(key_id.clone(), "other_peer_id".to_string()).to_string()
+        );
+
+        let cp = CPWrapper::new(&owner_peer_id, "host_id");
+        let too_long = "a".repeat(MAX_DISPLAY_LABEL_LEN + 1);
+        let result = registry.set_key_display_label_cp(key_id.clone(), too_long, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            DisplayLabelTooLong(MAX_DISPLAY_LABEL_LEN + 1, MAX_DISPLAY_LABEL_LEN).to_string()
+        );
+
+        let result =
+            registry.set_key_display_label_cp(key_id.clone(), "Chat rooms".to_string(), cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_key_metadata_cp(key_id.clone(), 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.display_label, "Chat rooms");
+        // the display label doesn't affect addressing
+        assert_eq!(result.key.id, key_id);
+        assert_eq!(result.key.label, "some_key");
+    }
+
+    #[test]
+    fn get_host_records_paginated_across_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let other_host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let key_kp = KeyPair::generate_ed25519();
+
+        for label in ["first", "second"].iter() {
+            let key_id = register_key_checked(&mut registry, &key_kp, label.to_string(), 10, 10, 0);
+            for hoster_kp in [&host_kp, &host_kp, &other_host_kp].iter() {
+                put_record_checked(
+                    &mut registry,
+                    key_id.clone(),
+                    &KeyPair::generate_ed25519(),
+                    hoster_kp,
+                    10,
+                    10,
+                    "value".to_string(),
+                    vec![],
+                    vec![],
+                    vec![],
+                    0,
+                );
+            }
+        }
+
+        let cp = CPWrapper::new("peer_id", &host_id).add_timestamp_tetraplets(0);
+        let result = registry.get_host_records_cp(20, 0, 0, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.total, 4);
+        assert_eq!(result.result.len(), 4);
+        assert!(result.result.iter().all(|r| r.metadata.peer_id == host_id));
+
+        let result = registry.get_host_records_cp(20, 3, 2, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.total, 4);
+        assert_eq!(result.result.len(), 1);
+
+        // listing host records doesn't count as a key access
+        let cp = CPWrapper::new("peer_id", &host_id).add_timestamp_tetraplets(1);
+        let result = registry.get_hot_keys_cp(0, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+    }
+
+    #[test]
+    fn put_record_timestamp_bounds() {
+        fn put_at(
+            registry: &mut ServiceInterface,
+            key_id: &str,
+            timestamp_created: u64,
+            current_timestamp: u64,
+        ) -> RegistryResult {
+            let issuer_kp = KeyPair::generate_ed25519();
+            let host_kp = KeyPair::generate_ed25519();
+            let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+            let peer_id = host_kp.get_peer_id().to_base58();
+            let metadata = create_record_metadata(
+                registry,
+                key_id.to_string(),
+                &issuer_kp,
+                timestamp_created.min(current_timestamp),
+                "value".to_string(),
+                peer_id.clone(),
+                vec![],
+                vec![],
+                vec![],
+            );
+            let signature =
+                get_signed_record_bytes(registry, &host_kp, metadata.clone(), timestamp_created);
+            let cp = CPWrapper::new(&issuer_peer_id, &peer_id)
+                .add_weight_tetraplets(3)
+                .add_timestamp_tetraplets(4);
+            registry.put_record_cp(
+                metadata,
+                timestamp_created,
+                signature,
+                get_weight(issuer_peer_id, 0),
+                current_timestamp,
+                cp.get(),
+            )
+        }
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+        let current_timestamp = 1000u64;
+        let latest = current_timestamp + MAX_CLOCK_SKEW_SEC;
+
+        let result = put_at(&mut registry, &key_id, latest, current_timestamp);
+        assert!(result.success, "{}", result.error);
+        let result = put_at(&mut registry, &key_id, latest + 1, current_timestamp);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            InvalidTimestampFuture(latest + 1, latest).to_string()
+        );
+
+        // no age limit by default
+        let result = put_at(&mut registry, &key_id, 10, current_timestamp);
+        assert!(result.success, "{}", result.error);
+
+        registry.set_max_record_age(100);
+        let cutoff = current_timestamp - 100;
+        let result = put_at(&mut registry, &key_id, cutoff, current_timestamp);
+        assert!(result.success, "{}", result.error);
+        let result = put_at(&mut registry, &key_id, cutoff - 1, current_timestamp);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            InvalidTimestampTooOld(cutoff - 1, cutoff).to_string()
+        );
+    }
+
+    #[test]
+    fn store_records_best_effort_keeps_valid_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let first_key_id =
+            register_key_checked(&mut registry, &key_kp, "first".to_string(), 10, 10, 0);
+        let second_key_id =
+            register_key_checked(&mut registry, &key_kp, "second".to_string(), 10, 10, 0);
+
+        let issuers: Vec<KeyPair> = (0..4).map(|_| KeyPair::generate_ed25519()).collect();
+        let key_ids = [
+            first_key_id.clone(),
+            second_key_id.clone(),
+            first_key_id.clone(),
+            "missing_key_id".to_string(),
+        ];
+        let mut records: Vec<Record> = issuers
+            .iter()
+            .zip(key_ids.iter())
+            .map(|(kp, key_id)| {
+                create_record(
+                    &mut registry,
+                    key_id.clone(),
+                    kp,
+                    &host_kp,
+                    20,
+                    "value".to_string(),
+                )
+            })
+            .collect();
+        records[2].metadata.value = "tampered".to_string();
+        let weights: Vec<WeightResult> = issuers
+            .iter()
+            .map(|kp| get_weight(kp.get_peer_id().to_base58(), 5))
+            .collect();
+
+        let cp = CPWrapper::new("peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, records.len())
+            .add_timestamp_tetraplets(2);
+        let result = registry.store_records_best_effort_cp(records, weights, 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.stored_count, 2);
+        let indices: Vec<u32> = result.errors.iter().map(|e| e.index).collect();
+        assert_eq!(indices, vec![2, 3]);
+        assert_eq!(
+            result.errors[1].error,
+            KeyNotExists("missing_key_id".to_string()).to_string()
+        );
+
+        assert_eq!(get_records(&mut registry, first_key_id, 30).len(), 1);
+        assert_eq!(get_records(&mut registry, second_key_id, 30).len(), 1);
+    }
+
+    #[test]
+    fn put_record_cas_detects_conflicts() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        let cp = CPWrapper::new(&issuer_peer_id, "host_id")
+            .add_weight_tetraplets(1)
+            .add_timestamp_tetraplets(3);
+        let put_cas = |registry: &mut ServiceInterface, timestamp_created: u64, expected: u64| {
+            let record = create_record(
+                registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                timestamp_created,
+                f!("value_{timestamp_created}"),
+            );
+            registry.put_record_cas_cp(
+                record,
+                get_weight(issuer_peer_id.clone(), 0),
+                expected,
+                100,
+                cp.get(),
+            )
+        };
+
+        let result = put_cas(&mut registry, 20, 0);
+        assert!(result.success, "{}", result.error);
+
+        // a concurrent update expecting no record loses
+        let result = put_cas(&mut registry, 30, 0);
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            CasConflict(key_id.clone(), issuer_peer_id.clone(), 0, 20).to_string()
+        );
+
+        let result = put_cas(&mut registry, 30, 20);
+        assert!(result.success, "{}", result.error);
+
+        let records = get_records(&mut registry, key_id, 100);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.value, "value_30");
+    }
+
+    #[test]
+    fn compare_digest_detects_replaced_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+        let put_at = |registry: &mut ServiceInterface, timestamp_created: u64| {
+            put_record_checked(
+                registry,
+                key_id.clone(),
+                &key_kp,
+                &host_kp,
+                timestamp_created,
+                timestamp_created,
+                f!("value_{timestamp_created}"),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        };
+        put_at(&mut registry, 20);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.compare_digest_cp(key_id.clone(), "".to_string(), 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(!result.matches);
+        let digest = result.local_digest;
+
+        let result = registry.compare_digest_cp(key_id.clone(), digest.clone(), 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.matches);
+
+        // the same identity with a newer version
+        put_at(&mut registry, 25);
+        let result = registry.compare_digest_cp(key_id, digest.clone(), 30, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(!result.matches);
+        assert_ne!(result.local_digest, digest);
+    }
+
+    #[test]
+    fn error_codes_are_populated() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let key_id =
+            register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 10, 10, 0);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(key_id.clone(), 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.error_code, 0);
+
+        // codes are stable: KeyNotExists is 2, InvalidTimestampTetraplet is 8
+        let result = registry.get_records_cp("missing".to_string(), 20, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error_code, 2);
+        let result = registry.get_key_metadata_cp("missing".to_string(), 20, cp.get());
+        assert_eq!(result.error_code, 2);
+
+        let cp = CPWrapper::new("peer_id", "host_id");
+        let result = registry.get_records_cp(key_id, 20, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error_code, 8);
+    }
+
+    #[test]
+    fn long_label_key_id_is_fixed_length_hash() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = key_kp.get_peer_id().to_base58();
+        let label = "a".repeat(10_000);
+
+        let key_id = register_key_checked(&mut registry, &key_kp, label.clone(), 10, 10, 0);
+        assert_eq!(
+            key_id,
+            registry.get_key_id(label.clone(), owner_peer_id.clone())
+        );
+
+        // clients reproduce the id as base58 of sha256 over the label and the owner
+        let expected =
+            bs58::encode(Sha256::digest(f!("{label}{owner_peer_id}").as_bytes())).into_string();
+        assert_eq!(key_id, expected);
+        assert_eq!(bs58::decode(&key_id).into_vec().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn check_keys_existence_aligned_with_input() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let first_key_id =
+            register_key_checked(&mut registry, &key_kp, "first".to_string(), 10, 10, 0);
+        let second_key_id =
+            register_key_checked(&mut registry, &key_kp, "second".to_string(), 10, 10, 0);
+
+        let result = registry.check_keys_existence(vec![
+            "missing".to_string(),
+            second_key_id,
+            first_key_id.clone(),
+            first_key_id,
+        ]);
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.exists, vec![false, true, true, true]);
+
+        let result = registry.check_keys_existence(vec![]);
+        assert!(result.success, "{}", result.error);
+        assert!(result.exists.is_empty());
+
+        // a pure read, not an access
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_hot_keys_cp(0, 10, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+    }
+
+    #[test]
+    fn get_records_in_window_returns_only_in_window() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id = register_key_checked(&mut registry, &key_kp, "label".to_string(), 10, 10, 0);
+
+        let mut issuers = vec![];
+        for timestamp in [100u64, 200, 300] {
+            let issuer_kp = KeyPair::generate_ed25519();
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                timestamp,
+                timestamp,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+            issuers.push(issuer_kp.get_peer_id().to_base58());
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(3);
+        let result = registry.get_records_in_window_cp(key_id.clone(), 150, 300, 300, cp.get());
+        assert!(result.success, "{}", result.error);
+        let mut issued_by: Vec<String> = result
+            .result
+            .into_iter()
+            .map(|r| r.metadata.issued_by)
+            .collect();
+        issued_by.sort();
+        let mut expected = issuers[1..].to_vec();
+        expected.sort();
+        assert_eq!(issued_by, expected);
+
+        let result = registry.get_records_in_window_cp(key_id, 300, 150, 300, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, InvalidTimeWindow(300, 150).to_string());
+    }
+
+    #[test]
+    fn get_stats_counts() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+
+        let cp = CPWrapper::new(&owner_peer_id, &host_id);
+        let result = registry.get_stats_cp(cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 0);
+        assert_eq!(result.oldest_key_timestamp, 0);
+
+        let key_id = register_key_checked(&mut registry, &owner_kp, "first".to_string(), 20, 20, 0);
+        register_key_checked(&mut registry, &owner_kp, "second".to_string(), 10, 10, 0);
+        let pin_cp = CPWrapper::new(&owner_peer_id, &host_id).add_timestamp_tetraplets(2);
+        let result = registry.set_key_pinned_cp(key_id.clone(), true, 20, pin_cp.get());
+        assert!(result.success, "{}", result.error);
+
+        // stored on the host and on another peer
+        for peer_kp in [&host_kp, &KeyPair::generate_ed25519()] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                peer_kp,
+                20,
+                20,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let result = registry.get_stats_cp(cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 2);
+        assert_eq!(result.records_count, 2);
+        assert_eq!(result.host_records_count, 1);
+        assert_eq!(result.pinned_keys_count, 1);
+        assert_eq!(result.oldest_key_timestamp, 10);
+    }
+
+    #[test]
+    fn get_records_flags_host_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let key_id = register_key_checked(&mut registry, &key_kp, "label".to_string(), 10, 10, 0);
+
+        for peer_kp in [&host_kp, &KeyPair::generate_ed25519()] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                peer_kp,
+                10,
+                10,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", &host_id).add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(key_id, 10, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 2);
+        let flags: Vec<bool> = result
+            .result
+            .iter()
+            .map(|r| r.metadata.peer_id == host_id)
+            .collect();
+        assert_eq!(result.is_host_record, flags);
+        assert_eq!(result.is_host_record.iter().filter(|h| **h).count(), 1);
+    }
+
+    #[test]
+    fn refresh_peer_records_keeps_key_from_clear_stale_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let host_id = "host_id";
+
+        let refreshed_key_id =
+            register_key_checked(&mut registry, &owner_kp, "refreshed".to_string(), 10, 10, 0);
+        let stale_key_id =
+            register_key_checked(&mut registry, &owner_kp, "stale".to_string(), 10, 10, 0);
+        for key_id in [&refreshed_key_id, &stale_key_id].iter() {
+            put_record_checked(
+                &mut registry,
+                key_id.to_string(),
+                &issuer_kp,
+                &host_kp,
+                10,
+                10,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        // refreshing without own records doesn't keep the key
+        let cp = CPWrapper::new("other_peer_id", host_id).add_timestamp_tetraplets(2);
+        let result = registry.refresh_peer_records_cp(stale_key_id.clone(), 90, 90, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 0);
+
+        let cp = CPWrapper::new(&issuer_peer_id, host_id).add_timestamp_tetraplets(2);
+        let result = registry.refresh_peer_records_cp(refreshed_key_id.clone(), 90, 90, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 1);
+
+        let cp = CPWrapper::new(host_id, host_id).add_timestamp_tetraplets(0);
+        let result = registry.clear_stale_keys_cp(100, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.keys_count, 1);
+        assert_eq!(result.records_count, 1);
+
+        let cp = CPWrapper::new("peer_id", host_id).add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(stale_key_id.clone(), 100, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, KeyNotExists(stale_key_id).to_string());
+
+        // signed fields are not changed by the refresh
+        let records = get_records(&mut registry, refreshed_key_id, 100);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.issued_by, issuer_peer_id);
+        assert_eq!(records[0].timestamp_created, 10);
+    }
+
+    #[test]
+    fn expiring_records_are_skipped_and_cleared() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let host_id = host_kp.get_peer_id().to_base58();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let key_id = register_key_checked(&mut registry, &key_kp, "label".to_string(), 10, 10, 0);
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &KeyPair::generate_ed25519(),
+            &host_kp,
+            10,
+            10,
+            "lasting".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let metadata = create_record_metadata(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            10,
+            "session".to_string(),
+            host_id.clone(),
+            vec![],
+            vec![],
+            vec![],
+        );
+        let bytes = registry.get_expiring_record_bytes(metadata.clone(), 10, 50);
+        let signature = host_kp.sign(&bytes).unwrap().to_vec().to_vec();
+
+        // expires_at is signed
+        let tampered = Record {
+            metadata: metadata.clone(),
+            timestamp_created: 10,
+            expires_at: 1000,
+            signature: signature.clone(),
+        };
+        let cp = CPWrapper::new(&issuer_peer_id, &host_id)
+            .add_batch_weight_tetraplets(1, 1)
+            .add_timestamp_tetraplets(2);
+        let weight = get_weight(issuer_peer_id.clone(), 0);
+        let result = registry.put_records_cp(vec![tampered], vec![weight.clone()], 10, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.stored_count, 0);
+        assert_eq!(result.errors.len(), 1);
+
+        let cp = CPWrapper::new(&issuer_peer_id, &host_id)
+            .add_weight_tetraplets(4)
+            .add_timestamp_tetraplets(5);
+        let result =
+            registry.put_expiring_record_cp(metadata, 10, 50, signature, weight, 10, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let values = |registry: &mut ServiceInterface, timestamp: u64| -> Vec<String> {
+            get_records(registry, key_id.clone(), timestamp)
+                .into_iter()
+                .map(|r| r.metadata.value)
+                .collect()
+        };
+        let mut stored = values(&mut registry, 49);
+        stored.sort();
+        assert_eq!(stored, vec!["lasting".to_string(), "session".to_string()]);
+        assert_eq!(values(&mut registry, 50), vec!["lasting".to_string()]);
+
+        // every reader skips the expired record before it is cleared
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_top_records_cp(key_id.clone(), 5, 50, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.value, "lasting".to_string());
+        let result = registry.get_records_by_authors_cp(
+            key_id.clone(),
+            vec![issuer_peer_id.clone()],
+            50,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert!(result.result.is_empty());
+        let result = registry.get_weight_histogram_cp(key_id.clone(), 10, 50, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.buckets.len(), 1);
+        assert_eq!(result.buckets[0].count, 1);
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_author_trust_cp(issuer_peer_id, 50, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.record_count, 0);
+        let cp = CPWrapper::new(&host_id, &host_id).add_timestamp_tetraplets(0);
+        let result = registry.export_all_cp(50, cp.get());
+        assert!(result.success, "{}", result.error);
+        let blob: serde_json::Value = serde_json::from_slice(&result.blob).unwrap();
+        assert_eq!(blob["records"].as_array().unwrap().len(), 1);
+
+        let cp = CPWrapper::new("peer_id", &host_id).add_timestamp_tetraplets(0);
+        let result = registry.clear_expired_cp(60, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count_records, 1);
+        assert_eq!(values(&mut registry, 60), vec!["lasting".to_string()]);
+    }
+
+    #[test]
+    fn get_global_feed_orders_across_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let first_key_id =
+            register_key_checked(&mut registry, &key_kp, "first".to_string(), 10, 10, 0);
+        let second_key_id =
+            register_key_checked(&mut registry, &key_kp, "second".to_string(), 10, 10, 0);
+
+        for (key_id, timestamp) in [
+            (&first_key_id, 10u64),
+            (&second_key_id, 30),
+            (&first_key_id, 20),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &host_kp,
+                timestamp,
+                timestamp,
+                timestamp.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(2);
+        let result = registry.get_global_feed_cp(0, 10, 40, cp.get());
+        assert!(result.success, "{}", result.error);
+        let feed: Vec<(String, u64)> = result
+            .result
+            .into_iter()
+            .map(|r| (r.metadata.key_id, r.timestamp_created))
+            .collect();
+        assert_eq!(
+            feed,
+            vec![
+                (second_key_id, 30),
+                (first_key_id.clone(), 20),
+                (first_key_id, 10)
+            ]
+        );
+
+        let result = registry.get_global_feed_cp(1, 1, 40, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].timestamp_created, 20);
+
+        let result = registry.get_global_feed_cp(0, 0, 40, cp.get());
+        assert!(!result.success);
+        assert_eq!(result.error, InvalidPageLimit.to_string());
+    }
+
+    #[test]
+    fn min_weight_for_put_gates_puts_and_republish() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        registry.set_min_weight_for_put(5);
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id = register_key_checked(&mut registry, &key_kp, "label".to_string(), 10, 10, 0);
+
+        let put = |registry: &mut ServiceInterface, issuer_kp: &KeyPair, weight: u32| {
+            put_record(
+                registry,
+                key_id.clone(),
+                issuer_kp,
+                &host_kp,
+                10,
+                10,
+                "value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                weight,
+            )
+        };
+        let result = put(&mut registry, &KeyPair::generate_ed25519(), 3);
+        assert!(!result.success);
+        assert_eq!(result.error, WeightTooLow(3, 5).to_string());
+        assert_eq!(result.error_code, 67);
+        let result = put(&mut registry, &KeyPair::generate_ed25519(), 5);
+        assert!(result.success, "{}", result.error);
+        // the host puts its own records regardless of the weight
+        let result = put(&mut registry, &host_kp, 1);
+        assert!(result.success, "{}", result.error);
+
+        let other_key_id =
+            register_key_checked(&mut registry, &key_kp, "other".to_string(), 10, 10, 0);
+        let issuers: Vec<KeyPair> = (0..2).map(|_| KeyPair::generate_ed25519()).collect();
+        let records: Vec<Record> = issuers
+            .iter()
+            .map(|kp| {
+                create_record(
+                    &mut registry,
+                    other_key_id.clone(),
+                    kp,
+                    &KeyPair::generate_ed25519(),
+                    10,
+                    "value".to_string(),
+                )
+            })
+            .collect();
+        let weights = vec![
+            get_weight(issuers[0].get_peer_id().to_base58(), 1),
+            get_weight(issuers[1].get_peer_id().to_base58(), 10),
+        ];
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 2)
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(records, weights, 10, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 1);
+        assert_eq!(result.skipped, 1);
+    }
+
+    #[test]
+    fn get_store_digest_is_order_independent() {
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let labels = ["first", "second"];
+        // (label, issuer) of each record
+        let records = [(0usize, 0usize), (1, 1), (0, 2)];
+
+        let fill = |order: &[usize]| -> ServiceInterface {
+            clear_env();
+            let mut registry = ServiceInterface::new();
+            for i in order.iter() {
+                let (label, issuer) = records[*i];
+                let key_id = register_key_checked(
+                    &mut registry,
+                    &key_kp,
+                    labels[label].to_string(),
+                    10,
+                    10,
+                    0,
+                );
+                put_record_checked(
+                    &mut registry,
+                    key_id,
+                    &issuers[issuer],
+                    &host_kp,
+                    10,
+                    10,
+                    "value".to_string(),
+                    vec![],
+                    vec![],
+                    vec![],
+                    0,
+                );
+            }
+            registry
+        };
+        let digest = |registry: &mut ServiceInterface| -> String {
+            let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(0);
+            let result = registry.get_store_digest_cp(10, cp.get());
+            assert!(result.success, "{}", result.error);
+            result.digest
+        };
+
+        let original = digest(&mut fill(&[0, 1, 2]));
+        assert!(!original.is_empty());
+        let mut registry = fill(&[2, 1, 0]);
+        assert_eq!(digest(&mut registry), original);
+
+        let key_id = register_key_checked(&mut registry, &key_kp, "first".to_string(), 10, 10, 0);
+        put_record_checked(
+            &mut registry,
+            key_id,
+            &issuers[0],
+            &host_kp,
+            11,
+            11,
+            "changed".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        assert_ne!(digest(&mut registry), original);
+    }
+
+    #[test]
+    fn record_signature_ignores_ids_order() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let issued_by = KeyPair::generate_ed25519().get_peer_id().to_base58();
+        let host_id = KeyPair::generate_ed25519().get_peer_id().to_base58();
+
+        let ids = |ids: &[&str]| -> Vec<String> { ids.iter().map(|id| id.to_string()).collect() };
+        let metadata = |registry: &mut ServiceInterface, relay_id: &[&str], service_id: &[&str]| {
+            registry.create_record_metadata(
+                "key_id".to_string(),
+                issued_by.clone(),
+                10,
+                "value".to_string(),
+                host_id.clone(),
+                ids(relay_id),
+                ids(service_id),
+                vec![],
+                vec![],
+            )
+        };
+        let metadata_bytes = |registry: &mut ServiceInterface, metadata: RecordMetadata| {
+            registry.get_record_metadata_bytes(
+                metadata.key_id,
+                metadata.issued_by,
+                metadata.timestamp_issued,
+                metadata.value,
+                metadata.peer_id,
+                metadata.relay_id,
+                metadata.service_id,
+                metadata.solution,
+            )
+        };
+
+        let shuffled = metadata(
+            &mut registry,
+            &["relay_b", "relay_a"],
+            &["service_b", "service_a", "service_b"],
+        );
+        // the same ids listed by another client
+        let sorted = metadata(
+            &mut registry,
+            &["relay_a", "relay_b"],
+            &["service_a", "service_b"],
+        );
+        let fewer = metadata(&mut registry, &["relay_a"], &["service_a", "service_b"]);
+
+        let bytes = metadata_bytes(&mut registry, shuffled.clone());
+        assert_eq!(bytes, metadata_bytes(&mut registry, sorted.clone()));
+        assert_ne!(bytes, metadata_bytes(&mut registry, fewer.clone()));
+
+        // the record signature embeds the metadata bytes, so it doesn't depend on the order either
+        let bytes = registry.get_record_bytes(shuffled, 10);
+        assert_eq!(bytes, registry.get_record_bytes(sorted, 10));
+        assert_ne!(bytes, registry.get_record_bytes(fewer, 10));
+    }
+
+    #[test]
+    fn republish_keys_keeps_newer_and_pinning() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let key_id =
+            register_key_checked(&mut registry, &owner_kp, "pinned".to_string(), 20, 20, 0);
+        let newer_key = get_key_metadata(&mut registry, key_id.clone(), 20);
+        let other_key_id =
+            register_key_checked(&mut registry, &owner_kp, "other".to_string(), 10, 20, 0);
+        let other_key = get_key_metadata(&mut registry, other_key_id.clone(), 20);
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        register_key_checked(&mut registry, &owner_kp, "pinned".to_string(), 10, 20, 0);
+        let older_key = get_key_metadata(&mut registry, key_id.clone(), 20);
+        let cp = CPWrapper::new(&owner_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.set_key_pinned_cp(key_id.clone(), true, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.republish_keys_cp(
+            vec![older_key.clone(), newer_key.clone(), other_key.clone()],
+            20,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 2);
+        assert_eq!(
+            get_key_metadata(&mut registry, key_id.clone(), 20),
+            newer_key
+        );
+        assert_eq!(get_key_metadata(&mut registry, other_key_id, 20), other_key);
+
+        // an older version doesn't replace the stored one
+        let result = registry.republish_keys_cp(vec![older_key], 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 0);
+
+        let result = registry.get_stats_cp(cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.pinned_keys_count, 1);
+
+        let mut forged = newer_key;
+        forged.timestamp_created = 30;
+        let result = registry.republish_keys_cp(vec![forged], 40, cp.get());
+        assert!(!result.success);
+        assert!(result.error.starts_with("Invalid signature for key_id"));
+    }
+
+    #[test]
+    fn require_relay_id_rejects_records_without_relay() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id = register_key_checked(&mut registry, &key_kp, "label".to_string(), 10, 10, 0);
+
+        let put = |registry: &mut ServiceInterface, relay_id: Vec<String>| {
+            put_record(
+                registry,
+                key_id.clone(),
+                &KeyPair::generate_ed25519(),
+                &host_kp,
+                10,
+                10,
+                "value".to_string(),
+                relay_id,
+                vec![],
+                vec![],
+                0,
+            )
+        };
+        let result = put(&mut registry, vec![]);
+        assert!(result.success, "{}", result.error);
+
+        registry.set_require_relay_id(true);
+        let result = put(&mut registry, vec![]);
+        assert!(!result.success);
+        assert_eq!(result.error, MissingRelayId(key_id.clone()).to_string());
+        let result = put(&mut registry, vec!["relay_id".to_string()]);
+        assert!(result.success, "{}", result.error);
+    }
+
+    #[test]
+    fn clear_record_removes_only_callers_value() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let other_kp = KeyPair::generate_ed25519();
+        let key_id = register_key_checked(&mut registry, &key_kp, "label".to_string(), 10, 10, 0);
+        for (kp, peer_kp, value) in [
+            (&issuer_kp, &host_kp, "value"),
+            (&issuer_kp, &KeyPair::generate_ed25519(), "other_value"),
+            (&other_kp, &host_kp, "value"),
+        ] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                kp,
+                peer_kp,
+                10,
+                10,
+                value.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new(&issuer_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.clear_record_cp(key_id.clone(), "value".to_string(), 20, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let hot_cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_hot_keys_cp(0, 20, hot_cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+
+        let mut remaining: Vec<(String, String)> = get_records(&mut registry, key_id.clone(), 20)
+            .into_iter()
+            .map(|r| (r.metadata.issued_by, r.metadata.value))
+            .collect();
+        remaining.sort();
+        let mut expected = vec![
+            (issuer_peer_id.clone(), "other_value".to_string()),
+            (other_kp.get_peer_id().to_base58(), "value".to_string()),
+        ];
+        expected.sort();
+        assert_eq!(remaining, expected);
+
+        let result = registry.clear_record_cp(key_id.clone(), "value".to_string(), 20, cp.get());
+        assert!(!result.success);
+        assert_eq!(
+            result.error,
+            RecordNotFound(key_id, issuer_peer_id, "value".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn record_access_follows_republished_and_deleted_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let other_peer_id = other_issuer_kp.get_peer_id().to_base58();
+        let key_id =
+            register_key_checked(&mut registry, &issuer_kp, "label".to_string(), 100, 100, 0);
+
+        let records = vec![
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                100,
+                "some_value".to_string(),
+            ),
+            create_record(
+                &mut registry,
+                key_id.clone(),
+                &other_issuer_kp,
+                &host_kp,
+                100,
+                "other_value".to_string(),
+            ),
+        ];
+        let weights = vec![
+            get_weight(issuer_peer_id.clone(), 0),
+            get_weight(other_peer_id.clone(), 0),
+        ];
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 2)
+            .add_timestamp_tetraplets(2);
+        let result = registry.republish_records_cp(records, weights, 100, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let accessed = |registry: &mut ServiceInterface| -> Vec<String> {
+            execute_service_db(
+                registry,
+                &f!("SELECT issued_by FROM {RECORD_ACCESS_TABLE_NAME} ORDER BY issued_by"),
+                &[],
+            )
+            .0
+        };
+        let mut expected = vec![issuer_peer_id.clone(), other_peer_id.clone()];
+        expected.sort();
+        assert_eq!(accessed(&mut registry), expected);
+
+        let cp = CPWrapper::new(&issuer_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.clear_record_cp(key_id, "some_value".to_string(), 100, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(accessed(&mut registry), vec![other_peer_id]);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(0);
+        let result = registry.clear_expired_cp(100 + DEFAULT_EXPIRED_AGE + 1, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.count_records, 1);
+        assert!(accessed(&mut registry).is_empty());
+    }
+
+    #[test]
+    fn get_empty_keys_lists_keys_without_records() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let owner_kp = KeyPair::generate_ed25519();
+        let owner_peer_id = owner_kp.get_peer_id().to_base58();
+        let empty_key_id =
+            register_key_checked(&mut registry, &owner_kp, "empty".to_string(), 10, 10, 0);
+        let pinned_key_id =
+            register_key_checked(&mut registry, &owner_kp, "pinned".to_string(), 11, 11, 0);
+        let used_key_id =
+            register_key_checked(&mut registry, &owner_kp, "used".to_string(), 12, 12, 0);
+        put_record_checked(
+            &mut registry,
+            used_key_id,
+            &KeyPair::generate_ed25519(),
+            &KeyPair::generate_ed25519(),
+            12,
+            12,
+            "value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        let cp = CPWrapper::new(&owner_peer_id, "host_id").add_timestamp_tetraplets(2);
+        let result = registry.set_key_pinned_cp(pinned_key_id.clone(), true, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_empty_keys_cp(0, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        let key_ids: Vec<String> = result.result.into_iter().map(|k| k.id).collect();
+        assert_eq!(key_ids, vec![empty_key_id, pinned_key_id]);
+        assert_eq!(result.pinned, vec![false, true]);
+
+        let result = registry.get_empty_keys_cp(1, 20, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+    }
+
+    #[test]
+    fn schema_version_recorded_on_fresh_storage() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        register_key_checked(&mut registry, &kp, "some_key".to_string(), 100, 100, 0);
+
+        let (version, _) = execute_service_db(
+            &mut registry,
+            &f!("SELECT version FROM {SCHEMA_VERSION_TABLE_NAME}"),
+            &[],
+        );
+        assert_eq!(version, vec![SCHEMA_VERSION.to_string()]);
+
+        // the fresh records table is already at the current schema
+        let (records_schema, _) = execute_service_db(
+            &mut registry,
+            "SELECT sql FROM sqlite_master WHERE name = ?1",
+            &[RECORDS_TABLE_NAME],
+        );
+        assert!(records_schema[0].contains("expires_at"));
+    }
+
+    #[test]
+    fn get_records_or_empty_existing_and_missing_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+        let key_id = register_key_checked(
+            &mut registry,
+            &kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "some_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_or_empty_cp(key_id, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert!(result.key_existed);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.value, "some_value");
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_or_empty_cp(
+            "missing_key_id".to_string(),
+            current_timestamp,
+            cp.get(),
+        );
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.error_code, 0);
+        assert!(!result.key_existed);
+        assert!(result.result.is_empty());
+    }
+
+    #[test]
+    fn put_record_if_better_rejects_weaker_record() {
+        fn put_if_better(
+            registry: &mut ServiceInterface,
+            key_id: &str,
+            issuer_kp: &KeyPair,
+            host_kp: &KeyPair,
+            timestamp_created: u64,
+            value: &str,
+            weight: u32,
+        ) -> bool {
+            let issuer_peer_id = issuer_kp.get_peer_id().to_base58();
+            let peer_id = host_kp.get_peer_id().to_base58();
+            let record_metadata = create_record_metadata(
+                registry,
+                key_id.to_string(),
+                issuer_kp,
+                timestamp_created,
+                value.to_string(),
+                peer_id.clone(),
+                vec![],
+                vec![],
+                vec![],
+            );
+            let signature = get_signed_record_bytes(
+                registry,
+                host_kp,
+                record_metadata.clone(),
+                timestamp_created,
+            );
+
+            let cp = CPWrapper::new(&issuer_peer_id, &peer_id)
+                .add_weight_tetraplets(3)
+                .add_timestamp_tetraplets(4);
+            let weight = get_weight(issuer_peer_id, weight);
+            let result = registry.put_record_if_better_cp(
+                record_metadata,
+                timestamp_created,
+                signature,
+                weight,
+                timestamp_created.max(200),
+                cp.get(),
+            );
+            assert!(result.success, "{}", result.error);
+            result.improved
+        }
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id = register_key_checked(&mut registry, &kp, "some_key".to_string(), 100, 100, 0);
+
+        assert!(put_if_better(
+            &mut registry,
+            &key_id,
+            &kp,
+            &host_kp,
+            150,
+            "first",
+            5
+        ));
+        // older and as old but lighter records don't improve the stored one
+        assert!(!put_if_better(
+            &mut registry,
+            &key_id,
+            &kp,
+            &host_kp,
+            120,
+            "older",
+            10
+        ));
+        assert!(!put_if_better(
+            &mut registry,
+            &key_id,
+            &kp,
+            &host_kp,
+            150,
+            "lighter",
+            1
+        ));
+        assert!(put_if_better(
+            &mut registry,
+            &key_id,
+            &kp,
+            &host_kp,
+            200,
+            "newer",
+            1
+        ));
+
+        let cp = CPWrapper::new("some_peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_cp(key_id, 200, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.result.len(), 1);
+        assert_eq!(result.result[0].metadata.value, "newer");
+    }
+
+    #[test]
+    fn republish_records_merged_returns_stored_set() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let current_timestamp = 100u64;
+
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "stored".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let records = vec![create_record(
+            &mut registry,
+            key_id.clone(),
+            &other_issuer_kp,
+            &host_kp,
+            current_timestamp,
+            "republished".to_string(),
+        )];
+        let weights = vec![get_weight(other_issuer_kp.get_peer_id().to_base58(), 0)];
+        let cp = CPWrapper::new("some_peer_id", "host_id")
+            .add_batch_weight_tetraplets(1, 1)
+            .add_timestamp_tetraplets(2);
+        let result =
+            registry.republish_records_merged_cp(records, weights, current_timestamp, cp.get());
+        assert!(result.success, "{}", result.error);
+        assert_eq!(result.updated, 1);
+
+        let mut merged: Vec<String> = result
+            .result
+            .iter()
+            .map(|r| r.metadata.value.clone())
+            .collect();
+        merged.sort();
+        assert_eq!(merged, vec!["republished", "stored"]);
+
+        let stored = get_records(&mut registry, key_id, current_timestamp);
+        assert_eq!(stored.len(), result.result.len());
+    }
+
+    #[test]
+    fn max_authors_per_key_rejects_new_author() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let authors: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+        let key_id = register_key_checked(
+            &mut registry,
+            &authors[0],
+            "some_key".to_string(),
+            100,
+            100,
+            0,
+        );
+        registry.set_max_authors_per_key(2);
+
+        for author in &authors[..2] {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                author,
+                &host_kp,
+                100,
+                100,
+                "some_value".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let result = put_record(
+            &mut registry,
+            key_id.clone(),
+            &authors[2],
+            &host_kp,
+            100,
+            100,
+            "new_author_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        assert!(!result.success);
+        assert_eq!(result.error, TooManyAuthors(key_id.clone(), 2).to_string());
+
+        // existing authors can keep writing
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &authors[0],
+            &host_kp,
+            101,
+            101,
+            "updated_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        assert_eq!(get_records(&mut registry, key_id.clone(), 101).len(), 2);
+
+        // the author with only expired records no longer takes a slot
+        let current_timestamp = 100 + DEFAULT_EXPIRED_AGE;
+        put_record_checked(
+            &mut registry,
+            key_id.clone(),
+            &authors[2],
+            &host_kp,
+            current_timestamp,
+            current_timestamp,
+            "new_author_value".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        assert_eq!(
+            get_records(&mut registry, key_id, current_timestamp).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn rewritten_issued_by_fails_verification() {
+        fn republish(registry: &mut ServiceInterface, record: Record) -> i64 {
+            let weights = vec![get_weight(record.metadata.issued_by.clone(), 0)];
+            let cp = CPWrapper::new("some_peer_id", "host_id")
+                .add_batch_weight_tetraplets(1, 1)
+                .add_timestamp_tetraplets(2);
+            registry
+                .republish_records_cp(vec![record], weights, 100, cp.get())
+                .error_code
+        }
+
+        /// Any verification error, only the variant of the service error wrapping it is compared
+        fn verification_error() -> VerificationError {
+            let signature = Signature::from_bytes(KeyFormat::Ed25519, vec![0; 64]);
+            KeyPair::generate_ed25519()
+                .public()
+                .verify(b"", &signature)
+                .unwrap_err()
+        }
+
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let relay_kp = KeyPair::generate_ed25519();
+        let issued_by = issuer_kp.get_peer_id().to_base58();
+        let peer_id = host_kp.get_peer_id().to_base58();
+        let current_timestamp = 100u64;
+        let key_id = register_key_checked(
+            &mut registry,
+            &issuer_kp,
+            "some_key".to_string(),
+            current_timestamp,
+            current_timestamp,
+            0,
+        );
+
+        let record = create_record(
+            &mut registry,
+            key_id.clone(),
+            &issuer_kp,
+            &host_kp,
+            current_timestamp,
+            "some_value".to_string(),
+        );
+
+        // metadata re-signed by the relay keeps peer_id's signature over the original issued_by
+        let mut forged = create_record(
+            &mut registry,
+            key_id.clone(),
+            &relay_kp,
+            &host_kp,
+            current_timestamp,
+            "some_value".to_string(),
+        );
+        forged.signature = record.signature.clone();
+
+        // rejected with the default config as well as with legacy signatures enabled
+        for legacy_signatures in [false, true] {
+            if legacy_signatures {
+                registry.set_legacy_signatures(true);
+            }
+
+            // issued_by rewritten alone breaks the issuer signature
+            let mut rewritten = record.clone();
+            rewritten.metadata.issued_by = relay_kp.get_peer_id().to_base58();
+            assert_eq!(
+                republish(&mut registry, rewritten.clone()),
+                InvalidRecordMetadataSignature(
+                    key_id.clone(),
+                    rewritten.metadata.issued_by,
+                    verification_error()
+                )
+                .code()
+            );
+
+            assert_eq!(
+                republish(&mut registry, forged.clone()),
+                InvalidRecordSignature(key_id.clone(), peer_id.clone(), verification_error())
+                    .code()
+            );
+        }
+
+        assert_eq!(republish(&mut registry, record), 0);
+        let records = get_records(&mut registry, key_id, current_timestamp);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].metadata.issued_by, issued_by);
+    }
+
+    #[test]
+    fn get_records_by_issuer_pages_across_keys() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let issuer_kp = KeyPair::generate_ed25519();
+        let other_issuer_kp = KeyPair::generate_ed25519();
+        let mut key_ids: Vec<String> = (0..3)
+            .map(|i| register_key_checked(&mut registry, &key_kp, i.to_string(), 10, 10, 0))
+            .collect();
+
+        for key_id in &key_ids {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                &issuer_kp,
+                &host_kp,
+                10,
+                10,
+                "spam".to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+        put_record_checked(
+            &mut registry,
+            key_ids[0].clone(),
+            &other_issuer_kp,
+            &host_kp,
+            10,
+            10,
+            "other".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+
+        let issued_by = issuer_kp.get_peer_id().to_base58();
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let first_page = registry.get_records_by_issuer_cp(issued_by.clone(), 10, 0, 2, cp.get());
+        assert!(first_page.success, "{}", first_page.error);
+        let second_page = registry.get_records_by_issuer_cp(issued_by.clone(), 10, 2, 2, cp.get());
+        assert!(second_page.success, "{}", second_page.error);
+        assert_eq!(first_page.result.len(), 2);
+        assert_eq!(second_page.result.len(), 1);
+
+        let mut found: Vec<String> = first_page
+            .result
+            .into_iter()
+            .chain(second_page.result)
+            .map(|r| {
+                assert_eq!(r.metadata.issued_by, issued_by);
+                r.metadata.key_id
+            })
+            .collect();
+        found.sort();
+        key_ids.sort();
+        assert_eq!(found, key_ids);
+
+        let result = registry.get_records_by_issuer_cp(issued_by, 10, 0, 0, cp.get());
+        assert_eq!(result.error, InvalidPageLimit.to_string());
+
+        let (indexes, _) = execute_service_db(
+            &mut registry,
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'records_issued_by_index'",
+            &[],
+        );
+        assert_eq!(indexes.len(), 1);
+    }
+
+    #[test]
+    fn get_records_by_insertion_follows_put_sequence() {
+        clear_env();
+        let mut registry = ServiceInterface::new();
+        let key_kp = KeyPair::generate_ed25519();
+        let host_kp = KeyPair::generate_ed25519();
+        let key_id = register_key_checked(&mut registry, &key_kp, "some_key".to_string(), 5, 5, 0);
+        let issuers: Vec<KeyPair> = (0..3).map(|_| KeyPair::generate_ed25519()).collect();
+
+        // signed timestamps don't follow the put sequence
+        for (issuer, timestamp) in issuers
+            .iter()
+            .zip([30u64, 10, 20])
+            .chain([(&issuers[0], 40)])
+        {
+            put_record_checked(
+                &mut registry,
+                key_id.clone(),
+                issuer,
+                &host_kp,
+                timestamp,
+                timestamp,
+                timestamp.to_string(),
+                vec![],
+                vec![],
+                vec![],
+                0,
+            );
+        }
+
+        let cp = CPWrapper::new("peer_id", "host_id").add_timestamp_tetraplets(1);
+        let result = registry.get_records_by_insertion_cp(key_id.clone(), 40, cp.get());
+        assert!(result.success, "{}", result.error);
+        let values: Vec<String> = result
+            .result
+            .into_iter()
+            .map(|r| r.metadata.value)
+            .collect();
+        // the rewritten record moves to the end
+        assert_eq!(values, vec!["10", "20", "40"]);
+
+        // seq of the last written record isn't handed out again after it is deleted
+        let (_, deleted) = execute_service_db(
+            &mut registry,
+            &f!("DELETE FROM {RECORDS_TABLE_NAME} WHERE seq = (SELECT MAX(seq) FROM {RECORDS_TABLE_NAME})"),
+            &[],
+        );
+        assert_eq!(deleted, 1);
+        put_record_checked(
+            &mut registry,
+            key_id,
+            &KeyPair::generate_ed25519(),
+            &host_kp,
+            50,
+            50,
+            "50".to_string(),
+            vec![],
+            vec![],
+            vec![],
+            0,
+        );
+        let (seqs, _) = execute_service_db(
+            &mut registry,
+            &f!("SELECT seq FROM {RECORDS_TABLE_NAME} ORDER BY seq"),
+            &[],
+        );
+        assert_eq!(seqs, vec!["2", "3", "5"]);
+    }
 }
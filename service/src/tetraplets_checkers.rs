@@ -59,3 +59,22 @@ pub(crate) fn check_weight_tetraplets(
         .then_some(())
         .ok_or_else(|| InvalidWeightTetraplet(format!("{:?}", tetraplet)))
 }
+
+/// Check tetraplets of a batch call once instead of per item:
+/// `timestamp_arg_number` is the index of `current_timestamp_sec` argument, and `weights_arg_number`,
+/// if the call has weights, is the index of the weights vector with `items_count` elements
+pub(crate) fn check_batch_tetraplets(
+    call_parameters: &CallParameters,
+    timestamp_arg_number: usize,
+    weights_arg_number: Option<usize>,
+    items_count: usize,
+) -> Result<(), ServiceError> {
+    check_timestamp_tetraplets(call_parameters, timestamp_arg_number)?;
+    if let Some(arg_number) = weights_arg_number {
+        for index in 0..items_count {
+            check_weight_tetraplets(call_parameters, arg_number, index)?;
+        }
+    }
+
+    Ok(())
+}
@@ -13,8 +13,9 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use crate::defaults::TOMBSTONE_GRACE_SEC;
 use crate::error::ServiceError;
-use crate::results::{GetTombstonesResult, RegistryResult};
+use crate::results::{GetTombstonesResult, PurgeResult, RegistryResult};
 use crate::storage_impl::get_storage;
 use crate::tetraplets_checkers::check_timestamp_tetraplets;
 use crate::tombstone::Tombstone;
@@ -116,3 +117,15 @@ pub fn republish_tombstones(
     })
     .into()
 }
+
+/// Remove tombstones older than `TOMBSTONE_GRACE_SEC`, younger ones are kept to prevent resurrection
+#[marine]
+pub fn purge_tombstones(current_timestamp_sec: u64) -> PurgeResult {
+    wrapped_try(|| {
+        let call_parameters = marine_rs_sdk::get_call_parameters();
+        check_timestamp_tetraplets(&call_parameters, 0)?;
+        let grace_timestamp = current_timestamp_sec.saturating_sub(TOMBSTONE_GRACE_SEC);
+        get_storage()?.purge_tombstones(grace_timestamp)
+    })
+    .into()
+}
@@ -43,7 +43,7 @@ impl Storage {
         )?;
         let mut statement = self.connection.prepare(f!(
             "INSERT OR REPLACE INTO {RECORDS_TABLE_NAME} VALUES (?, ?, ?, ?, ?, ?, ?, \
-            NULL, NULL, NULL, NULL, NULL, NULL);"
+            NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL);"
         ))?;
 
         let is_tombstoned = 1;
@@ -90,4 +90,15 @@ impl Storage {
         ))?;
         Ok(self.connection.changes() as u64)
     }
+
+    /// Remove tombstones issued before `grace_timestamp`, records they shadow are expired by now
+    pub fn purge_tombstones(&self, grace_timestamp: u64) -> Result<u64, ServiceError> {
+        let mut statement = self.connection.prepare(f!(
+            "DELETE FROM {RECORDS_TABLE_NAME} WHERE timestamp_issued < ? AND is_tombstoned = 1"
+        ))?;
+        statement.bind(1, &Value::Integer(grace_timestamp as i64))?;
+        statement.next().map(drop)?;
+
+        Ok(self.connection.changes() as u64)
+    }
 }